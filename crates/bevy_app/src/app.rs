@@ -31,6 +31,17 @@ pub struct App {
     pub executor: ParallelExecutor,
     pub startup_schedule: Schedule,
     pub startup_executor: ParallelExecutor,
+    sub_apps: Vec<SubApp>,
+}
+
+/// Moves data from an app to one of its sub-apps before the sub-app is updated
+type ExtractFn = Box<dyn FnMut(&mut App, &mut App)>;
+
+/// An [App] with its own [World], [Resources] and [Schedule] that is updated by another app
+struct SubApp {
+    name: &'static str,
+    app: App,
+    extract: ExtractFn,
 }
 
 impl Default for App {
@@ -43,6 +54,7 @@ impl Default for App {
             startup_schedule: Default::default(),
             startup_executor: ParallelExecutor::without_tracker_clears(),
             runner: Box::new(run_once),
+            sub_apps: Vec::new(),
         }
     }
 }
@@ -57,11 +69,19 @@ impl App {
         AppBuilder::default()
     }
 
+    /// Runs the schedule, then updates the sub-apps in the order they were added
     pub fn update(&mut self) {
         self.schedule
             .initialize(&mut self.world, &mut self.resources);
         self.executor
             .run(&mut self.schedule, &mut self.world, &mut self.resources);
+
+        let mut sub_apps = std::mem::take(&mut self.sub_apps);
+        for sub_app in sub_apps.iter_mut() {
+            (sub_app.extract)(self, &mut sub_app.app);
+            sub_app.app.update();
+        }
+        self.sub_apps = sub_apps;
     }
 
     pub fn initialize(&mut self) {
@@ -73,6 +93,46 @@ impl App {
             &mut self.world,
             &mut self.resources,
         );
+        for sub_app in self.sub_apps.iter_mut() {
+            sub_app.app.executor.initialize(&mut sub_app.app.resources);
+            sub_app.app.initialize();
+        }
+    }
+
+    /// Adds `app` as a sub-app named `name`. It is updated after each update of this app, right
+    /// after `extract` is called with this app and the sub-app to move data between their worlds.
+    /// The runner of `app` is not used.
+    ///
+    /// # Panics
+    /// Panics if there already is a sub-app named `name`
+    pub fn add_sub_app(
+        &mut self,
+        name: &'static str,
+        app: App,
+        extract: impl FnMut(&mut App, &mut App) + 'static,
+    ) {
+        if self.sub_app(name).is_some() {
+            panic!("Sub-app {} already exists.", name);
+        }
+        self.sub_apps.push(SubApp {
+            name,
+            app,
+            extract: Box::new(extract),
+        });
+    }
+
+    pub fn sub_app(&self, name: &str) -> Option<&App> {
+        self.sub_apps
+            .iter()
+            .find(|sub_app| sub_app.name == name)
+            .map(|sub_app| &sub_app.app)
+    }
+
+    pub fn sub_app_mut(&mut self, name: &str) -> Option<&mut App> {
+        self.sub_apps
+            .iter_mut()
+            .find(|sub_app| sub_app.name == name)
+            .map(|sub_app| &mut sub_app.app)
     }
 
     pub fn run(mut self) {
@@ -85,3 +145,43 @@ impl App {
 /// An event that indicates the app should exit. This will fully exit the app process.
 #[derive(Debug, Clone)]
 pub struct AppExit;
+
+#[cfg(test)]
+mod tests {
+    use super::App;
+    use bevy_ecs::{IntoQuerySystem, Res, ResMut};
+
+    struct Tick(u32);
+    struct Seen(Vec<u32>);
+
+    fn tick(mut tick: ResMut<Tick>) {
+        tick.0 += 1;
+    }
+
+    fn record(tick: Res<Tick>, mut seen: ResMut<Seen>) {
+        seen.0.push(tick.0 * 10);
+    }
+
+    #[test]
+    fn sub_apps_update_after_extraction() {
+        let mut view = App::build();
+        view.add_resource(Tick(0))
+            .add_resource(Seen(Vec::new()))
+            .add_system(record.system());
+
+        let mut app = App::build();
+        app.add_resource(Tick(0))
+            .add_system(tick.system())
+            .add_sub_app("view", std::mem::take(&mut view.app), |app, view| {
+                let tick = app.resources.get::<Tick>().unwrap().0;
+                view.resources.get_mut::<Tick>().unwrap().0 = tick;
+            });
+
+        let mut app = std::mem::take(&mut app.app);
+        app.initialize();
+        app.update();
+        app.update();
+        let view = app.sub_app("view").unwrap();
+        assert_eq!(view.resources.get::<Seen>().unwrap().0, vec![10, 20]);
+    }
+}
@@ -2,20 +2,25 @@ use crate::{
     app::{App, AppExit},
     event::Events,
     plugin::Plugin,
+    plugin_group::{PluginGroup, PluginGroupBuilder},
     stage, startup_stage,
 };
-use bevy_ecs::{FromResources, IntoQuerySystem, Resources, System, World};
+use bevy_ecs::{
+    CommandError, CommandErrorPolicy, CommandErrors, FromResources, IntoQuerySystem,
+    IntoSystemDescriptor, IntoThreadLocalSystem, Relation, ResMut, Resources, RunCriteria, State,
+    StateSystem, System, World,
+};
+use bevy_utils::HashSet;
 
 /// Configure [App]s using the builder pattern
 pub struct AppBuilder {
     pub app: App,
+    plugin_names: HashSet<String>,
 }
 
 impl Default for AppBuilder {
     fn default() -> Self {
-        let mut app_builder = AppBuilder {
-            app: App::default(),
-        };
+        let mut app_builder = AppBuilder::empty();
 
         app_builder.add_default_stages();
         app_builder.add_event::<AppExit>();
@@ -27,6 +32,7 @@ impl AppBuilder {
     pub fn empty() -> AppBuilder {
         AppBuilder {
             app: App::default(),
+            plugin_names: HashSet::default(),
         }
     }
 
@@ -94,7 +100,7 @@ impl AppBuilder {
         self
     }
 
-    pub fn add_system(&mut self, system: Box<dyn System>) -> &mut Self {
+    pub fn add_system(&mut self, system: impl IntoSystemDescriptor) -> &mut Self {
         self.add_system_to_stage(stage::UPDATE, system)
     }
 
@@ -121,7 +127,7 @@ impl AppBuilder {
     pub fn add_startup_system_to_stage(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl IntoSystemDescriptor,
     ) -> &mut Self {
         self.app
             .startup_schedule
@@ -142,7 +148,7 @@ impl AppBuilder {
         self
     }
 
-    pub fn add_startup_system(&mut self, system: Box<dyn System>) -> &mut Self {
+    pub fn add_startup_system(&mut self, system: impl IntoSystemDescriptor) -> &mut Self {
         self.app
             .startup_schedule
             .add_system_to_stage(startup_stage::STARTUP, system);
@@ -185,7 +191,7 @@ impl AppBuilder {
     pub fn add_system_to_stage(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl IntoSystemDescriptor,
     ) -> &mut Self {
         self.app.schedule.add_system_to_stage(stage_name, system);
         self
@@ -194,7 +200,7 @@ impl AppBuilder {
     pub fn add_system_to_stage_front(
         &mut self,
         stage_name: &'static str,
-        system: Box<dyn System>,
+        system: impl IntoSystemDescriptor,
     ) -> &mut Self {
         self.app
             .schedule
@@ -202,6 +208,31 @@ impl AppBuilder {
         self
     }
 
+    /// Adds a system that only runs when `run_criteria` allows it
+    pub fn add_system_to_stage_with_run_criteria(
+        &mut self,
+        stage_name: &'static str,
+        system: impl IntoSystemDescriptor,
+        run_criteria: RunCriteria,
+    ) -> &mut Self {
+        self.app
+            .schedule
+            .add_system_to_stage_with_run_criteria(stage_name, system, run_criteria);
+        self
+    }
+
+    /// Makes the whole stage only run when `run_criteria` allows it
+    pub fn set_stage_run_criteria(
+        &mut self,
+        stage_name: &'static str,
+        run_criteria: RunCriteria,
+    ) -> &mut Self {
+        self.app
+            .schedule
+            .set_stage_run_criteria(stage_name, run_criteria);
+        self
+    }
+
     pub fn add_systems_to_stage(
         &mut self,
         stage_name: &'static str,
@@ -221,6 +252,78 @@ impl AppBuilder {
             .add_system_to_stage(stage::EVENT, Events::<T>::update_system.system())
     }
 
+    /// Sends the [CommandError]s of failed commands as events, instead of logging them
+    pub fn add_command_error_events(&mut self) -> &mut Self {
+        self.add_resource(CommandErrorPolicy::Collect)
+            .add_resource(CommandErrors::default())
+            .add_event::<CommandError>()
+            .add_system_to_stage(stage::PRE_EVENT, send_command_error_events.system())
+    }
+
+    /// Registers relations of kind `K` in the app's [World], so that their
+    /// [RelationSources](bevy_ecs::RelationSources) are maintained. Relations retargeted by
+    /// [MapEntities](bevy_ecs::MapEntities), like when a scene is spawned, are caught up with before
+    /// the update stage.
+    pub fn add_relation<K>(&mut self) -> &mut Self
+    where
+        K: 'static,
+    {
+        Relation::<K>::register(&mut self.app.world);
+        self.add_system_to_stage(
+            stage::PRE_UPDATE,
+            Relation::<K>::maintenance_system.thread_local_system(),
+        )
+    }
+
+    /// Adds a [State] resource with the given initial value. Queued state transitions are applied at
+    /// the start of each update, before any systems registered with `on_state_*` run.
+    pub fn add_state<T>(&mut self, initial: T) -> &mut Self
+    where
+        T: Clone + Eq + Send + Sync + 'static,
+    {
+        self.add_resource(State::new(initial))
+            .add_system_to_stage_front(stage::FIRST, State::<T>::transition_system.system())
+    }
+
+    /// Adds a system that runs once in the update `state` becomes active
+    pub fn on_state_enter<T>(
+        &mut self,
+        stage_name: &'static str,
+        state: T,
+        system: Box<dyn System>,
+    ) -> &mut Self
+    where
+        T: Clone + Eq + Send + Sync + 'static,
+    {
+        self.add_system_to_stage(stage_name, StateSystem::on_enter(state, system))
+    }
+
+    /// Adds a system that runs every update while `state` is active
+    pub fn on_state_update<T>(
+        &mut self,
+        stage_name: &'static str,
+        state: T,
+        system: Box<dyn System>,
+    ) -> &mut Self
+    where
+        T: Clone + Eq + Send + Sync + 'static,
+    {
+        self.add_system_to_stage(stage_name, StateSystem::on_update(state, system))
+    }
+
+    /// Adds a system that runs once in the update `state` stops being active
+    pub fn on_state_exit<T>(
+        &mut self,
+        stage_name: &'static str,
+        state: T,
+        system: Box<dyn System>,
+    ) -> &mut Self
+    where
+        T: Clone + Eq + Send + Sync + 'static,
+    {
+        self.add_system_to_stage(stage_name, StateSystem::on_exit(state, system))
+    }
+
     /// Adds a resource to the current [App] and overwrites any resource previously added of the same type.
     pub fn add_resource<T>(&mut self, resource: T) -> &mut Self
     where
@@ -258,17 +361,84 @@ impl AppBuilder {
         self
     }
 
+    /// Adds a sub-app with its own [World], [Resources] and schedule, see [App::add_sub_app]
+    pub fn add_sub_app(
+        &mut self,
+        name: &'static str,
+        app: App,
+        extract: impl FnMut(&mut App, &mut App) + 'static,
+    ) -> &mut Self {
+        self.app.add_sub_app(name, app, extract);
+        self
+    }
+
+    /// # Panics
+    /// Panics if there is no sub-app named `name`
+    pub fn sub_app_mut(&mut self, name: &str) -> &mut App {
+        self.app
+            .sub_app_mut(name)
+            .unwrap_or_else(|| panic!("Sub-app {} does not exist.", name))
+    }
+
     pub fn set_runner(&mut self, run_fn: impl Fn(App) + 'static) -> &mut Self {
         self.app.runner = Box::new(run_fn);
         self
     }
 
+    /// Builds `plugin`, unless a plugin with the same name was already added. Adding a plugin twice
+    /// is logged as an error.
     pub fn add_plugin<T>(&mut self, plugin: T) -> &mut Self
     where
         T: Plugin,
     {
-        log::debug!("added plugin: {}", plugin.name());
-        plugin.build(self);
+        self.build_plugin(&plugin)
+    }
+
+    /// Same as [AppBuilder::add_plugin], for plugins that are only known at runtime
+    pub fn add_boxed_plugin(&mut self, plugin: Box<dyn Plugin>) -> &mut Self {
+        self.build_plugin(&*plugin)
+    }
+
+    fn build_plugin(&mut self, plugin: &dyn Plugin) -> &mut Self {
+        if self.plugin_names.insert(plugin.name().to_string()) {
+            log::debug!("added plugin: {}", plugin.name());
+            plugin.build(self);
+        } else {
+            log::error!("plugin {} was already added, skipping it", plugin.name());
+        }
         self
     }
+
+    /// Whether a plugin named `name` was added. Plugins are named after their type by default.
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugin_names.contains(name)
+    }
+
+    /// Adds the plugins of `group`
+    pub fn add_plugins<T: PluginGroup>(&mut self, group: T) -> &mut Self {
+        self.add_plugins_with(group, |_| {})
+    }
+
+    /// Adds the plugins of `group` after `func` made changes to them, like disabling or replacing
+    /// some of them
+    pub fn add_plugins_with<T, F>(&mut self, mut group: T, func: F) -> &mut Self
+    where
+        T: PluginGroup,
+        F: FnOnce(&mut PluginGroupBuilder),
+    {
+        let mut plugin_group_builder = PluginGroupBuilder::default();
+        group.build(&mut plugin_group_builder);
+        func(&mut plugin_group_builder);
+        plugin_group_builder.finish(self);
+        self
+    }
+}
+
+fn send_command_error_events(
+    mut errors: ResMut<CommandErrors>,
+    mut events: ResMut<Events<CommandError>>,
+) {
+    for error in errors.drain() {
+        events.send(error);
+    }
 }
@@ -7,6 +7,7 @@ mod app;
 mod app_builder;
 mod event;
 mod plugin;
+mod plugin_group;
 mod schedule_runner;
 
 pub use app::*;
@@ -14,6 +15,7 @@ pub use app_builder::*;
 pub use bevy_derive::DynamicPlugin;
 pub use event::*;
 pub use plugin::*;
+pub use plugin_group::*;
 pub use schedule_runner::*;
 
 pub mod prelude {
@@ -22,6 +24,7 @@ pub mod prelude {
         app_builder::AppBuilder,
         event::{EventReader, Events},
         plugin::Plugin,
+        plugin_group::{PluginGroup, PluginGroupBuilder},
         stage, DynamicPlugin,
     };
 }
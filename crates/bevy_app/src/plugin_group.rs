@@ -0,0 +1,167 @@
+use crate::{AppBuilder, Plugin};
+
+/// A named sequence of [Plugin]s that can be added to an app at once
+///
+/// Its plugins can be disabled, replaced or complemented before they are built, with
+/// [AppBuilder::add_plugins_with].
+pub trait PluginGroup {
+    fn build(&mut self, group: &mut PluginGroupBuilder);
+}
+
+struct PluginEntry {
+    type_name: &'static str,
+    plugin: Box<dyn Plugin>,
+    enabled: bool,
+}
+
+/// The plugins of a [PluginGroup], in the order they will be built. Plugins are referred to by type.
+#[derive(Default)]
+pub struct PluginGroupBuilder {
+    plugins: Vec<PluginEntry>,
+}
+
+impl PluginGroupBuilder {
+    /// Adds `plugin` after the other plugins
+    pub fn add<T: Plugin>(&mut self, plugin: T) -> &mut Self {
+        let index = self.plugins.len();
+        self.insert(index, plugin)
+    }
+
+    /// Adds `plugin` right before the plugin of type `Target`
+    pub fn add_before<Target: Plugin, T: Plugin>(&mut self, plugin: T) -> &mut Self {
+        let index = self.index_of::<Target>();
+        self.insert(index, plugin)
+    }
+
+    /// Adds `plugin` right after the plugin of type `Target`
+    pub fn add_after<Target: Plugin, T: Plugin>(&mut self, plugin: T) -> &mut Self {
+        let index = self.index_of::<Target>() + 1;
+        self.insert(index, plugin)
+    }
+
+    /// Puts `plugin` in the place of the plugin of type `Target`. It is enabled even if `Target`
+    /// was disabled.
+    pub fn replace<Target: Plugin, T: Plugin>(&mut self, plugin: T) -> &mut Self {
+        let index = self.index_of::<Target>();
+        self.plugins[index] = PluginEntry {
+            type_name: std::any::type_name::<T>(),
+            plugin: Box::new(plugin),
+            enabled: true,
+        };
+        self
+    }
+
+    /// Keeps the plugin of type `T` from being built
+    pub fn disable<T: Plugin>(&mut self) -> &mut Self {
+        let index = self.index_of::<T>();
+        self.plugins[index].enabled = false;
+        self
+    }
+
+    pub fn enable<T: Plugin>(&mut self) -> &mut Self {
+        let index = self.index_of::<T>();
+        self.plugins[index].enabled = true;
+        self
+    }
+
+    pub fn contains<T: Plugin>(&self) -> bool {
+        self.position::<T>().is_some()
+    }
+
+    /// Builds the enabled plugins in order
+    pub fn finish(self, app: &mut AppBuilder) {
+        for entry in self.plugins {
+            if entry.enabled {
+                app.add_boxed_plugin(entry.plugin);
+            } else {
+                log::debug!("skipped disabled plugin: {}", entry.plugin.name());
+            }
+        }
+    }
+
+    fn insert<T: Plugin>(&mut self, index: usize, plugin: T) -> &mut Self {
+        self.plugins.insert(
+            index,
+            PluginEntry {
+                type_name: std::any::type_name::<T>(),
+                plugin: Box::new(plugin),
+                enabled: true,
+            },
+        );
+        self
+    }
+
+    fn position<T: Plugin>(&self) -> Option<usize> {
+        let type_name = std::any::type_name::<T>();
+        self.plugins
+            .iter()
+            .position(|entry| entry.type_name == type_name)
+    }
+
+    fn index_of<T: Plugin>(&self) -> usize {
+        self.position::<T>().unwrap_or_else(|| {
+            panic!(
+                "Plugin {} is not in the plugin group",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PluginGroup, PluginGroupBuilder};
+    use crate::{AppBuilder, Plugin};
+
+    #[derive(Default)]
+    struct Built(Vec<&'static str>);
+
+    macro_rules! test_plugin {
+        ($name: ident) => {
+            struct $name;
+
+            impl Plugin for $name {
+                fn build(&self, app: &mut AppBuilder) {
+                    app.resources_mut()
+                        .get_mut::<Built>()
+                        .unwrap()
+                        .0
+                        .push(stringify!($name));
+                }
+            }
+        };
+    }
+
+    test_plugin!(A);
+    test_plugin!(B);
+    test_plugin!(C);
+    test_plugin!(D);
+    test_plugin!(E);
+
+    struct Group;
+
+    impl PluginGroup for Group {
+        fn build(&mut self, group: &mut PluginGroupBuilder) {
+            group.add(A).add(B).add(C);
+        }
+    }
+
+    #[test]
+    fn plugin_group_builder() {
+        let mut app = AppBuilder::empty();
+        app.add_resource(Built::default());
+        app.add_plugins_with(Group, |group| {
+            group
+                .disable::<A>()
+                .replace::<B, D>(D)
+                .add_before::<C, E>(E)
+                .add_after::<C, A>(A);
+        });
+        // duplicates are skipped
+        app.add_plugin(C).add_plugins(Group);
+        assert_eq!(
+            app.resources().get::<Built>().unwrap().0,
+            vec!["D", "E", "C", "A", "B"]
+        );
+    }
+}
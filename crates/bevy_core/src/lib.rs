@@ -2,22 +2,35 @@ mod bytes;
 mod float_ord;
 mod label;
 mod task_pool_options;
+mod test_harness;
 mod time;
 
 pub use bytes::*;
 pub use float_ord::*;
 pub use label::*;
 pub use task_pool_options::DefaultTaskPoolOptions;
+pub use test_harness::*;
 pub use time::*;
 
+/// The names of core stages in an App Schedule
+pub mod stage {
+    /// Name of the stage that runs its systems at the rate set by the [FixedTimestep](crate::FixedTimestep)
+    /// resource. Runs before UPDATE.
+    pub const FIXED_UPDATE: &str = "fixed_update";
+}
+
 pub mod prelude {
-    pub use crate::{DefaultTaskPoolOptions, EntityLabels, Labels, Time, Timer};
+    pub use crate::{
+        DefaultTaskPoolOptions, EntityLabels, FixedTimestep, Labels, ManualTimer, Stopwatch, Time,
+        TimeDomain, Timer,
+    };
 }
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 use bevy_math::{Mat3, Mat4, Quat, Vec2, Vec3};
 use bevy_type_registry::RegisterType;
+use std::time::Duration;
 
 /// Adds core functionality to Apps.
 #[derive(Default)]
@@ -31,6 +44,13 @@ impl Plugin for CorePlugin {
             .unwrap_or_else(DefaultTaskPoolOptions::default)
             .create_default_pools(app.resources_mut());
 
+        if !app.resources().contains::<FixedTimestep>() {
+            app.init_resource::<FixedTimestep>();
+        }
+        if !app.resources().contains::<Clock>() {
+            app.init_resource::<Clock>();
+        }
+
         app.init_resource::<Time>()
             .init_resource::<EntityLabels>()
             .register_component::<Timer>()
@@ -40,8 +60,14 @@ impl Plugin for CorePlugin {
             .register_property::<Mat4>()
             .register_property::<Quat>()
             .register_property::<Option<String>>()
-            .add_system_to_stage(stage::FIRST, time_system.system())
-            .add_system_to_stage(stage::FIRST, timer_system.system())
-            .add_system_to_stage(stage::PRE_UPDATE, entity_labels_system.system());
+            .register_property::<Duration>()
+            .add_system_to_stage(bevy_app::stage::FIRST, time_system.system())
+            .add_system_to_stage(bevy_app::stage::FIRST, timer_system.system())
+            .add_system_to_stage(bevy_app::stage::PRE_UPDATE, entity_labels_system.system())
+            .add_stage_before(bevy_app::stage::UPDATE, stage::FIXED_UPDATE)
+            .set_stage_run_criteria(
+                stage::FIXED_UPDATE,
+                fixed_timestep_run_criteria.run_criteria(),
+            );
     }
 }
@@ -0,0 +1,216 @@
+use crate::Clock;
+use bevy_app::{App, AppBuilder, Events};
+use bevy_ecs::{Fetch, HecsQuery, ReadOnlyFetch, Resource, Resources, World};
+use std::time::Duration;
+
+/// Runs an [App] frame by frame without a window or runner, for fast and reproducible tests
+///
+/// The [Clock] of the app is replaced by a manual clock, so [Time](crate::Time) advances by the same
+/// amount on every update.
+///
+/// # Example
+/// ```
+/// use bevy_app::prelude::*;
+/// use bevy_core::{CorePlugin, TestHarness, Time};
+/// use bevy_ecs::prelude::*;
+/// use bevy_type_registry::TypeRegistryPlugin;
+/// use std::time::Duration;
+///
+/// struct Position(f32);
+///
+/// fn movement(time: Res<Time>, mut position: Mut<Position>) {
+///     position.0 += time.delta_seconds;
+/// }
+///
+/// let mut app = App::build();
+/// app.add_plugin(TypeRegistryPlugin)
+///     .add_plugin(CorePlugin)
+///     .add_system(movement.system());
+/// let mut harness = TestHarness::new(&mut app, Duration::from_millis(250));
+/// harness.world_mut().spawn((Position(0.0),));
+///
+/// // the first update has no delta
+/// harness.step(5);
+/// harness.assert_all::<&Position, _>(|position| position.0 == 1.0);
+/// ```
+pub struct TestHarness {
+    app: App,
+}
+
+impl TestHarness {
+    /// Takes the app out of `app_builder` and runs its startup systems. Every update advances
+    /// [Time](crate::Time) by `frame_time`.
+    pub fn new(app_builder: &mut AppBuilder, frame_time: Duration) -> Self {
+        let mut app = std::mem::take(&mut app_builder.app);
+        app.resources.insert(Clock::manual(frame_time));
+        app.executor.initialize(&mut app.resources);
+        app.initialize();
+        Self { app }
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn world(&self) -> &World {
+        &self.app.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    pub fn resources(&self) -> &Resources {
+        &self.app.resources
+    }
+
+    pub fn resources_mut(&mut self) -> &mut Resources {
+        &mut self.app.resources
+    }
+
+    /// Runs one frame
+    pub fn update(&mut self) -> &mut Self {
+        self.app.update();
+        self
+    }
+
+    /// Runs `frames` frames
+    pub fn step(&mut self, frames: usize) -> &mut Self {
+        for _ in 0..frames {
+            self.app.update();
+        }
+        self
+    }
+
+    /// Moves the clock forward by `duration` on top of the frame time, as if the next frame took
+    /// longer
+    pub fn advance_clock(&mut self, duration: Duration) -> &mut Self {
+        self.app
+            .resources
+            .get_mut::<Clock>()
+            .unwrap()
+            .advance(duration);
+        self
+    }
+
+    /// Sends `event` to the systems reading events of type `T`, like an input event would be by a
+    /// window. The event type must have been added with [AppBuilder::add_event].
+    pub fn send_event<T: Resource>(&mut self, event: T) -> &mut Self {
+        self.app
+            .resources
+            .get_mut::<Events<T>>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "Event type {} was not added to the app.",
+                    std::any::type_name::<T>()
+                )
+            })
+            .send(event);
+        self
+    }
+
+    /// The number of entities that match `Q`
+    pub fn count<Q: HecsQuery>(&self) -> usize
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        self.app.world.query::<Q>().iter().count()
+    }
+
+    /// # Panics
+    /// Panics if the number of entities that match `Q` isn't `expected`
+    pub fn assert_count<Q: HecsQuery>(&self, expected: usize)
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        let count = self.count::<Q>();
+        assert_eq!(
+            count,
+            expected,
+            "expected {} entities to match {}, found {}",
+            expected,
+            std::any::type_name::<Q>(),
+            count
+        );
+    }
+
+    /// # Panics
+    /// Panics if `predicate` returns false for one of the entities that match `Q`
+    pub fn assert_all<Q: HecsQuery, F>(&self, mut predicate: F)
+    where
+        Q::Fetch: ReadOnlyFetch,
+        F: for<'a> FnMut(<Q::Fetch as Fetch<'a>>::Item) -> bool,
+    {
+        let mut query = self.app.world.query::<Q>();
+        for (index, item) in query.iter().enumerate() {
+            assert!(
+                predicate(item),
+                "entity #{} that matches {} doesn't satisfy the predicate",
+                index,
+                std::any::type_name::<Q>()
+            );
+        }
+    }
+
+    /// # Panics
+    /// Panics if `predicate` returns false for all entities that match `Q`
+    pub fn assert_any<Q: HecsQuery, F>(&self, predicate: F)
+    where
+        Q::Fetch: ReadOnlyFetch,
+        F: for<'a> FnMut(<Q::Fetch as Fetch<'a>>::Item) -> bool,
+    {
+        let mut query = self.app.world.query::<Q>();
+        assert!(
+            query.iter().any(predicate),
+            "no entity that matches {} satisfies the predicate",
+            std::any::type_name::<Q>()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestHarness;
+    use crate::{CorePlugin, Time};
+    use bevy_app::{App, EventReader, Events};
+    use bevy_ecs::{Commands, IntoQuerySystem, Local, Res};
+    use bevy_type_registry::TypeRegistryPlugin;
+    use std::time::Duration;
+
+    struct Spawn(u32);
+    struct Spawned(u32);
+
+    fn spawn(
+        mut commands: Commands,
+        mut reader: Local<EventReader<Spawn>>,
+        events: Res<Events<Spawn>>,
+    ) {
+        for event in reader.iter(&events) {
+            commands.spawn((Spawned(event.0),));
+        }
+    }
+
+    #[test]
+    fn harness_steps_time_and_events() {
+        let mut app = App::build();
+        app.add_plugin(TypeRegistryPlugin)
+            .add_plugin(CorePlugin)
+            .add_event::<Spawn>()
+            .add_system(spawn.system());
+        let mut harness = TestHarness::new(&mut app, Duration::from_millis(100));
+
+        harness.send_event(Spawn(1)).send_event(Spawn(2)).update();
+        harness.assert_count::<&Spawned>(2);
+        harness.assert_any::<&Spawned, _>(|spawned| spawned.0 == 2);
+
+        harness.advance_clock(Duration::from_millis(400)).update();
+        let time = harness.resources().get::<Time>().unwrap();
+        assert_eq!(time.real_delta, Duration::from_millis(500));
+        assert_eq!(time.delta, time.max_delta());
+        assert_eq!(time.seconds_since_startup, 0.6);
+    }
+}
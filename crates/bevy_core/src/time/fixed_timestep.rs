@@ -0,0 +1,186 @@
+use crate::time::{Time, TimeDomain};
+use bevy_ecs::{Res, ResMut, ShouldRun};
+use std::time::Duration;
+
+/// Drives a stage at a fixed rate. Time from the clock of its [TimeDomain], virtual by default, is
+/// accumulated every update and the stage runs once for each whole `step` in the accumulator.
+///
+/// Systems in the fixed stage should use [FixedTimestep::step_seconds] instead of `Time::delta`.
+/// Systems running after the fixed stage can use [FixedTimestep::alpha] to interpolate between the
+/// last two fixed steps.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps_per_update: Option<u32>,
+    accumulator: Duration,
+    steps_this_update: u32,
+    looping: bool,
+    domain: TimeDomain,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::from_seconds(1.0 / 60.0)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        assert!(
+            step > Duration::from_secs(0),
+            "fixed timestep must be non-zero"
+        );
+        Self {
+            step,
+            max_steps_per_update: None,
+            accumulator: Duration::from_secs(0),
+            steps_this_update: 0,
+            looping: false,
+            domain: TimeDomain::Virtual,
+        }
+    }
+
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self::new(Duration::from_secs_f64(seconds))
+    }
+
+    /// Caps the number of steps run in a single update. When the cap is reached, the remaining whole
+    /// steps are dropped so that a slow frame can't trigger an ever growing number of catch-up steps.
+    pub fn with_max_steps_per_update(mut self, max_steps: u32) -> Self {
+        self.max_steps_per_update = Some(max_steps);
+        self
+    }
+
+    /// Makes the steps follow the clock of `domain`
+    pub fn with_domain(mut self, domain: TimeDomain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn domain(&self) -> TimeDomain {
+        self.domain
+    }
+
+    pub fn set_domain(&mut self, domain: TimeDomain) {
+        self.domain = domain;
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn step_seconds(&self) -> f64 {
+        self.step.as_secs_f64()
+    }
+
+    pub fn set_step(&mut self, step: Duration) {
+        assert!(
+            step > Duration::from_secs(0),
+            "fixed timestep must be non-zero"
+        );
+        self.step = step;
+    }
+
+    pub fn max_steps_per_update(&self) -> Option<u32> {
+        self.max_steps_per_update
+    }
+
+    pub fn set_max_steps_per_update(&mut self, max_steps: Option<u32>) {
+        self.max_steps_per_update = max_steps;
+    }
+
+    /// Time accumulated towards the next step
+    pub fn accumulator(&self) -> Duration {
+        self.accumulator
+    }
+
+    /// How far the accumulator is into the next step, in the range `[0, 1)`
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.as_secs_f64() / self.step.as_secs_f64()
+    }
+
+    /// The number of steps run during the current update
+    pub fn steps_this_update(&self) -> u32 {
+        self.steps_this_update
+    }
+
+    fn next_step(&mut self, delta: Duration) -> ShouldRun {
+        if !self.looping {
+            self.accumulator += delta;
+            self.steps_this_update = 0;
+            self.looping = true;
+        }
+
+        let capped = self
+            .max_steps_per_update
+            .map_or(false, |max_steps| self.steps_this_update >= max_steps);
+
+        if self.accumulator >= self.step && !capped {
+            self.accumulator -= self.step;
+            self.steps_this_update += 1;
+            ShouldRun::YesAndLoop
+        } else {
+            if capped {
+                let step = self.step.as_nanos();
+                let dropped = self.accumulator.as_nanos() / step;
+                if dropped > 0 {
+                    log::debug!("fixed timestep dropped {} steps", dropped);
+                }
+                self.accumulator =
+                    Duration::from_nanos((self.accumulator.as_nanos() % step) as u64);
+            }
+            self.looping = false;
+            ShouldRun::No
+        }
+    }
+}
+
+/// Run criteria for the [FIXED_UPDATE](crate::stage::FIXED_UPDATE) stage
+pub fn fixed_timestep_run_criteria(
+    time: Res<Time>,
+    mut fixed_timestep: ResMut<FixedTimestep>,
+) -> ShouldRun {
+    let delta = time.delta_of(fixed_timestep.domain);
+    fixed_timestep.next_step(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedTimestep;
+    use bevy_ecs::ShouldRun;
+    use std::time::Duration;
+
+    fn run_update(fixed_timestep: &mut FixedTimestep, delta: Duration) -> u32 {
+        let mut steps = 0;
+        while fixed_timestep.next_step(delta) != ShouldRun::No {
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn accumulates_steps() {
+        let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10));
+        assert_eq!(run_update(&mut fixed_timestep, Duration::from_millis(5)), 0);
+        assert!((fixed_timestep.alpha() - 0.5).abs() < 1e-9);
+        assert_eq!(run_update(&mut fixed_timestep, Duration::from_millis(5)), 1);
+        assert_eq!(fixed_timestep.steps_this_update(), 1);
+        assert_eq!(
+            run_update(&mut fixed_timestep, Duration::from_millis(32)),
+            3
+        );
+        assert_eq!(fixed_timestep.accumulator(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn caps_catch_up_steps() {
+        let mut fixed_timestep =
+            FixedTimestep::new(Duration::from_millis(10)).with_max_steps_per_update(2);
+        assert_eq!(
+            run_update(&mut fixed_timestep, Duration::from_millis(55)),
+            2
+        );
+        assert_eq!(fixed_timestep.accumulator(), Duration::from_millis(5));
+        assert_eq!(run_update(&mut fixed_timestep, Duration::from_millis(5)), 1);
+    }
+}
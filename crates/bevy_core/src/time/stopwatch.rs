@@ -0,0 +1,78 @@
+use bevy_property::Properties;
+use std::time::Duration;
+
+/// Tracks the time elapsed while it is ticked and not paused
+///
+/// Unlike a [Timer](crate::Timer), it has no duration and never finishes. Stopwatches aren't ticked
+/// automatically.
+#[derive(Clone, Debug, Default, Properties)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Advances the stopwatch by `delta`, unless it is paused
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::from_secs(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stopwatch;
+    use std::time::Duration;
+
+    #[test]
+    fn stopwatch_ticks_unless_paused() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(Duration::from_millis(300));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(300));
+
+        stopwatch.pause();
+        stopwatch.tick(Duration::from_millis(200));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(300));
+
+        stopwatch.unpause();
+        stopwatch.tick(Duration::from_millis(200));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(500));
+
+        stopwatch.reset();
+        assert_eq!(stopwatch.elapsed(), Duration::from_secs(0));
+    }
+}
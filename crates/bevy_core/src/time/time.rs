@@ -7,14 +7,34 @@ use instant::Instant;
 use std::time::Instant;
 
 /// Tracks elapsed time since the last update and since the App has started
+///
+/// `delta` is virtual time, which games should use: it stands still while paused, is scaled by the
+/// relative speed and is clamped to `max_delta` so a hitch doesn't make objects jump. `real_delta`
+/// is the unscaled real time, for things that keep going while the game is paused, like menus.
+///
+/// `max_delta` defaults to 250ms, so `delta` is shorter than the real frame time on frames that take
+/// longer than that. Raise it with [Time::set_max_delta] to keep `delta` in sync with real time.
+///
+/// `seconds_since_startup` and [Time::time_since_startup] are real time and keep going while the game
+/// is paused. Animations driven by the total time should use `elapsed_seconds` instead.
 #[derive(Debug)]
 pub struct Time {
     pub delta: Duration,
     pub instant: Option<Instant>,
     pub delta_seconds_f64: f64,
     pub delta_seconds: f32,
+    /// Real time elapsed since the App has started
     pub seconds_since_startup: f64,
     pub startup: Instant,
+    pub real_delta: Duration,
+    pub real_delta_seconds_f64: f64,
+    pub real_delta_seconds: f32,
+    /// Virtual time elapsed since the App has started
+    pub elapsed: Duration,
+    pub elapsed_seconds: f64,
+    paused: bool,
+    relative_speed: f64,
+    max_delta: Duration,
 }
 
 impl Default for Time {
@@ -26,17 +46,41 @@ impl Default for Time {
             delta_seconds_f64: 0.0,
             seconds_since_startup: 0.0,
             delta_seconds: 0.0,
+            real_delta: Duration::from_secs(0),
+            real_delta_seconds_f64: 0.0,
+            real_delta_seconds: 0.0,
+            elapsed: Duration::from_secs(0),
+            elapsed_seconds: 0.0,
+            paused: false,
+            relative_speed: 1.0,
+            max_delta: Duration::from_millis(250),
         }
     }
 }
 
 impl Time {
     pub fn update(&mut self) {
-        let now = Instant::now();
+        self.update_with_instant(Instant::now());
+    }
+
+    /// Updates the time as if the current instant was `now`
+    pub fn update_with_instant(&mut self, now: Instant) {
         if let Some(instant) = self.instant {
-            self.delta = now - instant;
+            self.real_delta = now - instant;
+            self.real_delta_seconds_f64 = self.real_delta.as_secs_f64();
+            self.real_delta_seconds = self.real_delta.as_secs_f32();
+
+            self.delta = if self.paused {
+                Duration::from_secs(0)
+            } else {
+                self.real_delta
+                    .min(self.max_delta)
+                    .mul_f64(self.relative_speed)
+            };
             self.delta_seconds_f64 = self.delta.as_secs_f64();
             self.delta_seconds = self.delta.as_secs_f32();
+            self.elapsed += self.delta;
+            self.elapsed_seconds = self.elapsed.as_secs_f64();
         }
 
         let duration_since_startup = now - self.startup;
@@ -44,11 +88,170 @@ impl Time {
         self.instant = Some(now);
     }
 
+    /// The real time elapsed since the App has started, read from the system clock
     pub fn time_since_startup(&self) -> Duration {
         Instant::now() - self.startup
     }
+
+    /// Stops virtual time. Real time keeps going.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How fast virtual time goes compared to real time
+    pub fn relative_speed(&self) -> f64 {
+        self.relative_speed
+    }
+
+    /// Makes virtual time go `relative_speed` times as fast as real time, like `0.5` for slow-motion
+    ///
+    /// # Panics
+    /// Panics if `relative_speed` is negative or not finite
+    pub fn set_relative_speed(&mut self, relative_speed: f64) {
+        assert!(
+            relative_speed.is_finite() && relative_speed >= 0.0,
+            "relative speed must be finite and non-negative"
+        );
+        self.relative_speed = relative_speed;
+    }
+
+    /// The longest real time that is turned into virtual time in a single update
+    pub fn max_delta(&self) -> Duration {
+        self.max_delta
+    }
+
+    pub fn set_max_delta(&mut self, max_delta: Duration) {
+        self.max_delta = max_delta;
+    }
+
+    /// The time elapsed since the last update on the clock of `domain`
+    pub fn delta_of(&self, domain: TimeDomain) -> Duration {
+        match domain {
+            TimeDomain::Virtual => self.delta,
+            TimeDomain::Real => self.real_delta,
+        }
+    }
+
+    pub fn delta_seconds_of(&self, domain: TimeDomain) -> f32 {
+        match domain {
+            TimeDomain::Virtual => self.delta_seconds,
+            TimeDomain::Real => self.real_delta_seconds,
+        }
+    }
+}
+
+/// Which clock of [Time] something follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeDomain {
+    /// Paused, scaled and clamped game time
+    Virtual,
+    /// Unscaled real time, which keeps going while the game is paused
+    Real,
+}
+
+impl Default for TimeDomain {
+    fn default() -> Self {
+        TimeDomain::Virtual
+    }
+}
+
+/// The clock that [Time] is updated from, the system clock by default
+#[derive(Debug, Clone, Copy)]
+pub enum Clock {
+    Real,
+    /// A clock that stands still unless it is advanced. Every update of [Time] advances it by
+    /// `step` first, so apps using it run the same way each time, no matter how long frames take.
+    Manual {
+        now: Instant,
+        step: Duration,
+    },
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::Real
+    }
+}
+
+impl Clock {
+    /// A manual clock that advances by `step` on every update
+    pub fn manual(step: Duration) -> Self {
+        Clock::Manual {
+            now: Instant::now(),
+            step,
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        match self {
+            Clock::Real => Instant::now(),
+            Clock::Manual { now, .. } => *now,
+        }
+    }
+
+    /// Moves a manual clock forward by `duration`
+    ///
+    /// # Panics
+    /// Panics if the clock is the real clock
+    pub fn advance(&mut self, duration: Duration) {
+        match self {
+            Clock::Real => panic!("The real clock can't be advanced."),
+            Clock::Manual { now, .. } => *now += duration,
+        }
+    }
 }
 
-pub(crate) fn time_system(mut time: ResMut<Time>) {
-    time.update();
+pub(crate) fn time_system(mut time: ResMut<Time>, mut clock: ResMut<Clock>) {
+    match &mut *clock {
+        Clock::Real => time.update(),
+        Clock::Manual { now, step } => {
+            if time.instant.is_none() {
+                time.startup = *now;
+            }
+            *now += *step;
+            time.update_with_instant(*now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Instant, Time, TimeDomain};
+    use std::time::Duration;
+
+    #[test]
+    fn virtual_time_is_paused_scaled_and_clamped() {
+        let start = Instant::now();
+        let mut time = Time::default();
+        time.update_with_instant(start);
+        let update = |time: &mut Time, millis| {
+            let now = time.instant.unwrap() + Duration::from_millis(millis);
+            time.update_with_instant(now);
+        };
+
+        time.set_relative_speed(2.0);
+        update(&mut time, 100);
+        assert_eq!(time.delta, Duration::from_millis(200));
+        assert_eq!(time.real_delta, Duration::from_millis(100));
+
+        time.set_relative_speed(1.0);
+        update(&mut time, 1000);
+        assert_eq!(time.delta, time.max_delta());
+        assert_eq!(time.delta_of(TimeDomain::Real), Duration::from_secs(1));
+
+        time.pause();
+        update(&mut time, 100);
+        assert_eq!(time.delta, Duration::from_secs(0));
+        assert_eq!(time.real_delta, Duration::from_millis(100));
+        assert_eq!(time.elapsed, Duration::from_millis(450));
+        assert_eq!(time.elapsed_seconds, 0.45);
+    }
 }
@@ -1,63 +1,249 @@
-use crate::time::Time;
+use crate::time::{Time, TimeDomain};
 use bevy_ecs::prelude::*;
 use bevy_property::Properties;
-use std::time::Duration;
+use std::{convert::TryFrom, time::Duration};
 
 /// Tracks elapsed time. Enters the finished state once `duration` is reached.
 ///
 /// Non repeating timers will stop tracking and stay in the finished state until reset.
 /// Repeating timers will only be in the finished state on each tick `duration` is reached or exceeded, and can still be reset at any given point.
+///
+/// Timer components are ticked by the [CorePlugin](crate::CorePlugin), unless their entity has a
+/// [ManualTimer] component.
 #[derive(Clone, Debug, Default, Properties)]
 pub struct Timer {
-    pub elapsed: f32,
-    pub duration: f32,
-    pub finished: bool,
-    /// Will only be true on the tick `duration` is reached or exceeded.
-    pub just_finished: bool,
-    pub repeating: bool,
+    elapsed: Duration,
+    duration: Duration,
+    finished: bool,
+    times_finished: u32,
+    repeating: bool,
+    paused: bool,
+    #[property(ignore)]
+    domain: TimeDomain,
 }
 
 impl Timer {
     pub fn new(duration: Duration, repeating: bool) -> Self {
         Timer {
-            duration: duration.as_secs_f32(),
+            duration,
             repeating,
             ..Default::default()
         }
     }
 
+    /// A timer of `seconds`. Negative durations are treated as zero.
     pub fn from_seconds(seconds: f32, repeating: bool) -> Self {
-        Timer {
-            duration: seconds,
-            repeating,
-            ..Default::default()
-        }
+        Self::new(Duration::from_secs_f32(seconds.max(0.0)), repeating)
+    }
+
+    /// Makes the timer follow the clock of `domain` when it is ticked by the
+    /// [CorePlugin](crate::CorePlugin), like [TimeDomain::Real] for timers that should keep ticking
+    /// while the game is paused
+    pub fn with_domain(mut self, domain: TimeDomain) -> Self {
+        self.domain = domain;
+        self
     }
 
-    /// Advances the timer by `delta` seconds.
-    pub fn tick(&mut self, delta: f32) {
-        let prev_finished = self.elapsed >= self.duration;
-        if !prev_finished {
-            self.elapsed += delta;
+    pub fn domain(&self) -> TimeDomain {
+        self.domain
+    }
+
+    /// Advances the timer by `delta`. A repeating timer can finish several times in a single tick.
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if self.paused || (self.finished && !self.repeating) {
+            self.times_finished = 0;
+            if self.repeating {
+                self.finished = false;
+            }
+            return self;
+        }
+
+        self.elapsed += delta;
+        if self.elapsed < self.duration {
+            self.finished = false;
+            self.times_finished = 0;
+        } else if !self.repeating {
+            self.elapsed = self.duration;
+            self.finished = true;
+            self.times_finished = 1;
+        } else if self.duration == Duration::from_secs(0) {
+            self.elapsed = Duration::from_secs(0);
+            self.finished = true;
+            self.times_finished = 1;
+        } else {
+            let duration = self.duration.as_nanos();
+            let elapsed = self.elapsed.as_nanos();
+            self.elapsed = Duration::from_nanos((elapsed % duration) as u64);
+            self.finished = true;
+            self.times_finished = u32::try_from(elapsed / duration).unwrap_or(u32::MAX);
         }
+        self
+    }
+
+    /// Whether the timer has reached its duration. Repeating timers are only finished on the ticks
+    /// they reached it.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Whether the timer reached its duration during the last tick
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// The number of times the timer reached its duration during the last tick, which can be more
+    /// than one for repeating timers ticked by more than their duration
+    pub fn times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
 
-        self.finished = self.elapsed >= self.duration;
-        self.just_finished = !prev_finished && self.finished;
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+        self.update_finished();
+    }
 
-        if self.repeating && self.finished {
-            self.elapsed %= self.duration;
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+        self.update_finished();
+    }
+
+    /// The time left until the timer finishes
+    pub fn remaining(&self) -> Duration {
+        self.duration
+            .checked_sub(self.elapsed)
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    pub fn remaining_secs(&self) -> f32 {
+        self.remaining().as_secs_f32()
+    }
+
+    /// How far the timer is towards its duration, in the range `[0, 1]`
+    pub fn percent(&self) -> f32 {
+        if self.duration == Duration::from_secs(0) {
+            1.0
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
         }
     }
 
+    pub fn repeating(&self) -> bool {
+        self.repeating
+    }
+
+    pub fn set_repeating(&mut self, repeating: bool) {
+        self.repeating = repeating;
+    }
+
+    /// Stops the timer from advancing when it is ticked
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
     pub fn reset(&mut self) {
         self.finished = false;
-        self.just_finished = false;
-        self.elapsed = 0.0;
+        self.times_finished = 0;
+        self.elapsed = Duration::from_secs(0);
+    }
+
+    /// Keeps a non repeating timer finished only while its duration is reached, so it resumes when
+    /// it is given more time
+    fn update_finished(&mut self) {
+        if !self.repeating {
+            self.finished = self.elapsed >= self.duration;
+        }
     }
 }
 
-pub(crate) fn timer_system(time: Res<Time>, mut query: Query<&mut Timer>) {
+/// Keeps the [Timer] of an entity from being ticked by the [CorePlugin](crate::CorePlugin), for
+/// timers that are ticked by hand
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManualTimer;
+
+pub(crate) fn timer_system(time: Res<Time>, mut query: Query<Without<ManualTimer, &mut Timer>>) {
     for mut timer in &mut query.iter() {
-        timer.tick(time.delta_seconds);
+        let delta = time.delta_of(timer.domain());
+        timer.tick(delta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timer;
+    use std::time::Duration;
+
+    #[test]
+    fn repeating_timer_finishes_several_times() {
+        let mut timer = Timer::new(Duration::from_millis(100), true);
+        timer.tick(Duration::from_millis(50));
+        assert!(!timer.finished());
+        assert_eq!(timer.remaining(), Duration::from_millis(50));
+
+        timer.tick(Duration::from_millis(280));
+        assert!(timer.just_finished());
+        assert_eq!(timer.times_finished(), 3);
+        assert_eq!(timer.elapsed(), Duration::from_millis(30));
+
+        timer.pause();
+        timer.tick(Duration::from_millis(100));
+        assert_eq!(timer.times_finished(), 0);
+        assert_eq!(timer.elapsed(), Duration::from_millis(30));
+
+        timer.unpause();
+        timer.tick(Duration::from_millis(10));
+        assert!(!timer.finished());
+    }
+
+    #[test]
+    fn non_repeating_timer_stays_finished() {
+        let mut timer = Timer::from_seconds(1.0, false);
+        timer.tick(Duration::from_millis(2500));
+        assert!(timer.finished());
+        assert_eq!(timer.times_finished(), 1);
+        assert_eq!(timer.remaining(), Duration::from_secs(0));
+
+        timer.tick(Duration::from_millis(100));
+        assert!(timer.finished());
+        assert!(!timer.just_finished());
+
+        timer.set_duration(Duration::from_secs(2));
+        assert!(!timer.finished());
+        timer.tick(Duration::from_millis(1500));
+        assert!(timer.just_finished());
+
+        timer.reset();
+        assert!(!timer.finished());
+        assert_eq!(timer.elapsed(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn times_finished_saturates() {
+        let mut timer = Timer::new(Duration::from_nanos(1), true);
+        timer.tick(Duration::from_secs(10));
+        assert_eq!(timer.times_finished(), u32::MAX);
+        assert_eq!(
+            Timer::from_seconds(-1.0, false).duration(),
+            Duration::from_secs(0)
+        );
     }
 }
@@ -69,8 +69,7 @@ impl PrintDiagnosticsPlugin {
         time: Res<Time>,
         diagnostics: Res<Diagnostics>,
     ) {
-        state.timer.tick(time.delta_seconds);
-        if state.timer.finished {
+        if state.timer.tick(time.real_delta).finished() {
             println!("Diagnostics:");
             println!("{}", "-".repeat(93));
             if let Some(ref filter) = state.filter {
@@ -90,8 +89,7 @@ impl PrintDiagnosticsPlugin {
         time: Res<Time>,
         diagnostics: Res<Diagnostics>,
     ) {
-        state.timer.tick(time.delta_seconds);
-        if state.timer.finished {
+        if state.timer.tick(time.real_delta).finished() {
             println!("Diagnostics (Debug):");
             println!("{}", "-".repeat(93));
             if let Some(ref filter) = state.filter {
@@ -19,8 +19,8 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use proc_macro_crate::crate_name;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Path};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, GenericParam, Lifetime, LifetimeDef, Path};
 
 /// Implement `Bundle` for a monomorphic struct
 ///
@@ -58,7 +58,7 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
     let n = tys.len();
     let code = quote! {
         impl #path::DynamicBundle for #ident {
-            fn with_ids<T>(&self, f: impl FnOnce(&[std::any::TypeId]) -> T) -> T {
+            fn with_ids<T>(&self, f: impl FnOnce(&[#path::ComponentId]) -> T) -> T {
                 Self::with_static_ids(f)
             }
 
@@ -66,9 +66,9 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
                 Self::static_type_info()
             }
 
-            unsafe fn put(mut self, mut f: impl FnMut(*mut u8, std::any::TypeId, usize) -> bool) {
+            unsafe fn put(mut self, mut f: impl FnMut(*mut u8, #path::ComponentId, usize) -> bool) {
                 #(
-                    if f((&mut self.#fields as *mut #tys).cast::<u8>(), std::any::TypeId::of::<#tys>(), std::mem::size_of::<#tys>()) {
+                    if f((&mut self.#fields as *mut #tys).cast::<u8>(), #path::ComponentId::of::<#tys>(), std::mem::size_of::<#tys>()) {
                         #[allow(clippy::forget_copy)]
                         std::mem::forget(self.#fields);
                     }
@@ -77,22 +77,22 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
         }
 
         impl #path::Bundle for #ident {
-            fn with_static_ids<T>(f: impl FnOnce(&[std::any::TypeId]) -> T) -> T {
-                use std::any::TypeId;
+            fn with_static_ids<T>(f: impl FnOnce(&[#path::ComponentId]) -> T) -> T {
+                use #path::ComponentId;
                 use std::mem;
 
                 #path::lazy_static::lazy_static! {
-                    static ref ELEMENTS: [TypeId; #n] = {
+                    static ref ELEMENTS: [ComponentId; #n] = {
                         let mut dedup = #path::bevy_utils::HashSet::default();
-                        for &(ty, name) in [#((std::any::TypeId::of::<#tys>(), std::any::type_name::<#tys>())),*].iter() {
+                        for &(ty, name) in [#((ComponentId::of::<#tys>(), std::any::type_name::<#tys>())),*].iter() {
                             if !dedup.insert(ty) {
                                 panic!("{} has multiple {} fields; each type must occur at most once!", stringify!(#ident), name);
                             }
                         }
 
-                        let mut tys = [#((mem::align_of::<#tys>(), TypeId::of::<#tys>())),*];
+                        let mut tys = [#((mem::align_of::<#tys>(), ComponentId::of::<#tys>())),*];
                         tys.sort_unstable_by(|x, y| x.0.cmp(&y.0).reverse().then(x.1.cmp(&y.1)));
-                        let mut ids = [TypeId::of::<()>(); #n];
+                        let mut ids = [ComponentId::of::<()>(); #n];
                         for (id, info) in ids.iter_mut().zip(tys.iter()) {
                             *id = info.1;
                         }
@@ -110,10 +110,10 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
             }
 
             unsafe fn get(
-                mut f: impl FnMut(std::any::TypeId, usize) -> Option<std::ptr::NonNull<u8>>,
+                mut f: impl FnMut(#path::ComponentId, usize) -> Option<std::ptr::NonNull<u8>>,
             ) -> Result<Self, #path::MissingComponent> {
                 #(
-                    let #fields = f(std::any::TypeId::of::<#tys>(), std::mem::size_of::<#tys>())
+                    let #fields = f(#path::ComponentId::of::<#tys>(), std::mem::size_of::<#tys>())
                             .ok_or_else(#path::MissingComponent::new::<#tys>)?
                             .cast::<#tys>()
                         .as_ptr();
@@ -125,6 +125,104 @@ pub fn derive_bundle(input: TokenStream) -> TokenStream {
     TokenStream::from(code)
 }
 
+/// Implement `SystemParam` for a struct whose fields are system parameters, so a system can take
+/// them all as a single parameter
+///
+/// The struct can have at most one lifetime, which is the lifetime of the fetched parameters. A
+/// hidden `Fetch<Name>` struct is generated next to it to fetch the parameters.
+#[proc_macro_derive(SystemParam)]
+pub fn derive_system_param(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let data = match input.data {
+        syn::Data::Struct(s) => s,
+        _ => {
+            return TokenStream::from(
+                quote! { compile_error!("derive(SystemParam) only supports structs"); },
+            )
+        }
+    };
+    let ident = input.ident;
+    let vis = input.vis;
+    let (tys, fields) = struct_fields(&data.fields);
+    let path_str = if crate_name("bevy").is_ok() {
+        "bevy::ecs"
+    } else {
+        "bevy_ecs"
+    };
+    let path: Path = syn::parse(path_str.parse::<TokenStream>().unwrap()).unwrap();
+
+    // the parameters are fetched for the lifetime of the struct, or a new one if it has none
+    let mut fetch_generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().count() {
+        0 => {
+            let lifetime = Lifetime::new("'__fetch", Span::call_site());
+            fetch_generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeDef::new(lifetime.clone())),
+            );
+            lifetime
+        }
+        1 => input.generics.lifetimes().next().unwrap().lifetime.clone(),
+        _ => {
+            return TokenStream::from(
+                quote! { compile_error!("derive(SystemParam) supports at most one lifetime"); },
+            )
+        }
+    };
+    let type_params = input
+        .generics
+        .type_params()
+        .map(|param| &param.ident)
+        .collect::<Vec<_>>();
+    let fetch_ident = format_ident!("Fetch{}", ident);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (fetch_impl_generics, _, _) = fetch_generics.split_for_impl();
+
+    let code = quote! {
+        #[doc(hidden)]
+        #vis struct #fetch_ident<#(#type_params),*>(std::marker::PhantomData<(#(#type_params,)*)>);
+
+        impl #impl_generics #path::FetchSystemParam for #ident #ty_generics #where_clause {
+            type Fetch = #fetch_ident<#(#type_params),*>;
+        }
+
+        impl #impl_generics #path::SystemParam for #ident #ty_generics #where_clause {}
+
+        impl #fetch_impl_generics #path::SystemParamFetch<#lifetime> for #fetch_ident<#(#type_params),*> #where_clause {
+            type Item = #ident #ty_generics;
+
+            fn init(state: &mut #path::SystemParamState) {
+                #(<<#tys as #path::FetchSystemParam>::Fetch as #path::SystemParamFetch<#lifetime>>::init(state);)*
+            }
+
+            fn initialize(
+                state: &#path::SystemParamState,
+                world: &mut #path::World,
+                resources: &mut #path::Resources,
+            ) {
+                #(<<#tys as #path::FetchSystemParam>::Fetch as #path::SystemParamFetch<#lifetime>>::initialize(state, world, resources);)*
+            }
+
+            unsafe fn get_param(
+                state: &#lifetime #path::SystemParamState,
+                query_index: &mut usize,
+                world: &#lifetime #path::World,
+                resources: &#lifetime #path::Resources,
+            ) -> Option<Self::Item> {
+                Some(#ident {
+                    #(#fields: <<#tys as #path::FetchSystemParam>::Fetch as #path::SystemParamFetch<#lifetime>>::get_param(
+                        state,
+                        query_index,
+                        world,
+                        resources,
+                    )?,)*
+                })
+            }
+        }
+    };
+    TokenStream::from(code)
+}
+
 fn struct_fields(fields: &syn::Fields) -> (Vec<&syn::Type>, Vec<syn::Ident>) {
     match fields {
         syn::Fields::Named(ref fields) => fields
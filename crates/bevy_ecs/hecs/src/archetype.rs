@@ -19,19 +19,21 @@ use crate::{
         alloc::{alloc, dealloc, Layout},
         vec::Vec,
     },
-    Entity,
+    ComponentTicks, Entity,
 };
 use bevy_utils::AHasher;
 use core::{
     any::{type_name, TypeId},
     cell::UnsafeCell,
-    hash::{BuildHasherDefault, Hasher},
+    hash::{BuildHasherDefault, Hash, Hasher},
     mem,
     ptr::{self, NonNull},
 };
 use std::collections::HashMap;
 
-use crate::{borrow::AtomicBorrow, query::Fetch, Access, Component, Query};
+use crate::{
+    borrow::AtomicBorrow, query::Fetch, Access, Column, ColumnWriter, Component, Query, SparseSets,
+};
 
 /// A collection of entities having the same component types
 ///
@@ -40,7 +42,7 @@ use crate::{borrow::AtomicBorrow, query::Fetch, Access, Component, Query};
 #[derive(Debug)]
 pub struct Archetype {
     types: Vec<TypeInfo>,
-    state: TypeIdMap<TypeState>,
+    state: ComponentIdMap<TypeState>,
     len: usize,
     entities: Vec<Entity>,
     // UnsafeCell allows unique references into `data` to be constructed while shared references
@@ -95,23 +97,25 @@ impl Archetype {
     #[allow(missing_docs)]
     #[inline]
     pub fn has<T: Component>(&self) -> bool {
-        self.has_dynamic(TypeId::of::<T>())
+        self.has_dynamic(ComponentId::of::<T>())
     }
 
     #[allow(missing_docs)]
     #[inline]
     pub fn has_type(&self, ty: TypeId) -> bool {
-        self.has_dynamic(ty)
+        self.has_dynamic(ty.into())
     }
 
-    pub(crate) fn has_dynamic(&self, id: TypeId) -> bool {
+    /// Whether entities in this archetype have a component with the given id
+    #[inline]
+    pub fn has_dynamic(&self, id: ComponentId) -> bool {
         self.state.contains_key(&id)
     }
 
     #[allow(missing_docs)]
     #[inline]
     pub fn get<T: Component>(&self) -> Option<NonNull<T>> {
-        let state = self.state.get(&TypeId::of::<T>())?;
+        let state = self.state.get(&ComponentId::of::<T>())?;
         Some(unsafe {
             NonNull::new_unchecked(
                 (*self.data.get()).as_ptr().add(state.offset).cast::<T>() as *mut T
@@ -122,7 +126,7 @@ impl Archetype {
     #[allow(missing_docs)]
     #[inline]
     pub fn get_with_type_state<T: Component>(&self) -> Option<(NonNull<T>, &TypeState)> {
-        let state = self.state.get(&TypeId::of::<T>())?;
+        let state = self.state.get(&ComponentId::of::<T>())?;
         Some(unsafe {
             (
                 NonNull::new_unchecked(
@@ -134,12 +138,12 @@ impl Archetype {
     }
 
     #[allow(missing_docs)]
-    pub fn get_type_state(&self, ty: TypeId) -> Option<&TypeState> {
+    pub fn get_type_state(&self, ty: ComponentId) -> Option<&TypeState> {
         self.state.get(&ty)
     }
 
     #[allow(missing_docs)]
-    pub fn get_type_state_mut(&mut self, ty: TypeId) -> Option<&mut TypeState> {
+    pub fn get_type_state_mut(&mut self, ty: ComponentId) -> Option<&mut TypeState> {
         self.state.get_mut(&ty)
     }
 
@@ -148,7 +152,7 @@ impl Archetype {
     pub fn borrow<T: Component>(&self) {
         if self
             .state
-            .get(&TypeId::of::<T>())
+            .get(&ComponentId::of::<T>())
             .map_or(false, |x| !x.borrow.borrow())
         {
             panic!("{} already borrowed uniquely", type_name::<T>());
@@ -160,7 +164,7 @@ impl Archetype {
     pub fn borrow_mut<T: Component>(&self) {
         if self
             .state
-            .get(&TypeId::of::<T>())
+            .get(&ComponentId::of::<T>())
             .map_or(false, |x| !x.borrow.borrow_mut())
         {
             panic!("{} already borrowed", type_name::<T>());
@@ -170,7 +174,7 @@ impl Archetype {
     #[allow(missing_docs)]
     #[inline]
     pub fn release<T: Component>(&self) {
-        if let Some(x) = self.state.get(&TypeId::of::<T>()) {
+        if let Some(x) = self.state.get(&ComponentId::of::<T>()) {
             x.borrow.release();
         }
     }
@@ -178,7 +182,7 @@ impl Archetype {
     #[allow(missing_docs)]
     #[inline]
     pub fn release_mut<T: Component>(&self) {
-        if let Some(x) = self.state.get(&TypeId::of::<T>()) {
+        if let Some(x) = self.state.get(&ComponentId::of::<T>()) {
             x.borrow.release_mut();
         }
     }
@@ -200,6 +204,11 @@ impl Archetype {
         self.entities.iter().take(self.len)
     }
 
+    /// The entities in the archetype, in storage order
+    pub(crate) fn entity_slice(&self) -> &[Entity] {
+        &self.entities[..self.len]
+    }
+
     #[inline]
     pub(crate) fn entities(&self) -> NonNull<Entity> {
         unsafe { NonNull::new_unchecked(self.entities.as_ptr() as *mut _) }
@@ -214,11 +223,17 @@ impl Archetype {
         &self.types
     }
 
+    /// The storage of the components with the given id, if the archetype has them
+    pub(crate) fn get_column(&self, ty: ComponentId) -> Option<NonNull<u8>> {
+        let state = self.state.get(&ty)?;
+        Some(unsafe { NonNull::new_unchecked((*self.data.get()).as_ptr().add(state.offset)) })
+    }
+
     /// # Safety
     /// `index` must be in-bounds
     pub(crate) unsafe fn get_dynamic(
         &self,
-        ty: TypeId,
+        ty: ComponentId,
         size: usize,
         index: usize,
     ) -> Option<NonNull<u8>> {
@@ -253,17 +268,61 @@ impl Archetype {
         self.entities.len()
     }
 
-    #[allow(missing_docs)]
-    pub fn clear_trackers(&mut self) {
+    /// The components of type `ty` of every entity in the archetype
+    pub(crate) fn column(&self, ty: &TypeInfo) -> Column<'_> {
+        unsafe { Column::new(*ty, self.get_column(ty.id).unwrap(), self.len) }
+    }
+
+    /// Adds `entities` at the end of the archetype, with the components `write` fills each column
+    /// with, and returns the index of the first one
+    ///
+    /// The entities only become part of the archetype once every column is filled, so nothing is
+    /// left uninitialized if `write` panics.
+    pub(crate) fn extend(
+        &mut self,
+        entities: &[Entity],
+        component_ticks: ComponentTicks,
+        mut write: impl FnMut(&TypeInfo, &mut ColumnWriter),
+    ) -> usize {
+        self.reserve(entities.len());
+        let start = self.len;
+        for ty in &self.types {
+            let mut writer = unsafe {
+                let data = self.get_column(ty.id).unwrap().as_ptr();
+                ColumnWriter::new(
+                    *ty,
+                    NonNull::new_unchecked(data.add(ty.layout.size() * start)),
+                    entities.len(),
+                )
+            };
+            write(ty, &mut writer);
+            writer.finish();
+        }
+        for type_state in self.state.values_mut() {
+            for ticks in &mut type_state.component_ticks[start..start + entities.len()] {
+                *ticks = component_ticks;
+            }
+        }
+        self.entities[start..start + entities.len()].copy_from_slice(entities);
+        self.len += entities.len();
+        start
+    }
+
+    /// Clamps the change ticks of every component so that they never get too old to be compared
+    pub fn check_change_ticks(&mut self, change_tick: u32) {
+        let len = self.len;
         for type_state in self.state.values_mut() {
-            type_state.clear_trackers();
+            for component_ticks in type_state.component_ticks[..len].iter_mut() {
+                component_ticks.check_ticks(change_tick);
+            }
         }
     }
 
     fn grow(&mut self, increment: usize) {
         unsafe {
+            // the storage holds as many rows as there are entity slots
             let old_count = self.len;
-            let count = old_count + increment;
+            let count = self.capacity() + increment;
             self.entities.resize(
                 self.entities.len() + increment,
                 Entity {
@@ -273,8 +332,9 @@ impl Archetype {
             );
 
             for type_state in self.state.values_mut() {
-                type_state.mutated_entities.resize_with(count, || false);
-                type_state.added_entities.resize_with(count, || false);
+                type_state
+                    .component_ticks
+                    .resize_with(count, ComponentTicks::default);
             }
 
             let old_data_size = mem::replace(&mut self.data_size, 0);
@@ -331,7 +391,6 @@ impl Archetype {
                 .as_ptr();
             (ty.drop)(removed);
             if index != last {
-                // TODO: copy component tracker state here
                 ptr::copy_nonoverlapping(
                     self.get_dynamic(ty.id, ty.layout.size(), last)
                         .unwrap()
@@ -341,8 +400,7 @@ impl Archetype {
                 );
 
                 let type_state = self.state.get_mut(&ty.id).unwrap();
-                type_state.mutated_entities[index] = type_state.mutated_entities[last];
-                type_state.added_entities[index] = type_state.added_entities[last];
+                type_state.component_ticks[index] = type_state.component_ticks[last];
             }
         }
         self.len = last;
@@ -358,7 +416,7 @@ impl Archetype {
     pub(crate) unsafe fn move_to(
         &mut self,
         index: usize,
-        mut f: impl FnMut(*mut u8, TypeId, usize, bool, bool),
+        mut f: impl FnMut(*mut u8, ComponentId, usize, ComponentTicks),
     ) -> Option<Entity> {
         let last = self.len - 1;
         for ty in &self.types {
@@ -366,10 +424,8 @@ impl Archetype {
                 .get_dynamic(ty.id, ty.layout.size(), index)
                 .unwrap()
                 .as_ptr();
-            let type_state = self.state.get(&ty.id).unwrap();
-            let is_added = type_state.added_entities[index];
-            let is_mutated = type_state.mutated_entities[index];
-            f(moved, ty.id(), ty.layout().size(), is_added, is_mutated);
+            let component_ticks = self.state.get(&ty.id).unwrap().component_ticks[index];
+            f(moved, ty.id(), ty.layout().size(), component_ticks);
             if index != last {
                 ptr::copy_nonoverlapping(
                     self.get_dynamic(ty.id, ty.layout.size(), last)
@@ -379,8 +435,7 @@ impl Archetype {
                     ty.layout.size(),
                 );
                 let type_state = self.state.get_mut(&ty.id).unwrap();
-                type_state.added_entities[index] = type_state.added_entities[last];
-                type_state.mutated_entities[index] = type_state.mutated_entities[last];
+                type_state.component_ticks[index] = type_state.component_ticks[last];
             }
         }
         self.len -= 1;
@@ -402,15 +457,13 @@ impl Archetype {
     pub unsafe fn put_dynamic(
         &mut self,
         component: *mut u8,
-        ty: TypeId,
+        ty: ComponentId,
         size: usize,
         index: usize,
-        added: bool,
+        component_ticks: ComponentTicks,
     ) {
         let state = self.state.get_mut(&ty).unwrap();
-        if added {
-            state.added_entities[index] = true;
-        }
+        state.component_ticks[index] = component_ticks;
         let ptr = (*self.data.get())
             .as_ptr()
             .add(state.offset + size * index)
@@ -419,8 +472,8 @@ impl Archetype {
     }
 
     /// How, if at all, `Q` will access entities in this archetype
-    pub fn access<Q: Query>(&self) -> Option<Access> {
-        Q::Fetch::access(self)
+    pub fn access<Q: Query>(&self, sparse_sets: &SparseSets) -> Option<Access> {
+        Q::Fetch::access(self, sparse_sets)
     }
 }
 
@@ -446,8 +499,7 @@ impl Drop for Archetype {
 pub struct TypeState {
     offset: usize,
     borrow: AtomicBorrow,
-    mutated_entities: Vec<bool>,
-    added_entities: Vec<bool>,
+    component_ticks: Vec<ComponentTicks>,
 }
 
 impl TypeState {
@@ -455,38 +507,58 @@ impl TypeState {
         Self {
             offset: 0,
             borrow: AtomicBorrow::new(),
-            mutated_entities: Vec::new(),
-            added_entities: Vec::new(),
+            component_ticks: Vec::new(),
         }
     }
 
-    fn clear_trackers(&mut self) {
-        for mutated in self.mutated_entities.iter_mut() {
-            *mutated = false;
-        }
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn component_ticks(&self) -> NonNull<ComponentTicks> {
+        unsafe { NonNull::new_unchecked(self.component_ticks.as_ptr() as *mut ComponentTicks) }
+    }
 
-        for added in self.added_entities.iter_mut() {
-            *added = false;
-        }
+    pub(crate) fn borrow_state(&self) -> &AtomicBorrow {
+        &self.borrow
     }
+}
 
-    #[allow(missing_docs)]
+/// Identifies a kind of component, either by its Rust type or by an id chosen at runtime
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ComponentId {
+    /// A component that is a Rust type
+    RustTypeId(TypeId),
+    /// A component defined at runtime, for example by a scripting language
+    ExternalId(u64),
+}
+
+impl ComponentId {
+    /// The id of the component type `T`
     #[inline]
-    pub fn mutated(&self) -> NonNull<bool> {
-        unsafe { NonNull::new_unchecked(self.mutated_entities.as_ptr() as *mut bool) }
+    pub fn of<T: 'static>() -> Self {
+        ComponentId::RustTypeId(TypeId::of::<T>())
     }
+}
 
-    #[allow(missing_docs)]
-    #[inline]
-    pub fn added(&self) -> NonNull<bool> {
-        unsafe { NonNull::new_unchecked(self.added_entities.as_ptr() as *mut bool) }
+impl From<TypeId> for ComponentId {
+    fn from(ty: TypeId) -> Self {
+        ComponentId::RustTypeId(ty)
+    }
+}
+
+impl Hash for ComponentId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            ComponentId::RustTypeId(ty) => ty.hash(state),
+            // spread the bits of small ids, like `TypeId`s already are
+            ComponentId::ExternalId(id) => state.write_u64(id.wrapping_mul(0x9e37_79b9_7f4a_7c15)),
+        }
     }
 }
 
 /// Metadata required to store a component
 #[derive(Debug, Copy, Clone)]
 pub struct TypeInfo {
-    id: TypeId,
+    id: ComponentId,
     layout: Layout,
     drop: unsafe fn(*mut u8),
 }
@@ -499,18 +571,39 @@ impl TypeInfo {
         }
 
         Self {
-            id: TypeId::of::<T>(),
+            id: ComponentId::of::<T>(),
             layout: Layout::new::<T>(),
             drop: drop_ptr::<T>,
         }
     }
 
+    /// Metadata for a component type defined at runtime, identified by `ComponentId::ExternalId(id)`
+    ///
+    /// Values of the component occupy `layout` and are dropped in place by `drop`. Every
+    /// `TypeInfo` with the same `id` must have the same layout and drop function.
+    pub fn dynamic(id: u64, layout: Layout, drop: unsafe fn(*mut u8)) -> Self {
+        Self {
+            id: ComponentId::ExternalId(id),
+            layout,
+            drop,
+        }
+    }
+
     #[allow(missing_docs)]
     #[inline]
-    pub fn id(&self) -> TypeId {
+    pub fn id(&self) -> ComponentId {
         self.id
     }
 
+    /// The Rust type of the component, if it isn't defined at runtime
+    #[inline]
+    pub fn type_id(&self) -> Option<TypeId> {
+        match self.id {
+            ComponentId::RustTypeId(ty) => Some(ty),
+            ComponentId::ExternalId(_) => None,
+        }
+    }
+
     #[allow(missing_docs)]
     #[inline]
     pub fn layout(&self) -> Layout {
@@ -529,7 +622,7 @@ impl PartialOrd for TypeInfo {
 }
 
 impl Ord for TypeInfo {
-    /// Order by alignment, descending. Ties broken with TypeId.
+    /// Order by alignment, descending. Ties broken with ComponentId.
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.layout
             .align()
@@ -552,16 +645,16 @@ fn align(x: usize, alignment: usize) -> usize {
     (x + alignment - 1) & (!alignment + 1)
 }
 
-/// A hasher optimized for hashing a single TypeId.
+/// A hasher optimized for hashing a single ComponentId.
 ///
-/// TypeId is already thoroughly hashed, so there's no reason to hash it again.
-/// Just leave the bits unchanged.
+/// TypeId is already thoroughly hashed, and external ids are spread by `ComponentId`'s `Hash`
+/// impl, so there's no reason to hash them again. Just leave the bits unchanged.
 #[derive(Default)]
-pub(crate) struct TypeIdHasher {
+pub(crate) struct ComponentIdHasher {
     hash: u64,
 }
 
-impl Hasher for TypeIdHasher {
+impl Hasher for ComponentIdHasher {
     fn write_u64(&mut self, n: u64) {
         // Only a single value can be hashed, so the old hash should be zero.
         debug_assert_eq!(self.hash, 0);
@@ -589,9 +682,9 @@ impl Hasher for TypeIdHasher {
     }
 }
 
-/// A HashMap with TypeId keys
+/// A HashMap with ComponentId keys
 ///
 /// Because TypeId is already a fully-hashed u64 (including data in the high seven bits,
 /// which hashbrown needs), there is no need to hash it again. Instead, this uses the much
 /// faster no-op hash.
-pub(crate) type TypeIdMap<V> = HashMap<TypeId, V, BuildHasherDefault<TypeIdHasher>>;
+pub(crate) type ComponentIdMap<V> = HashMap<ComponentId, V, BuildHasherDefault<ComponentIdHasher>>;
@@ -15,12 +15,17 @@
 // modified by Bevy contributors
 
 use core::{
+    any::type_name,
+    convert::TryInto,
     fmt::Debug,
     ops::{Deref, DerefMut},
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use crate::{archetype::Archetype, Component, MissingComponent};
+use crate::{
+    alloc::vec::Vec, archetype::Archetype, Component, ComponentTicks, MissingComponent, Mut,
+    SparseSets,
+};
 
 /// Atomically enforces Rust-style borrow checking at runtime
 #[derive(Debug)]
@@ -73,7 +78,7 @@ const UNIQUE_BIT: usize = !(usize::max_value() >> 1);
 /// Shared borrow of an entity's component
 #[derive(Clone)]
 pub struct Ref<'a, T: Component> {
-    archetype: &'a Archetype,
+    borrow: &'a AtomicBorrow,
     target: &'a T,
 }
 
@@ -84,14 +89,21 @@ impl<'a, T: Component> Ref<'a, T> {
     ///
     /// - the index of the component must be valid
     pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
-        let target = archetype
-            .get::<T>()
+        let (target, type_state) = archetype
+            .get_with_type_state::<T>()
             .ok_or_else(MissingComponent::new::<T>)?;
-        archetype.borrow::<T>();
-        Ok(Self {
-            archetype,
-            target: &*target.as_ptr().add(index as usize),
-        })
+        Ok(Self::from_raw(
+            &*target.as_ptr().add(index as usize),
+            type_state.borrow_state(),
+        ))
+    }
+
+    /// Borrows `target` with `borrow`, which guards every component of its kind
+    pub(crate) fn from_raw(target: &'a T, borrow: &'a AtomicBorrow) -> Self {
+        if !borrow.borrow() {
+            panic!("{} already borrowed uniquely", type_name::<T>());
+        }
+        Self { borrow, target }
     }
 }
 
@@ -100,7 +112,7 @@ unsafe impl<T: Component> Sync for Ref<'_, T> {}
 
 impl<'a, T: Component> Drop for Ref<'a, T> {
     fn drop(&mut self) {
-        self.archetype.release::<T>();
+        self.borrow.release();
     }
 }
 
@@ -123,9 +135,10 @@ where
 
 /// Unique borrow of an entity's component
 pub struct RefMut<'a, T: Component> {
-    archetype: &'a Archetype,
+    borrow: &'a AtomicBorrow,
     target: &'a mut T,
-    modified: &'a mut bool,
+    component_ticks: &'a mut ComponentTicks,
+    change_tick: u32,
 }
 
 impl<'a, T: Component> RefMut<'a, T> {
@@ -134,16 +147,38 @@ impl<'a, T: Component> RefMut<'a, T> {
     /// # Safety
     ///
     /// - the index of the component must be valid
-    pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
+    pub unsafe fn new(
+        archetype: &'a Archetype,
+        index: usize,
+        change_tick: u32,
+    ) -> Result<Self, MissingComponent> {
         let (target, type_state) = archetype
             .get_with_type_state::<T>()
             .ok_or_else(MissingComponent::new::<T>)?;
-        archetype.borrow_mut::<T>();
-        Ok(Self {
-            archetype,
-            target: &mut *target.as_ptr().add(index),
-            modified: &mut *type_state.mutated().as_ptr().add(index),
-        })
+        Ok(Self::from_raw(
+            &mut *target.as_ptr().add(index),
+            &mut *type_state.component_ticks().as_ptr().add(index),
+            type_state.borrow_state(),
+            change_tick,
+        ))
+    }
+
+    /// Uniquely borrows `target` with `borrow`, which guards every component of its kind
+    pub(crate) fn from_raw(
+        target: &'a mut T,
+        component_ticks: &'a mut ComponentTicks,
+        borrow: &'a AtomicBorrow,
+        change_tick: u32,
+    ) -> Self {
+        if !borrow.borrow_mut() {
+            panic!("{} already borrowed", type_name::<T>());
+        }
+        Self {
+            borrow,
+            target,
+            component_ticks,
+            change_tick,
+        }
     }
 }
 
@@ -152,7 +187,7 @@ unsafe impl<T: Component> Sync for RefMut<'_, T> {}
 
 impl<'a, T: Component> Drop for RefMut<'a, T> {
     fn drop(&mut self) {
-        self.archetype.release_mut::<T>();
+        self.borrow.release_mut();
     }
 }
 
@@ -166,7 +201,7 @@ impl<'a, T: Component> Deref for RefMut<'a, T> {
 
 impl<'a, T: Component> DerefMut for RefMut<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
-        *self.modified = true;
+        self.component_ticks.set_mutated(self.change_tick);
         self.target
     }
 }
@@ -180,26 +215,102 @@ where
     }
 }
 
+/// Unique borrow of the same component of several distinct entities
+///
+/// Every component of kind `T` that shares storage with one of the borrowed components is borrowed
+/// until this is dropped.
+pub struct RefMutMany<'a, T: Component, const N: usize> {
+    borrows: Vec<&'a AtomicBorrow>,
+    components: [Mut<'a, T>; N],
+}
+
+impl<'a, T: Component, const N: usize> RefMutMany<'a, T, N> {
+    /// Uniquely borrows `components` with `borrows`, which must contain each borrow guarding them
+    /// exactly once
+    pub(crate) fn from_raw(components: [Mut<'a, T>; N], borrows: Vec<&'a AtomicBorrow>) -> Self {
+        for (i, borrow) in borrows.iter().enumerate() {
+            if !borrow.borrow_mut() {
+                for borrow in &borrows[..i] {
+                    borrow.release_mut();
+                }
+                panic!("{} already borrowed", type_name::<T>());
+            }
+        }
+        Self {
+            borrows,
+            components,
+        }
+    }
+
+    /// Mutably borrows every component, in the order their entities were given
+    pub fn get_mut(&mut self) -> [Mut<'_, T>; N] {
+        let components = self
+            .components
+            .iter_mut()
+            .map(|component| Mut {
+                value: &mut *component.value,
+                component_ticks: &mut *component.component_ticks,
+                change_tick: component.change_tick,
+            })
+            .collect::<Vec<_>>();
+        match components.try_into() {
+            Ok(components) => components,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+unsafe impl<T: Component, const N: usize> Send for RefMutMany<'_, T, N> {}
+unsafe impl<T: Component, const N: usize> Sync for RefMutMany<'_, T, N> {}
+
+impl<'a, T: Component, const N: usize> Drop for RefMutMany<'a, T, N> {
+    fn drop(&mut self) {
+        for borrow in self.borrows.iter() {
+            borrow.release_mut();
+        }
+    }
+}
+
+impl<'a, T: Component, const N: usize> Deref for RefMutMany<'a, T, N> {
+    type Target = [Mut<'a, T>; N];
+
+    fn deref(&self) -> &[Mut<'a, T>; N] {
+        &self.components
+    }
+}
+
+impl<'a, T: Component, const N: usize> Debug for RefMutMany<'a, T, N>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.components.iter().map(|component| &**component))
+            .finish()
+    }
+}
+
 /// Handle to an entity with any component types
 #[derive(Copy, Clone)]
 pub struct EntityRef<'a> {
-    archetype: Option<&'a Archetype>,
+    archetype: &'a Archetype,
+    sparse_sets: &'a SparseSets,
     index: usize,
+    change_tick: u32,
 }
 
 impl<'a> EntityRef<'a> {
-    /// Construct a `Ref` for an entity with no components
-    pub(crate) fn empty() -> Self {
+    pub(crate) unsafe fn new(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        index: usize,
+        change_tick: u32,
+    ) -> Self {
         Self {
-            archetype: None,
-            index: 0,
-        }
-    }
-
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize) -> Self {
-        Self {
-            archetype: Some(archetype),
+            archetype,
+            sparse_sets,
             index,
+            change_tick,
         }
     }
 
@@ -208,14 +319,31 @@ impl<'a> EntityRef<'a> {
     /// Panics if the component is already uniquely borrowed from another entity with the same
     /// components.
     pub fn get<T: Component>(&self) -> Option<Ref<'a, T>> {
-        Some(unsafe { Ref::new(self.archetype?, self.index).ok()? })
+        unsafe {
+            let (target, _, borrow) = self
+                .sparse_sets
+                .get_component::<T>(self.archetype, self.index)
+                .ok()?;
+            Some(Ref::from_raw(&*target.as_ptr(), borrow))
+        }
     }
 
     /// Uniquely borrow the component of type `T`, if it exists
     ///
     /// Panics if the component is already borrowed from another entity with the same components.
     pub fn get_mut<T: Component>(&self) -> Option<RefMut<'a, T>> {
-        Some(unsafe { RefMut::new(self.archetype?, self.index).ok()? })
+        unsafe {
+            let (target, component_ticks, borrow) = self
+                .sparse_sets
+                .get_component::<T>(self.archetype, self.index)
+                .ok()?;
+            Some(RefMut::from_raw(
+                &mut *target.as_ptr(),
+                &mut *component_ticks.as_ptr(),
+                borrow,
+                self.change_tick,
+            ))
+        }
     }
 }
 
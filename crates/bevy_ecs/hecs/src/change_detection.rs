@@ -0,0 +1,87 @@
+// modified by Bevy contributors
+
+/// The number of change ticks after which `World::check_change_ticks` clamps old ticks
+pub const CHECK_TICK_THRESHOLD: u32 = 518_400_000;
+
+/// The maximum age a change tick can have before it is clamped. Ticks are compared with wrapping
+/// arithmetic, so they must be clamped before they get close to `u32::MAX` ticks old.
+pub const MAX_CHANGE_AGE: u32 = u32::MAX - (2 * CHECK_TICK_THRESHOLD - 1);
+
+/// The ticks at which a component was added and last mutated
+///
+/// A change is detected by comparing these ticks against the tick at which the observer (usually a
+/// system) last ran. Observers only see changes made after their last run, no matter how many
+/// frames ago that was.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ComponentTicks {
+    pub(crate) added: u32,
+    pub(crate) mutated: u32,
+}
+
+impl ComponentTicks {
+    /// Ticks for a component added at `change_tick`. Adding a component does not count as a
+    /// mutation, so the mutated tick starts out older than any observer.
+    pub fn new(change_tick: u32) -> Self {
+        Self {
+            added: change_tick,
+            mutated: 0,
+        }
+    }
+
+    /// The tick at which the component was added
+    pub fn added(&self) -> u32 {
+        self.added
+    }
+
+    /// The tick at which the component was last mutated
+    pub fn mutated(&self) -> u32 {
+        self.mutated
+    }
+
+    /// Returns true if the component was added after `last_change_tick`
+    #[inline]
+    pub fn is_added(&self, last_change_tick: u32, change_tick: u32) -> bool {
+        is_tick_newer(self.added, last_change_tick, change_tick)
+    }
+
+    /// Returns true if the component was mutated after `last_change_tick`
+    #[inline]
+    pub fn is_mutated(&self, last_change_tick: u32, change_tick: u32) -> bool {
+        is_tick_newer(self.mutated, last_change_tick, change_tick)
+    }
+
+    /// Returns true if the component was added or mutated after `last_change_tick`
+    #[inline]
+    pub fn is_changed(&self, last_change_tick: u32, change_tick: u32) -> bool {
+        self.is_added(last_change_tick, change_tick)
+            || self.is_mutated(last_change_tick, change_tick)
+    }
+
+    /// Marks the component as mutated at `change_tick`
+    #[inline]
+    pub fn set_mutated(&mut self, change_tick: u32) {
+        self.mutated = change_tick;
+    }
+
+    pub(crate) fn check_ticks(&mut self, change_tick: u32) {
+        check_tick(&mut self.added, change_tick);
+        check_tick(&mut self.mutated, change_tick);
+    }
+}
+
+/// Returns true if `tick` happened after `last_change_tick`, as seen from `change_tick`. Handles the
+/// tick counter wrapping around as long as both ticks are less than [MAX_CHANGE_AGE] old.
+#[inline]
+pub fn is_tick_newer(tick: u32, last_change_tick: u32, change_tick: u32) -> bool {
+    let ticks_since_change = change_tick.wrapping_sub(tick);
+    let ticks_since_last_run = change_tick.wrapping_sub(last_change_tick);
+    ticks_since_change < ticks_since_last_run
+}
+
+/// Clamps `tick` so that it is never more than [MAX_CHANGE_AGE] older than `change_tick`
+#[inline]
+pub fn check_tick(tick: &mut u32, change_tick: u32) {
+    if change_tick.wrapping_sub(*tick) > MAX_CHANGE_AGE {
+        *tick = change_tick.wrapping_sub(MAX_CHANGE_AGE);
+    }
+}
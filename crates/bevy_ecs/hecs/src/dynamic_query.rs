@@ -0,0 +1,360 @@
+// modified by Bevy contributors
+
+use crate::{
+    alloc::vec::Vec, Access, Archetype, AtomicBorrow, ComponentId, ComponentSparseSet,
+    ComponentTicks, Entity, World,
+};
+use core::{fmt, ptr::NonNull, slice};
+
+/// A query over components identified by `ComponentId`s, for when the component types are only
+/// known at runtime
+///
+/// Components are fetched as raw pointers, in the order they were added with `read` and `write`.
+/// Only entities that have all of those components and the `with` ones, but none of the `without`
+/// ones, are matched.
+///
+/// # Example
+/// ```
+/// # use bevy_hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123, true));
+/// world.spawn((456,));
+///
+/// let mut query = DynamicQuery::new();
+/// query
+///     .write(ComponentId::of::<i32>())
+///     .with(ComponentId::of::<bool>());
+/// let mut borrow = world.query_dynamic(&query);
+/// let items = borrow.iter().collect::<Vec<_>>();
+/// assert_eq!(items.len(), 1);
+/// assert_eq!(items[0].entity(), a);
+/// unsafe {
+///     *items[0].get(0).cast::<i32>().as_ptr() += 1;
+/// }
+/// # drop(items);
+/// # drop(borrow);
+/// # assert_eq!(*world.get::<i32>(a).unwrap(), 124);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DynamicQuery {
+    fetches: Vec<(ComponentId, Access)>,
+    with: Vec<ComponentId>,
+    without: Vec<ComponentId>,
+}
+
+impl DynamicQuery {
+    /// Create a query that matches every entity and fetches no components
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the component with id `id` for reading
+    pub fn read(&mut self, id: ComponentId) -> &mut Self {
+        self.fetches.push((id, Access::Read));
+        self
+    }
+
+    /// Fetch the component with id `id` for reading and writing. Its change ticks are updated for
+    /// every entity the query yields.
+    pub fn write(&mut self, id: ComponentId) -> &mut Self {
+        self.fetches.push((id, Access::Write));
+        self
+    }
+
+    /// Only match entities that have the component with id `id`, without fetching it
+    pub fn with(&mut self, id: ComponentId) -> &mut Self {
+        self.with.push(id);
+        self
+    }
+
+    /// Only match entities that don't have the component with id `id`
+    pub fn without(&mut self, id: ComponentId) -> &mut Self {
+        self.without.push(id);
+        self
+    }
+
+    /// Whether entities in `archetype` may match, before checking components in sparse sets
+    fn matches_archetype(&self, world: &World, archetype: &Archetype) -> bool {
+        let sparse_sets = world.sparse_sets();
+        self.fetches
+            .iter()
+            .map(|&(id, _)| id)
+            .chain(self.with.iter().copied())
+            .all(|id| archetype.has_dynamic(id) || sparse_sets.contains(id))
+            && self.without.iter().all(|&id| !archetype.has_dynamic(id))
+    }
+}
+
+/// Where a fetched component is stored
+#[derive(Copy, Clone)]
+enum Column {
+    Table {
+        data: NonNull<u8>,
+        component_ticks: NonNull<ComponentTicks>,
+        size: usize,
+    },
+    Sparse(NonNull<ComponentSparseSet>),
+}
+
+struct MatchedArchetype<'w> {
+    archetype: &'w Archetype,
+    columns: Vec<Column>,
+}
+
+/// A borrow of a `World` sufficient to execute a `DynamicQuery`
+///
+/// The fetched components are borrowed when this is created and released when it is dropped.
+pub struct DynamicQueryBorrow<'w> {
+    archetypes: Vec<MatchedArchetype<'w>>,
+    with_sparse: Vec<&'w ComponentSparseSet>,
+    without_sparse: Vec<&'w ComponentSparseSet>,
+    accesses: Vec<Access>,
+    borrows: Vec<(&'w AtomicBorrow, Access)>,
+    change_tick: u32,
+}
+
+impl<'w> DynamicQueryBorrow<'w> {
+    /// Borrows the components fetched by `query`
+    ///
+    /// Panics if a component it writes is already borrowed, or a component it reads is already
+    /// borrowed uniquely.
+    pub(crate) fn new(world: &'w World, query: &DynamicQuery) -> Self {
+        let sparse_sets = world.sparse_sets();
+        let mut borrow = Self {
+            archetypes: Vec::new(),
+            with_sparse: query
+                .with
+                .iter()
+                .filter_map(|&id| sparse_sets.get(id))
+                .collect(),
+            without_sparse: query
+                .without
+                .iter()
+                .filter_map(|&id| sparse_sets.get(id))
+                .collect(),
+            accesses: query.fetches.iter().map(|&(_, access)| access).collect(),
+            borrows: Vec::new(),
+            change_tick: world.read_change_tick(),
+        };
+
+        for &(id, access) in query.fetches.iter() {
+            if let Some(sparse_set) = sparse_sets.get(id) {
+                borrow.borrow(sparse_set.borrow_state(), access, id);
+            }
+        }
+        for archetype in world
+            .archetypes()
+            .filter(|archetype| query.matches_archetype(world, archetype))
+        {
+            let mut columns = Vec::with_capacity(query.fetches.len());
+            for &(id, access) in query.fetches.iter() {
+                let column = match (archetype.get_column(id), archetype.get_type_state(id)) {
+                    (Some(data), Some(type_state)) => {
+                        borrow.borrow(type_state.borrow_state(), access, id);
+                        let size = archetype
+                            .types()
+                            .iter()
+                            .find(|ty| ty.id() == id)
+                            .unwrap()
+                            .layout()
+                            .size();
+                        Column::Table {
+                            data,
+                            component_ticks: type_state.component_ticks(),
+                            size,
+                        }
+                    }
+                    _ => Column::Sparse(NonNull::from(sparse_sets.get(id).unwrap())),
+                };
+                columns.push(column);
+            }
+            borrow
+                .archetypes
+                .push(MatchedArchetype { archetype, columns });
+        }
+        borrow
+    }
+
+    fn borrow(&mut self, state: &'w AtomicBorrow, access: Access, id: ComponentId) {
+        let borrowed = match access {
+            Access::Write => state.borrow_mut(),
+            _ => state.borrow(),
+        };
+        if !borrowed {
+            // the borrows taken so far are released when `self` is dropped
+            match access {
+                Access::Write => panic!("{:?} already borrowed", id),
+                _ => panic!("{:?} already borrowed uniquely", id),
+            }
+        }
+        self.borrows.push((state, access));
+    }
+
+    /// Execute the query
+    pub fn iter<'q>(&'q mut self) -> DynamicQueryIter<'q, 'w> {
+        let borrow: &'q Self = self;
+        DynamicQueryIter {
+            borrow,
+            archetypes: borrow.archetypes.iter(),
+            current: None,
+            index: 0,
+        }
+    }
+}
+
+impl<'w> Drop for DynamicQueryBorrow<'w> {
+    fn drop(&mut self) {
+        for (state, access) in self.borrows.iter() {
+            match access {
+                Access::Write => state.release_mut(),
+                _ => state.release(),
+            }
+        }
+    }
+}
+
+impl<'w> fmt::Debug for DynamicQueryBorrow<'w> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicQueryBorrow")
+            .field("archetypes", &self.archetypes.len())
+            .field("change_tick", &self.change_tick)
+            .finish()
+    }
+}
+
+impl<'q, 'w> IntoIterator for &'q mut DynamicQueryBorrow<'w> {
+    type IntoIter = DynamicQueryIter<'q, 'w>;
+    type Item = DynamicItem<'q>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entities matched by a `DynamicQuery`
+pub struct DynamicQueryIter<'q, 'w> {
+    borrow: &'q DynamicQueryBorrow<'w>,
+    archetypes: slice::Iter<'q, MatchedArchetype<'w>>,
+    current: Option<&'q MatchedArchetype<'w>>,
+    index: usize,
+}
+
+impl<'q, 'w> DynamicQueryIter<'q, 'w> {
+    /// Whether `entity` has the fetched and filtered components stored in sparse sets
+    fn matches_sparse(&self, archetype: &MatchedArchetype<'w>, entity: Entity) -> bool {
+        archetype.columns.iter().all(|column| match column {
+            Column::Table { .. } => true,
+            // SAFE: the sparse sets outlive the borrow
+            Column::Sparse(sparse_set) => unsafe { sparse_set.as_ref().contains(entity) },
+        }) && self
+            .borrow
+            .with_sparse
+            .iter()
+            .all(|sparse_set| sparse_set.contains(entity))
+            && !self
+                .borrow
+                .without_sparse
+                .iter()
+                .any(|sparse_set| sparse_set.contains(entity))
+    }
+}
+
+impl<'q, 'w> Iterator for DynamicQueryIter<'q, 'w> {
+    type Item = DynamicItem<'q>;
+
+    fn next(&mut self) -> Option<DynamicItem<'q>> {
+        loop {
+            let archetype = match self.current {
+                Some(archetype) if self.index < archetype.archetype.len() => archetype,
+                _ => {
+                    self.current = Some(self.archetypes.next()?);
+                    self.index = 0;
+                    continue;
+                }
+            };
+            let index = self.index;
+            self.index += 1;
+
+            let entity = archetype.archetype.get_entity(index);
+            if !self.matches_sparse(archetype, entity) {
+                continue;
+            }
+            let item = DynamicItem {
+                entity,
+                index,
+                columns: &archetype.columns,
+            };
+            for (i, &access) in self.borrow.accesses.iter().enumerate() {
+                if access == Access::Write {
+                    // SAFE: the component is borrowed uniquely and the entity matched
+                    unsafe {
+                        (*item.component_ticks(i).as_ptr()).set_mutated(self.borrow.change_tick);
+                    }
+                }
+            }
+            return Some(item);
+        }
+    }
+}
+
+/// An entity matched by a `DynamicQuery`, with pointers to its fetched components
+#[derive(Clone)]
+pub struct DynamicItem<'q> {
+    entity: Entity,
+    index: usize,
+    columns: &'q [Column],
+}
+
+impl<'q> DynamicItem<'q> {
+    /// The matched entity
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// The number of fetched components
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Whether the query fetches no components
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// A pointer to the `i`th fetched component, in the order they were added to the query
+    ///
+    /// The pointer may only be written through if the component was fetched with `write`, and only
+    /// while the `DynamicQueryBorrow` is alive.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> NonNull<u8> {
+        match self.columns[i] {
+            Column::Table { data, size, .. } => unsafe {
+                NonNull::new_unchecked(data.as_ptr().add(size * self.index))
+            },
+            Column::Sparse(sparse_set) => unsafe {
+                sparse_set.as_ref().get_with_ticks(self.entity).unwrap().0
+            },
+        }
+    }
+
+    fn component_ticks(&self, i: usize) -> NonNull<ComponentTicks> {
+        match self.columns[i] {
+            Column::Table {
+                component_ticks, ..
+            } => unsafe { NonNull::new_unchecked(component_ticks.as_ptr().add(self.index)) },
+            Column::Sparse(sparse_set) => unsafe {
+                sparse_set.as_ref().get_with_ticks(self.entity).unwrap().1
+            },
+        }
+    }
+}
+
+impl<'q> fmt::Debug for DynamicItem<'q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicItem")
+            .field("entity", &self.entity)
+            .field("components", &self.columns.len())
+            .finish()
+    }
+}
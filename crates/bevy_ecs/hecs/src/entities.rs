@@ -1,4 +1,4 @@
-use alloc::{boxed::Box, vec::Vec};
+use alloc::{boxed::Box, vec, vec::Vec};
 use core::{
     convert::TryFrom,
     fmt, mem,
@@ -233,12 +233,6 @@ impl Entities {
         if meta.generation != entity.generation {
             return Err(NoSuchEntity);
         }
-        if meta.location.archetype == 0 {
-            return Ok(Location {
-                archetype: 0,
-                index: usize::max_value(),
-            });
-        }
         Ok(meta.location)
     }
 
@@ -314,6 +308,56 @@ impl Entities {
         self.reserved = new_reserved.into();
     }
 
+    pub fn snapshot(&self) -> EntitiesSnapshot {
+        let free_cursor = self.free_cursor.load(Ordering::Relaxed);
+        EntitiesSnapshot {
+            generations: self.meta.iter().map(|meta| meta.generation).collect(),
+            free: self.free[..free_cursor as usize].to_vec(),
+        }
+    }
+
+    /// Replace the allocator state with `snapshot`, discarding all reservations
+    ///
+    /// Returns the entities that were alive in the snapshot. Their locations should be written
+    /// immediately.
+    pub fn restore(&mut self, snapshot: &EntitiesSnapshot) -> Vec<Entity> {
+        let len = snapshot.generations.len();
+        let mut alive = vec![true; len];
+        for &id in snapshot.free.iter() {
+            alive[id as usize] = false;
+        }
+
+        self.meta = snapshot
+            .generations
+            .iter()
+            .map(|&generation| EntityMeta {
+                generation,
+                location: Location {
+                    archetype: 0,
+                    index: usize::max_value(), // dummy value, to be filled in
+                },
+            })
+            .collect();
+        self.free = snapshot.free.clone();
+        self.free.resize(len, 0);
+        // Not racey due to &mut self
+        self.free_cursor
+            .store(snapshot.free.len() as u32, Ordering::Relaxed);
+        self.pending.store(0, Ordering::Relaxed);
+        self.reserved = (0..len).map(|_| AtomicU32::new(0)).collect();
+        self.reserved_cursor.store(0, Ordering::Relaxed);
+
+        alive
+            .iter()
+            .enumerate()
+            .filter(|(_, &alive)| alive)
+            .map(|(id, _)| Entity {
+                id: id as u32,
+                generation: snapshot.generations[id],
+            })
+            .collect()
+    }
+
     pub fn get_reserver(&self) -> EntityReserver {
         // SAFE: reservers use atomics for anything write-related
         let entities: &'static Entities = unsafe { mem::transmute(self) };
@@ -321,6 +365,15 @@ impl Entities {
     }
 }
 
+/// The state of a `World`'s entity allocator: the generation of every entity ID and which IDs are
+/// free. Restoring it with `World::restore_entities` brings back the entities that were alive with
+/// the same IDs and generations.
+#[derive(Debug, Clone, Default)]
+pub struct EntitiesSnapshot {
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
 /// Reserves entities in a way that is usable in multi-threaded contexts.
 #[derive(Debug)]
 pub struct EntityReserver {
@@ -23,12 +23,11 @@ use crate::alloc::{
 
 use bevy_utils::HashSet;
 use core::{
-    any::TypeId,
     mem::{self, MaybeUninit},
     ptr,
 };
 
-use crate::{archetype::TypeInfo, Component, DynamicBundle};
+use crate::{archetype::TypeInfo, Component, ComponentId, DynamicBundle};
 
 /// Helper for incrementally constructing a bundle of components with dynamic component types
 ///
@@ -47,8 +46,8 @@ pub struct EntityBuilder {
     storage: Box<[MaybeUninit<u8>]>,
     cursor: usize,
     info: Vec<(TypeInfo, usize)>,
-    ids: Vec<TypeId>,
-    id_set: HashSet<TypeId>,
+    ids: Vec<ComponentId>,
+    id_set: HashSet<ComponentId>,
 }
 
 impl EntityBuilder {
@@ -65,7 +64,7 @@ impl EntityBuilder {
 
     /// Add `component` to the entity
     pub fn add<T: Component>(&mut self, component: T) -> &mut Self {
-        if !self.id_set.insert(TypeId::of::<T>()) {
+        if !self.id_set.insert(ComponentId::of::<T>()) {
             return self;
         }
         let end = self.cursor + mem::size_of::<T>();
@@ -86,6 +85,53 @@ impl EntityBuilder {
         self
     }
 
+    /// Add a component described by `info`, moving it out of `component`
+    ///
+    /// This is how components defined at runtime, with `TypeInfo::dynamic`, are added to entities.
+    /// The value behind `component` must not be used or dropped afterwards.
+    ///
+    /// # Safety
+    /// `component` must point to a valid value of the type described by `info`, and `info` must
+    /// have the same layout and drop function as every other `TypeInfo` with its id.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// # use core::alloc::Layout;
+    /// unsafe fn drop_nothing(_: *mut u8) {}
+    ///
+    /// let health = TypeInfo::dynamic(1, Layout::new::<f32>(), drop_nothing);
+    /// let mut world = World::new();
+    /// let mut builder = EntityBuilder::new();
+    /// let mut value = 100.0f32;
+    /// unsafe {
+    ///     builder.add_dynamic(health, (&mut value as *mut f32).cast());
+    /// }
+    /// let e = world.spawn(builder.build());
+    /// assert!(world.has_component(e, health.id()));
+    /// ```
+    pub unsafe fn add_dynamic(&mut self, info: TypeInfo, component: *const u8) -> &mut Self {
+        if !self.id_set.insert(info.id()) {
+            info.drop(component as *mut u8);
+            return self;
+        }
+        let size = info.layout().size();
+        let end = self.cursor + size;
+        if end > self.storage.len() {
+            self.grow(end);
+        }
+        if size != 0 {
+            ptr::copy_nonoverlapping(
+                component,
+                self.storage.as_mut_ptr().add(self.cursor).cast(),
+                size,
+            );
+        }
+        self.info.push((info, self.cursor));
+        self.cursor += size;
+        self
+    }
+
     fn grow(&mut self, min_size: usize) {
         let new_len = min_size.next_power_of_two().max(64);
         let mut new_storage = vec![MaybeUninit::uninit(); new_len].into_boxed_slice();
@@ -166,7 +212,7 @@ pub struct BuiltEntity<'a> {
 }
 
 impl DynamicBundle for BuiltEntity<'_> {
-    fn with_ids<T>(&self, f: impl FnOnce(&[TypeId]) -> T) -> T {
+    fn with_ids<T>(&self, f: impl FnOnce(&[ComponentId]) -> T) -> T {
         f(&self.builder.ids)
     }
 
@@ -175,7 +221,7 @@ impl DynamicBundle for BuiltEntity<'_> {
         self.builder.info.iter().map(|x| x.0).collect()
     }
 
-    unsafe fn put(self, mut f: impl FnMut(*mut u8, TypeId, usize) -> bool) {
+    unsafe fn put(self, mut f: impl FnMut(*mut u8, ComponentId, usize) -> bool) {
         for (ty, offset) in self.builder.info.drain(..) {
             let ptr = self.builder.storage.as_mut_ptr().add(offset).cast();
             if !f(ptr, ty.id(), ty.layout().size()) {
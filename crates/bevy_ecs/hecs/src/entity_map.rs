@@ -0,0 +1,127 @@
+use crate::{Entity, World};
+use bevy_utils::HashMap;
+use core::fmt;
+use std::collections::hash_map::Entry;
+
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// Error indicating that an entity couldn't be remapped
+#[derive(Debug)]
+pub enum MapEntitiesError {
+    /// The entity is not a key of the `EntityMap`
+    EntityNotFound(Entity),
+}
+
+#[cfg(feature = "std")]
+impl Error for MapEntitiesError {}
+
+impl fmt::Display for MapEntitiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MapEntitiesError::EntityNotFound(_) => {
+                f.write_str("The given entity does not exist in the map.")
+            }
+        }
+    }
+}
+
+/// Components that reference other entities, which need to be remapped when the entities are
+/// copied or moved to another `World`
+pub trait MapEntities {
+    /// Replaces every referenced entity by the one it maps to in `entity_map`
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError>;
+}
+
+/// Remaps the entities referenced by the component of an entity whose type the mapper was
+/// registered for, if it has one
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EntityMapper {
+    pub map: fn(&mut World, Entity, &EntityMap) -> Result<(), MapEntitiesError>,
+    /// Fails if the component references an entity that isn't a key of `selection`, which must map
+    /// each of its keys to itself. Leaves the component and its change ticks untouched.
+    pub check: fn(&mut World, Entity, &EntityMap) -> Result<(), MapEntitiesError>,
+}
+
+impl EntityMapper {
+    pub fn of<T: MapEntities + Send + Sync + 'static>() -> Self {
+        Self {
+            map: map_component_entities::<T>,
+            check: check_component_entities::<T>,
+        }
+    }
+}
+
+fn map_component_entities<T: MapEntities + Send + Sync + 'static>(
+    world: &mut World,
+    entity: Entity,
+    entity_map: &EntityMap,
+) -> Result<(), MapEntitiesError> {
+    match world.get_mut::<T>(entity) {
+        Ok(mut component) => component.map_entities(entity_map),
+        Err(_) => Ok(()),
+    }
+}
+
+fn check_component_entities<T: MapEntities + Send + Sync + 'static>(
+    world: &mut World,
+    entity: Entity,
+    selection: &EntityMap,
+) -> Result<(), MapEntitiesError> {
+    // mapping entities to themselves leaves the component as it is, even when it fails halfway
+    match world.get_mut_untracked::<T>(entity) {
+        Ok(component) => component.map_entities(selection),
+        Err(_) => Ok(()),
+    }
+}
+
+/// A mapping from the entities of one `World` to the entities of another
+#[derive(Default, Debug)]
+pub struct EntityMap {
+    map: HashMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    /// Maps `from` to `to`
+    pub fn insert(&mut self, from: Entity, to: Entity) {
+        self.map.insert(from, to);
+    }
+
+    /// Removes the mapping of `entity`
+    pub fn remove(&mut self, entity: Entity) {
+        self.map.remove(&entity);
+    }
+
+    #[allow(missing_docs)]
+    pub fn entry(&mut self, entity: Entity) -> Entry<'_, Entity, Entity> {
+        self.map.entry(entity)
+    }
+
+    /// The entity `entity` maps to
+    pub fn get(&self, entity: Entity) -> Result<Entity, MapEntitiesError> {
+        self.map
+            .get(&entity)
+            .cloned()
+            .ok_or(MapEntitiesError::EntityNotFound(entity))
+    }
+
+    /// The entities that are mapped
+    pub fn keys(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.map.keys().cloned()
+    }
+
+    /// The entities they are mapped to
+    pub fn values(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.map.values().cloned()
+    }
+
+    /// The number of mapped entities
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[allow(missing_docs)]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
@@ -0,0 +1,100 @@
+// modified by Bevy contributors
+
+use crate::{alloc::vec::Vec, ComponentId, Entity, World};
+use alloc::sync::Arc;
+use bevy_utils::HashMap;
+use core::fmt;
+
+/// A function run by the `World` when a component is inserted, replaced or removed
+pub type ComponentHook = Arc<dyn Fn(&mut World, Entity) + Send + Sync>;
+
+/// The point in a component's lifetime at which a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookKind {
+    Insert,
+    Replace,
+    Remove,
+}
+
+#[derive(Default)]
+struct ComponentHooks {
+    on_insert: Vec<ComponentHook>,
+    on_replace: Vec<ComponentHook>,
+    on_remove: Vec<ComponentHook>,
+}
+
+impl ComponentHooks {
+    fn get(&self, kind: HookKind) -> &[ComponentHook] {
+        match kind {
+            HookKind::Insert => &self.on_insert,
+            HookKind::Replace => &self.on_replace,
+            HookKind::Remove => &self.on_remove,
+        }
+    }
+
+    fn get_mut(&mut self, kind: HookKind) -> &mut Vec<ComponentHook> {
+        match kind {
+            HookKind::Insert => &mut self.on_insert,
+            HookKind::Replace => &mut self.on_replace,
+            HookKind::Remove => &mut self.on_remove,
+        }
+    }
+}
+
+/// The lifecycle hooks registered on a `World`, by component type
+#[derive(Default)]
+pub(crate) struct Hooks {
+    hooks: HashMap<ComponentId, ComponentHooks>,
+    /// The hooks that are currently running, so that a hook that triggers its own hooks again
+    /// (for example by despawning its entity) doesn't recurse
+    running: Vec<(Entity, ComponentId, HookKind)>,
+}
+
+impl Hooks {
+    pub fn add(&mut self, ty: ComponentId, kind: HookKind, hook: ComponentHook) {
+        self.hooks.entry(ty).or_default().get_mut(kind).push(hook);
+    }
+
+    /// Filters `types` down to the ones with registered hooks
+    pub fn hooked(&self, types: &[ComponentId]) -> Vec<ComponentId> {
+        if self.hooks.is_empty() {
+            return Vec::new();
+        }
+        types
+            .iter()
+            .copied()
+            .filter(|ty| self.hooks.contains_key(ty))
+            .collect()
+    }
+
+    /// Collects the `kind` hooks of each of `types`, so they can be run while the world is
+    /// borrowed mutably
+    pub fn collect(&self, types: &[ComponentId], kind: HookKind) -> Vec<ComponentHook> {
+        types
+            .iter()
+            .filter_map(|ty| self.hooks.get(ty))
+            .flat_map(|hooks| hooks.get(kind).iter().cloned())
+            .collect()
+    }
+
+    /// Marks the `kind` hooks of `ty` as running for `entity`. Returns false if they already are.
+    pub fn start(&mut self, entity: Entity, ty: ComponentId, kind: HookKind) -> bool {
+        if self.running.contains(&(entity, ty, kind)) {
+            return false;
+        }
+        self.running.push((entity, ty, kind));
+        true
+    }
+
+    pub fn finish(&mut self, entity: Entity, ty: ComponentId, kind: HookKind) {
+        if let Some(index) = self.running.iter().rposition(|&x| x == (entity, ty, kind)) {
+            self.running.remove(index);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.hooks.keys()).finish()
+    }
+}
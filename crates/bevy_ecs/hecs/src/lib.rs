@@ -67,25 +67,46 @@ macro_rules! smaller_tuples_too {
 mod archetype;
 mod borrow;
 mod bundle;
+mod change_detection;
+mod dynamic_query;
 mod entities;
 mod entity_builder;
+mod entity_map;
+mod hooks;
 mod query;
 mod query_one;
+mod query_state;
 #[cfg(feature = "serde")]
 mod serde;
+mod snapshot;
+mod sparse_set;
 mod world;
 
-pub use archetype::{Archetype, TypeState};
-pub use borrow::{AtomicBorrow, Ref, RefMut};
+#[cfg(feature = "serde")]
+pub use crate::serde::SerializedEntity;
+pub use archetype::{Archetype, ComponentId, TypeState};
+pub use borrow::{AtomicBorrow, Ref, RefMut, RefMutMany};
 pub use bundle::{Bundle, DynamicBundle, MissingComponent};
-pub use entities::{Entity, EntityReserver, Location, NoSuchEntity};
+pub use change_detection::{
+    check_tick, is_tick_newer, ComponentTicks, CHECK_TICK_THRESHOLD, MAX_CHANGE_AGE,
+};
+pub use dynamic_query::{DynamicItem, DynamicQuery, DynamicQueryBorrow, DynamicQueryIter};
+pub use entities::{EntitiesSnapshot, Entity, EntityReserver, Location, NoSuchEntity};
 pub use entity_builder::{BuiltEntity, EntityBuilder};
+pub use entity_map::{EntityMap, MapEntities, MapEntitiesError};
+pub use hooks::ComponentHook;
 pub use query::{
     Access, Added, BatchedIter, Changed, Mut, Mutated, Or, Query, QueryBorrow, QueryIter,
     ReadOnlyFetch, With, Without,
 };
 pub use query_one::QueryOne;
-pub use world::{ArchetypesGeneration, Component, ComponentError, SpawnBatchIter, World};
+pub use query_state::{MatchedArchetypes, QueryState};
+pub use snapshot::{Column, ColumnWriter, ComponentsSnapshot};
+pub use sparse_set::{ComponentSparseSet, SparseSets, StorageType};
+pub use world::{
+    ArchetypesGeneration, Component, ComponentError, MoveEntitiesError, SpawnBatchIter, World,
+    WorldId,
+};
 
 // Unstable implementation details needed by the macros
 #[doc(hidden)]
@@ -99,4 +120,4 @@ pub use lazy_static;
 pub use query::Fetch;
 
 #[cfg(feature = "macros")]
-pub use bevy_hecs_macros::Bundle;
+pub use bevy_hecs_macros::{Bundle, SystemParam};
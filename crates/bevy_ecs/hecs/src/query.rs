@@ -15,12 +15,16 @@
 // modified by Bevy contributors
 
 use core::{
+    any::TypeId,
     marker::PhantomData,
     ops::{Deref, DerefMut},
     ptr::NonNull,
 };
 
-use crate::{archetype::Archetype, Component, Entity, MissingComponent};
+use crate::{
+    archetype::Archetype, Component, ComponentId, ComponentSparseSet, ComponentTicks, Entity,
+    MissingComponent, SparseSets,
+};
 
 /// A collection of component types to fetch from a `World`
 pub trait Query {
@@ -40,19 +44,46 @@ pub trait Fetch<'a>: Sized {
     #[allow(clippy::declare_interior_mutable_const)] // no const fn in traits
     const DANGLING: Self;
 
-    /// How this query will access `archetype`, if at all
-    fn access(archetype: &Archetype) -> Option<Access>;
+    /// How this query will access `archetype`, if at all. Components stored in `sparse_sets` are
+    /// not part of the archetype, so accessing them only counts as `Access::Iterate`.
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access>;
+
+    /// Calls `f` with every component type this query reads or writes, and how
+    fn component_access(f: &mut dyn FnMut(TypeId, Access));
 
     /// Acquire dynamic borrows from `archetype`
     fn borrow(archetype: &Archetype);
-    /// Construct a `Fetch` for `archetype` if it should be traversed
+    /// Construct a `Fetch` for `archetype` if it should be traversed. Changes made after
+    /// `last_change_tick` are visible to change detecting queries, and mutations are recorded
+    /// with `change_tick`.
     ///
     /// # Safety
     /// `offset` must be in bounds of `archetype`
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self>;
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self>;
     /// Release dynamic borrows acquired by `borrow`
     fn release(archetype: &Archetype);
 
+    /// Acquire dynamic borrows from the sparse sets in `sparse_sets`. Unlike `borrow`, this is
+    /// called once per query rather than once per archetype.
+    fn borrow_sparse(sparse_sets: &SparseSets);
+    /// Release dynamic borrows acquired by `borrow_sparse`
+    fn release_sparse(sparse_sets: &SparseSets);
+
+    /// if this returns false, the nth entity is missing a component stored in a sparse set and
+    /// must not be fetched. `should_skip` always returns true for such entities.
+    ///
+    /// # Safety
+    /// shouldn't be called if there is no current item
+    unsafe fn matches(&self, _n: usize) -> bool {
+        true
+    }
+
     /// if this returns true, the nth item should be skipped during iteration
     ///
     /// # Safety
@@ -82,6 +113,99 @@ pub enum Access {
     Write,
 }
 
+/// Looks up the entities of an archetype in a sparse set
+#[derive(Copy, Clone)]
+struct SparseLookup {
+    entities: NonNull<Entity>,
+    sparse_set: NonNull<ComponentSparseSet>,
+}
+
+impl SparseLookup {
+    unsafe fn new<T: Component>(
+        archetype: &Archetype,
+        sparse_sets: &SparseSets,
+        offset: usize,
+    ) -> Option<Self> {
+        sparse_sets
+            .get(ComponentId::of::<T>())
+            .map(|sparse_set| SparseLookup {
+                entities: NonNull::new_unchecked(archetype.entities().as_ptr().add(offset)),
+                sparse_set: NonNull::from(sparse_set),
+            })
+    }
+
+    #[inline]
+    unsafe fn get(&self, n: usize) -> Option<(NonNull<u8>, NonNull<ComponentTicks>)> {
+        self.sparse_set
+            .as_ref()
+            .get_with_ticks(*self.entities.as_ptr().add(n))
+    }
+
+    #[inline]
+    unsafe fn contains(&self, n: usize) -> bool {
+        self.sparse_set
+            .as_ref()
+            .contains(*self.entities.as_ptr().add(n))
+    }
+}
+
+/// Where a fetch finds the `T` components of the entities in an archetype
+enum ComponentStorage<T> {
+    Table(NonNull<T>, NonNull<ComponentTicks>),
+    SparseSet(SparseLookup),
+}
+
+impl<T: Component> ComponentStorage<T> {
+    const DANGLING: Self = ComponentStorage::Table(NonNull::dangling(), NonNull::dangling());
+
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets, access: Access) -> Option<Access> {
+        if archetype.has::<T>() {
+            Some(access)
+        } else if sparse_sets.contains(ComponentId::of::<T>()) {
+            Some(Access::Iterate)
+        } else {
+            None
+        }
+    }
+
+    unsafe fn get(archetype: &Archetype, sparse_sets: &SparseSets, offset: usize) -> Option<Self> {
+        match archetype.get_with_type_state::<T>() {
+            Some((components, type_state)) => Some(ComponentStorage::Table(
+                NonNull::new_unchecked(components.as_ptr().add(offset)),
+                NonNull::new_unchecked(type_state.component_ticks().as_ptr().add(offset)),
+            )),
+            None => SparseLookup::new::<T>(archetype, sparse_sets, offset)
+                .map(ComponentStorage::SparseSet),
+        }
+    }
+
+    #[inline]
+    unsafe fn contains(&self, n: usize) -> bool {
+        match self {
+            ComponentStorage::Table(..) => true,
+            ComponentStorage::SparseSet(lookup) => lookup.contains(n),
+        }
+    }
+
+    /// The component of the nth entity, which must have one
+    #[inline]
+    unsafe fn component(&self, n: usize) -> *mut T {
+        match self {
+            ComponentStorage::Table(components, _) => components.as_ptr().add(n),
+            ComponentStorage::SparseSet(lookup) => lookup.get(n).unwrap().0.as_ptr().cast(),
+        }
+    }
+
+    /// The change ticks of the component of the nth entity, which must have one
+    #[inline]
+    unsafe fn component_ticks(&self, n: usize) -> *mut ComponentTicks {
+        match self {
+            ComponentStorage::Table(_, component_ticks) => component_ticks.as_ptr().add(n),
+            ComponentStorage::SparseSet(lookup) => lookup.get(n).unwrap().1.as_ptr(),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct EntityFetch(NonNull<Entity>);
 unsafe impl ReadOnlyFetch for EntityFetch {}
@@ -96,15 +220,23 @@ impl<'a> Fetch<'a> for EntityFetch {
     const DANGLING: Self = Self(NonNull::dangling());
 
     #[inline]
-    fn access(_archetype: &Archetype) -> Option<Access> {
+    fn access(_archetype: &Archetype, _sparse_sets: &SparseSets) -> Option<Access> {
         Some(Access::Iterate)
     }
 
+    fn component_access(_f: &mut dyn FnMut(TypeId, Access)) {}
+
     #[inline]
     fn borrow(_archetype: &Archetype) {}
 
     #[inline]
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
+    unsafe fn get(
+        archetype: &'a Archetype,
+        _sparse_sets: &'a SparseSets,
+        offset: usize,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Option<Self> {
         Some(EntityFetch(NonNull::new_unchecked(
             archetype.entities().as_ptr().add(offset),
         )))
@@ -113,6 +245,10 @@ impl<'a> Fetch<'a> for EntityFetch {
     #[inline]
     fn release(_archetype: &Archetype) {}
 
+    fn borrow_sparse(_sparse_sets: &SparseSets) {}
+
+    fn release_sparse(_sparse_sets: &SparseSets) {}
+
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Self::Item {
         *self.0.as_ptr().add(n)
@@ -124,40 +260,62 @@ impl<'a, T: Component> Query for &'a T {
 }
 
 #[doc(hidden)]
-pub struct FetchRead<T>(NonNull<T>);
+pub struct FetchRead<T>(ComponentStorage<T>);
 
 unsafe impl<T> ReadOnlyFetch for FetchRead<T> {}
 
 impl<'a, T: Component> Fetch<'a> for FetchRead<T> {
     type Item = &'a T;
 
-    const DANGLING: Self = Self(NonNull::dangling());
+    const DANGLING: Self = Self(ComponentStorage::DANGLING);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            Some(Access::Read)
-        } else {
-            None
-        }
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        ComponentStorage::<T>::access(archetype, sparse_sets, Access::Read)
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        f(TypeId::of::<T>(), Access::Read);
     }
 
     fn borrow(archetype: &Archetype) {
         archetype.borrow::<T>();
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get::<T>()
-            .map(|x| Self(NonNull::new_unchecked(x.as_ptr().add(offset))))
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Option<Self> {
+        ComponentStorage::get(archetype, sparse_sets, offset).map(Self)
     }
 
     fn release(archetype: &Archetype) {
         archetype.release::<T>();
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.borrow::<T>();
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.release::<T>();
+    }
+
+    #[inline]
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.0.contains(n)
+    }
+
+    #[inline]
+    unsafe fn should_skip(&self, n: usize) -> bool {
+        !self.0.contains(n)
+    }
+
     #[inline]
     unsafe fn fetch(&self, n: usize) -> &'a T {
-        &*self.0.as_ptr().add(n)
+        &*self.0.component(n)
     }
 }
 
@@ -172,7 +330,8 @@ impl<T: Query> Query for Option<T> {
 /// Unique borrow of an entity's component
 pub struct Mut<'a, T: Component> {
     pub(crate) value: &'a mut T,
-    pub(crate) mutated: &'a mut bool,
+    pub(crate) component_ticks: &'a mut ComponentTicks,
+    pub(crate) change_tick: u32,
 }
 
 impl<'a, T: Component> Mut<'a, T> {
@@ -180,13 +339,18 @@ impl<'a, T: Component> Mut<'a, T> {
     ///
     /// # Safety
     /// This doesn't check the bounds of index in archetype
-    pub unsafe fn new(archetype: &'a Archetype, index: usize) -> Result<Self, MissingComponent> {
+    pub unsafe fn new(
+        archetype: &'a Archetype,
+        index: usize,
+        change_tick: u32,
+    ) -> Result<Self, MissingComponent> {
         let (target, type_state) = archetype
             .get_with_type_state::<T>()
             .ok_or_else(MissingComponent::new::<T>)?;
         Ok(Self {
             value: &mut *target.as_ptr().add(index),
-            mutated: &mut *type_state.mutated().as_ptr().add(index),
+            component_ticks: &mut *type_state.component_ticks().as_ptr().add(index),
+            change_tick,
         })
     }
 }
@@ -206,7 +370,7 @@ impl<'a, T: Component> Deref for Mut<'a, T> {
 impl<'a, T: Component> DerefMut for Mut<'a, T> {
     #[inline]
     fn deref_mut(&mut self) -> &mut T {
-        *self.mutated = true;
+        self.component_ticks.set_mutated(self.change_tick);
         self.value
     }
 }
@@ -221,45 +385,64 @@ impl<'a, T: Component> Query for Mut<'a, T> {
     type Fetch = FetchMut<T>;
 }
 #[doc(hidden)]
-pub struct FetchMut<T>(NonNull<T>, NonNull<bool>);
+pub struct FetchMut<T>(ComponentStorage<T>, u32);
 
 impl<'a, T: Component> Fetch<'a> for FetchMut<T> {
     type Item = Mut<'a, T>;
 
-    const DANGLING: Self = Self(NonNull::dangling(), NonNull::dangling());
+    const DANGLING: Self = Self(ComponentStorage::DANGLING, 0);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            Some(Access::Write)
-        } else {
-            None
-        }
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        ComponentStorage::<T>::access(archetype, sparse_sets, Access::Write)
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        f(TypeId::of::<T>(), Access::Write);
     }
 
     fn borrow(archetype: &Archetype) {
         archetype.borrow_mut::<T>();
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get_with_type_state::<T>()
-            .map(|(components, type_state)| {
-                Self(
-                    NonNull::new_unchecked(components.as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.mutated().as_ptr().add(offset)),
-                )
-            })
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        _last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        ComponentStorage::get(archetype, sparse_sets, offset)
+            .map(|storage| Self(storage, change_tick))
     }
 
     fn release(archetype: &Archetype) {
         archetype.release_mut::<T>();
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.borrow_mut::<T>();
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.release_mut::<T>();
+    }
+
+    #[inline]
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.0.contains(n)
+    }
+
+    #[inline]
+    unsafe fn should_skip(&self, n: usize) -> bool {
+        !self.0.contains(n)
+    }
+
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Mut<'a, T> {
         Mut {
-            value: &mut *self.0.as_ptr().add(n),
-            mutated: &mut *self.1.as_ptr().add(n),
+            value: &mut *self.0.component(n),
+            component_ticks: &mut *self.0.component_ticks(n),
+            change_tick: self.1,
         }
     }
 }
@@ -275,22 +458,34 @@ macro_rules! impl_or_query {
 
             const DANGLING: Self = Self(($( $T::DANGLING ),+));
 
-            fn access(archetype: &Archetype) -> Option<Access> {
+            fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
                 let mut max_access = None;
                 $(
-                max_access = max_access.max($T::access(archetype));
+                max_access = max_access.max($T::access(archetype, sparse_sets));
                 )+
                 max_access
             }
 
+            fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+                $(
+                    $T::component_access(f);
+                 )+
+            }
+
             fn borrow(archetype: &Archetype) {
                 $(
                     $T::borrow(archetype);
                  )+
             }
 
-            unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-                Some(Self(( $( $T::get(archetype, offset)?),+ )))
+            unsafe fn get(
+                archetype: &'a Archetype,
+                sparse_sets: &'a SparseSets,
+                offset: usize,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> Option<Self> {
+                Some(Self(( $( $T::get(archetype, sparse_sets, offset, last_change_tick, change_tick)?),+ )))
             }
 
             fn release(archetype: &Archetype) {
@@ -299,14 +494,36 @@ macro_rules! impl_or_query {
                  )+
             }
 
+            fn borrow_sparse(sparse_sets: &SparseSets) {
+                $(
+                    $T::borrow_sparse(sparse_sets);
+                 )+
+            }
+
+            fn release_sparse(sparse_sets: &SparseSets) {
+                $(
+                    $T::release_sparse(sparse_sets);
+                 )+
+            }
+
             #[allow(non_snake_case)]
             unsafe fn fetch(&self, n: usize) -> Self::Item {
                 let ($( $T ),+) = &self.0;
                 ($( $T.fetch(n) ),+)
             }
 
-             #[allow(non_snake_case)]
+            #[allow(non_snake_case)]
+            unsafe fn matches(&self, n: usize) -> bool {
+                let ($( $T ),+) = &self.0;
+                true $( && $T.matches(n) )+
+            }
+
+            #[allow(non_snake_case)]
             unsafe fn should_skip(&self, n: usize) -> bool {
+                // every component is fetched, so all of them have to be present
+                if !self.matches(n) {
+                    return true;
+                }
                 let ($( $T ),+) = &self.0;
                 true $( && $T.should_skip(n) )+
             }
@@ -348,8 +565,8 @@ pub struct Or<T>(PhantomData<T>);
 #[doc(hidden)]
 pub struct FetchOr<T>(T);
 
-/// Query transformer that retrieves components of type `T` that have been mutated since the start of the frame.
-/// Added components do not count as mutated.
+/// Query transformer that retrieves components of type `T` that have been mutated since the system
+/// running the query last ran. Added components do not count as mutated.
 pub struct Mutated<'a, T> {
     value: &'a T,
 }
@@ -368,54 +585,68 @@ impl<'a, T: Component> Query for Mutated<'a, T> {
 }
 
 #[doc(hidden)]
-pub struct FetchMutated<T>(NonNull<T>, NonNull<bool>);
+pub struct FetchMutated<T>(ComponentStorage<T>, u32, u32);
 
 impl<'a, T: Component> Fetch<'a> for FetchMutated<T> {
     type Item = Mutated<'a, T>;
 
-    const DANGLING: Self = Self(NonNull::dangling(), NonNull::dangling());
+    const DANGLING: Self = Self(ComponentStorage::DANGLING, 0, 0);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            Some(Access::Read)
-        } else {
-            None
-        }
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        ComponentStorage::<T>::access(archetype, sparse_sets, Access::Read)
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        f(TypeId::of::<T>(), Access::Read);
     }
 
     fn borrow(archetype: &Archetype) {
         archetype.borrow::<T>();
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get_with_type_state::<T>()
-            .map(|(components, type_state)| {
-                Self(
-                    NonNull::new_unchecked(components.as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.mutated().as_ptr().add(offset)),
-                )
-            })
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        ComponentStorage::get(archetype, sparse_sets, offset)
+            .map(|storage| Self(storage, last_change_tick, change_tick))
     }
 
     fn release(archetype: &Archetype) {
         archetype.release::<T>();
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.borrow::<T>();
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.release::<T>();
+    }
+
+    #[inline]
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.0.contains(n)
+    }
+
     unsafe fn should_skip(&self, n: usize) -> bool {
-        // skip if the current item wasn't mutated
-        !*self.1.as_ptr().add(n)
+        // skip if the current item is missing or wasn't mutated
+        !self.0.contains(n) || !(*self.0.component_ticks(n)).is_mutated(self.1, self.2)
     }
 
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Self::Item {
         Mutated {
-            value: &*self.0.as_ptr().add(n),
+            value: &*self.0.component(n),
         }
     }
 }
 
-/// Query transformer that retrieves components of type `T` that have been added since the start of the frame.
+/// Query transformer that retrieves components of type `T` that have been added since the system
+/// running the query last ran.
 pub struct Added<'a, T> {
     value: &'a T,
 }
@@ -434,55 +665,69 @@ impl<'a, T: Component> Query for Added<'a, T> {
 }
 
 #[doc(hidden)]
-pub struct FetchAdded<T>(NonNull<T>, NonNull<bool>);
+pub struct FetchAdded<T>(ComponentStorage<T>, u32, u32);
 unsafe impl<T> ReadOnlyFetch for FetchAdded<T> {}
 
 impl<'a, T: Component> Fetch<'a> for FetchAdded<T> {
     type Item = Added<'a, T>;
 
-    const DANGLING: Self = Self(NonNull::dangling(), NonNull::dangling());
+    const DANGLING: Self = Self(ComponentStorage::DANGLING, 0, 0);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            Some(Access::Read)
-        } else {
-            None
-        }
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        ComponentStorage::<T>::access(archetype, sparse_sets, Access::Read)
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        f(TypeId::of::<T>(), Access::Read);
     }
 
     fn borrow(archetype: &Archetype) {
         archetype.borrow::<T>();
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get_with_type_state::<T>()
-            .map(|(components, type_state)| {
-                Self(
-                    NonNull::new_unchecked(components.as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.added().as_ptr().add(offset)),
-                )
-            })
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        ComponentStorage::get(archetype, sparse_sets, offset)
+            .map(|storage| Self(storage, last_change_tick, change_tick))
     }
 
     fn release(archetype: &Archetype) {
         archetype.release::<T>();
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.borrow::<T>();
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.release::<T>();
+    }
+
+    #[inline]
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.0.contains(n)
+    }
+
     unsafe fn should_skip(&self, n: usize) -> bool {
-        // skip if the current item wasn't added
-        !*self.1.as_ptr().add(n)
+        // skip if the current item is missing or wasn't added
+        !self.0.contains(n) || !(*self.0.component_ticks(n)).is_added(self.1, self.2)
     }
 
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Self::Item {
         Added {
-            value: &*self.0.as_ptr().add(n),
+            value: &*self.0.component(n),
         }
     }
 }
 
-/// Query transformer that retrieves components of type `T` that have either been mutated or added since the start of the frame.
+/// Query transformer that retrieves components of type `T` that have either been mutated or added
+/// since the system running the query last ran.
 pub struct Changed<'a, T> {
     value: &'a T,
 }
@@ -501,55 +746,63 @@ impl<'a, T: Component> Query for Changed<'a, T> {
 }
 
 #[doc(hidden)]
-pub struct FetchChanged<T>(NonNull<T>, NonNull<bool>, NonNull<bool>);
+pub struct FetchChanged<T>(ComponentStorage<T>, u32, u32);
 unsafe impl<T> ReadOnlyFetch for FetchChanged<T> {}
 
 impl<'a, T: Component> Fetch<'a> for FetchChanged<T> {
     type Item = Changed<'a, T>;
 
-    const DANGLING: Self = Self(
-        NonNull::dangling(),
-        NonNull::dangling(),
-        NonNull::dangling(),
-    );
+    const DANGLING: Self = Self(ComponentStorage::DANGLING, 0, 0);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            Some(Access::Read)
-        } else {
-            None
-        }
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        ComponentStorage::<T>::access(archetype, sparse_sets, Access::Read)
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        f(TypeId::of::<T>(), Access::Read);
     }
 
     fn borrow(archetype: &Archetype) {
         archetype.borrow::<T>();
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        archetype
-            .get_with_type_state::<T>()
-            .map(|(components, type_state)| {
-                Self(
-                    NonNull::new_unchecked(components.as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.added().as_ptr().add(offset)),
-                    NonNull::new_unchecked(type_state.mutated().as_ptr().add(offset)),
-                )
-            })
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        ComponentStorage::get(archetype, sparse_sets, offset)
+            .map(|storage| Self(storage, last_change_tick, change_tick))
     }
 
     fn release(archetype: &Archetype) {
         archetype.release::<T>();
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.borrow::<T>();
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        sparse_sets.release::<T>();
+    }
+
+    #[inline]
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.0.contains(n)
+    }
+
     unsafe fn should_skip(&self, n: usize) -> bool {
-        // skip if the current item wasn't added or mutated
-        !*self.1.as_ptr().add(n) && !*self.2.as_ptr().add(n)
+        // skip if the current item is missing or wasn't added or mutated
+        !self.0.contains(n) || !(*self.0.component_ticks(n)).is_changed(self.1, self.2)
     }
 
     #[inline]
     unsafe fn fetch(&self, n: usize) -> Self::Item {
         Changed {
-            value: &*self.0.as_ptr().add(n),
+            value: &*self.0.component(n),
         }
     }
 }
@@ -563,28 +816,59 @@ impl<'a, T: Fetch<'a>> Fetch<'a> for TryFetch<T> {
 
     const DANGLING: Self = Self(None);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        Some(T::access(archetype).unwrap_or(Access::Iterate))
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        Some(T::access(archetype, sparse_sets).unwrap_or(Access::Iterate))
+    }
+
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        T::component_access(f)
     }
 
     fn borrow(archetype: &Archetype) {
         T::borrow(archetype)
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        Some(Self(T::get(archetype, offset)))
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        Some(Self(T::get(
+            archetype,
+            sparse_sets,
+            offset,
+            last_change_tick,
+            change_tick,
+        )))
     }
 
     fn release(archetype: &Archetype) {
         T::release(archetype)
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        T::borrow_sparse(sparse_sets)
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        T::release_sparse(sparse_sets)
+    }
+
     unsafe fn fetch(&self, n: usize) -> Option<T::Item> {
-        Some(self.0.as_ref()?.fetch(n))
+        let fetch = self.0.as_ref()?;
+        if fetch.matches(n) {
+            Some(fetch.fetch(n))
+        } else {
+            None
+        }
     }
 
     unsafe fn should_skip(&self, n: usize) -> bool {
-        self.0.as_ref().map_or(false, |fetch| fetch.should_skip(n))
+        self.0
+            .as_ref()
+            .map_or(false, |fetch| fetch.matches(n) && fetch.should_skip(n))
     }
 }
 
@@ -612,7 +896,7 @@ impl<T: Component, Q: Query> Query for Without<T, Q> {
 }
 
 #[doc(hidden)]
-pub struct FetchWithout<T, F>(F, PhantomData<fn(T)>);
+pub struct FetchWithout<T, F>(F, Option<SparseLookup>, PhantomData<fn(T)>);
 unsafe impl<'a, T: Component, F: Fetch<'a>> ReadOnlyFetch for FetchWithout<T, F> where
     F: ReadOnlyFetch
 {
@@ -621,37 +905,69 @@ unsafe impl<'a, T: Component, F: Fetch<'a>> ReadOnlyFetch for FetchWithout<T, F>
 impl<'a, T: Component, F: Fetch<'a>> Fetch<'a> for FetchWithout<T, F> {
     type Item = F::Item;
 
-    const DANGLING: Self = Self(F::DANGLING, PhantomData);
+    const DANGLING: Self = Self(F::DANGLING, None, PhantomData);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
         if archetype.has::<T>() {
             None
         } else {
-            F::access(archetype)
+            F::access(archetype, sparse_sets)
         }
     }
 
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        F::component_access(f)
+    }
+
     fn borrow(archetype: &Archetype) {
         F::borrow(archetype)
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
         if archetype.has::<T>() {
             return None;
         }
-        Some(Self(F::get(archetype, offset)?, PhantomData))
+        Some(Self(
+            F::get(
+                archetype,
+                sparse_sets,
+                offset,
+                last_change_tick,
+                change_tick,
+            )?,
+            SparseLookup::new::<T>(archetype, sparse_sets, offset),
+            PhantomData,
+        ))
     }
 
     fn release(archetype: &Archetype) {
         F::release(archetype)
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        F::borrow_sparse(sparse_sets)
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        F::release_sparse(sparse_sets)
+    }
+
     unsafe fn fetch(&self, n: usize) -> F::Item {
         self.0.fetch(n)
     }
 
+    unsafe fn matches(&self, n: usize) -> bool {
+        !self.1.map_or(false, |lookup| lookup.contains(n)) && self.0.matches(n)
+    }
+
     unsafe fn should_skip(&self, n: usize) -> bool {
-        self.0.should_skip(n)
+        self.1.map_or(false, |lookup| lookup.contains(n)) || self.0.should_skip(n)
     }
 }
 
@@ -681,43 +997,77 @@ impl<T: Component, Q: Query> Query for With<T, Q> {
 }
 
 #[doc(hidden)]
-pub struct FetchWith<T, F>(F, PhantomData<fn(T)>);
+pub struct FetchWith<T, F>(F, Option<SparseLookup>, PhantomData<fn(T)>);
 unsafe impl<'a, T: Component, F: Fetch<'a>> ReadOnlyFetch for FetchWith<T, F> where F: ReadOnlyFetch {}
 
 impl<'a, T: Component, F: Fetch<'a>> Fetch<'a> for FetchWith<T, F> {
     type Item = F::Item;
 
-    const DANGLING: Self = Self(F::DANGLING, PhantomData);
+    const DANGLING: Self = Self(F::DANGLING, None, PhantomData);
 
-    fn access(archetype: &Archetype) -> Option<Access> {
-        if archetype.has::<T>() {
-            F::access(archetype)
+    fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
+        if archetype.has::<T>() || sparse_sets.contains(ComponentId::of::<T>()) {
+            F::access(archetype, sparse_sets)
         } else {
             None
         }
     }
 
+    fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+        F::component_access(f)
+    }
+
     fn borrow(archetype: &Archetype) {
         F::borrow(archetype)
     }
 
-    unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-        if !archetype.has::<T>() {
-            return None;
-        }
-        Some(Self(F::get(archetype, offset)?, PhantomData))
+    unsafe fn get(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        offset: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Option<Self> {
+        let lookup = if archetype.has::<T>() {
+            None
+        } else {
+            Some(SparseLookup::new::<T>(archetype, sparse_sets, offset)?)
+        };
+        Some(Self(
+            F::get(
+                archetype,
+                sparse_sets,
+                offset,
+                last_change_tick,
+                change_tick,
+            )?,
+            lookup,
+            PhantomData,
+        ))
     }
 
     fn release(archetype: &Archetype) {
         F::release(archetype)
     }
 
+    fn borrow_sparse(sparse_sets: &SparseSets) {
+        F::borrow_sparse(sparse_sets)
+    }
+
+    fn release_sparse(sparse_sets: &SparseSets) {
+        F::release_sparse(sparse_sets)
+    }
+
     unsafe fn fetch(&self, n: usize) -> F::Item {
         self.0.fetch(n)
     }
 
+    unsafe fn matches(&self, n: usize) -> bool {
+        self.1.map_or(true, |lookup| lookup.contains(n)) && self.0.matches(n)
+    }
+
     unsafe fn should_skip(&self, n: usize) -> bool {
-        self.0.should_skip(n)
+        !self.1.map_or(true, |lookup| lookup.contains(n)) || self.0.should_skip(n)
     }
 }
 
@@ -726,15 +1076,29 @@ impl<'a, T: Component, F: Fetch<'a>> Fetch<'a> for FetchWith<T, F> {
 /// Note that borrows are not released until this object is dropped.
 pub struct QueryBorrow<'w, Q: Query> {
     archetypes: &'w [Archetype],
+    sparse_sets: &'w SparseSets,
+    matched_archetypes: Option<&'w [usize]>,
     borrowed: bool,
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
 impl<'w, Q: Query> QueryBorrow<'w, Q> {
-    pub(crate) fn new(archetypes: &'w [Archetype]) -> Self {
+    pub(crate) fn new(
+        archetypes: &'w [Archetype],
+        sparse_sets: &'w SparseSets,
+        matched_archetypes: Option<&'w [usize]>,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetypes,
+            sparse_sets,
+            matched_archetypes,
             borrowed: false,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData,
         }
     }
@@ -771,9 +1135,28 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
             );
         }
 
+        for index in 0..self.archetype_count() {
+            Q::Fetch::borrow(self.archetype(index).unwrap());
+        }
+        Q::Fetch::borrow_sparse(self.sparse_sets);
+
         self.borrowed = true;
     }
 
+    /// The number of archetypes the query visits
+    fn archetype_count(&self) -> usize {
+        self.matched_archetypes
+            .map_or(self.archetypes.len(), |matched| matched.len())
+    }
+
+    /// The `index`th archetype the query visits
+    fn archetype(&self, index: usize) -> Option<&'w Archetype> {
+        match self.matched_archetypes {
+            Some(matched) => matched.get(index).map(|&index| &self.archetypes[index]),
+            None => self.archetypes.get(index),
+        }
+    }
+
     /// Transform the query into one that requires a certain component without borrowing it
     ///
     /// This can be useful when the component needs to be borrowed elsewhere and it isn't necessary
@@ -826,7 +1209,11 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
     fn transform<R: Query>(mut self) -> QueryBorrow<'w, R> {
         let borrow = QueryBorrow {
             archetypes: self.archetypes,
+            sparse_sets: self.sparse_sets,
+            matched_archetypes: self.matched_archetypes,
             borrowed: self.borrowed,
+            last_change_tick: self.last_change_tick,
+            change_tick: self.change_tick,
             _marker: PhantomData,
         };
 
@@ -838,6 +1225,17 @@ impl<'w, Q: Query> QueryBorrow<'w, Q> {
 unsafe impl<'w, Q: Query> Send for QueryBorrow<'w, Q> {}
 unsafe impl<'w, Q: Query> Sync for QueryBorrow<'w, Q> {}
 
+impl<'w, Q: Query> Drop for QueryBorrow<'w, Q> {
+    fn drop(&mut self) {
+        if self.borrowed {
+            for index in 0..self.archetype_count() {
+                Q::Fetch::release(self.archetype(index).unwrap());
+            }
+            Q::Fetch::release_sparse(self.sparse_sets);
+        }
+    }
+}
+
 impl<'q, 'w, Q: Query> IntoIterator for &'q mut QueryBorrow<'w, Q> {
     type IntoIter = QueryIter<'q, 'w, Q>;
     type Item = <Q::Fetch as Fetch<'q>>::Item;
@@ -865,15 +1263,20 @@ impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
         loop {
             match unsafe { self.iter.next() } {
                 None => {
-                    let archetype = self.borrow.archetypes.get(self.archetype_index)?;
+                    let archetype = self.borrow.archetype(self.archetype_index)?;
                     self.archetype_index += 1;
                     unsafe {
-                        self.iter = Q::Fetch::get(archetype, 0).map_or(ChunkIter::EMPTY, |fetch| {
-                            ChunkIter {
-                                fetch,
-                                len: archetype.len(),
-                                position: 0,
-                            }
+                        self.iter = Q::Fetch::get(
+                            archetype,
+                            self.borrow.sparse_sets,
+                            0,
+                            self.borrow.last_change_tick,
+                            self.borrow.change_tick,
+                        )
+                        .map_or(ChunkIter::EMPTY, |fetch| ChunkIter {
+                            fetch,
+                            len: archetype.len(),
+                            position: 0,
                         });
                     }
                 }
@@ -890,10 +1293,9 @@ impl<'q, 'w, Q: Query> Iterator for QueryIter<'q, 'w, Q> {
 
 impl<'q, 'w, Q: Query> ExactSizeIterator for QueryIter<'q, 'w, Q> {
     fn len(&self) -> usize {
-        self.borrow
-            .archetypes
-            .iter()
-            .filter(|&x| Q::Fetch::access(x).is_some())
+        (0..self.borrow.archetype_count())
+            .filter_map(|index| self.borrow.archetype(index))
+            .filter(|&x| Q::Fetch::access(x, self.borrow.sparse_sets).is_some())
             .map(|x| x.len())
             .sum()
     }
@@ -947,14 +1349,22 @@ impl<'q, 'w, Q: Query> Iterator for BatchedIter<'q, 'w, Q> {
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let archetype = self.borrow.archetypes.get(self.archetype_index)?;
+            let archetype = self.borrow.archetype(self.archetype_index)?;
             let offset = self.batch_size * self.batch;
             if offset >= archetype.len() {
                 self.archetype_index += 1;
                 self.batch = 0;
                 continue;
             }
-            if let Some(fetch) = unsafe { Q::Fetch::get(archetype, offset) } {
+            if let Some(fetch) = unsafe {
+                Q::Fetch::get(
+                    archetype,
+                    self.borrow.sparse_sets,
+                    offset,
+                    self.borrow.last_change_tick,
+                    self.borrow.change_tick,
+                )
+            } {
                 self.batch += 1;
                 return Some(Batch {
                     _marker: PhantomData,
@@ -1001,26 +1411,45 @@ macro_rules! tuple_impl {
             const DANGLING: Self = ($($name::DANGLING,)*);
 
             #[allow(unused_variables, unused_mut)]
-            fn access(archetype: &Archetype) -> Option<Access> {
+            fn access(archetype: &Archetype, sparse_sets: &SparseSets) -> Option<Access> {
                 let mut access = Access::Iterate;
                 $(
-                    access = access.max($name::access(archetype)?);
+                    access = access.max($name::access(archetype, sparse_sets)?);
                 )*
                 Some(access)
             }
 
+            #[allow(unused_variables)]
+            fn component_access(f: &mut dyn FnMut(TypeId, Access)) {
+                $($name::component_access(f);)*
+            }
+
             #[allow(unused_variables)]
             fn borrow(archetype: &Archetype) {
                 $($name::borrow(archetype);)*
             }
             #[allow(unused_variables)]
-            unsafe fn get(archetype: &'a Archetype, offset: usize) -> Option<Self> {
-                Some(($($name::get(archetype, offset)?,)*))
+            unsafe fn get(
+                archetype: &'a Archetype,
+                sparse_sets: &'a SparseSets,
+                offset: usize,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> Option<Self> {
+                Some(($($name::get(archetype, sparse_sets, offset, last_change_tick, change_tick)?,)*))
             }
             #[allow(unused_variables)]
             fn release(archetype: &Archetype) {
                 $($name::release(archetype);)*
             }
+            #[allow(unused_variables)]
+            fn borrow_sparse(sparse_sets: &SparseSets) {
+                $($name::borrow_sparse(sparse_sets);)*
+            }
+            #[allow(unused_variables)]
+            fn release_sparse(sparse_sets: &SparseSets) {
+                $($name::release_sparse(sparse_sets);)*
+            }
 
             #[allow(unused_variables)]
             unsafe fn fetch(&self, n: usize) -> Self::Item {
@@ -1029,6 +1458,13 @@ macro_rules! tuple_impl {
                 ($($name.fetch(n),)*)
             }
 
+            #[allow(unused_variables)]
+            unsafe fn matches(&self, n: usize) -> bool {
+                #[allow(non_snake_case)]
+                let ($($name,)*) = self;
+                $($name.matches(n)&&)* true
+            }
+
             #[allow(unused_variables)]
             unsafe fn should_skip(&self, n: usize) -> bool {
                 #[allow(non_snake_case)]
@@ -4,13 +4,16 @@ use core::marker::PhantomData;
 
 use crate::{
     query::{Fetch, ReadOnlyFetch, With, Without},
-    Archetype, Component, Query,
+    Archetype, Component, Query, SparseSets,
 };
 
 /// A borrow of a `World` sufficient to execute the query `Q` on a single entity
 pub struct QueryOne<'a, Q: Query> {
     archetype: &'a Archetype,
+    sparse_sets: &'a SparseSets,
     index: usize,
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
@@ -20,10 +23,19 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     /// # Safety
     ///
     /// `index` must be in-bounds for `archetype`
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize) -> Self {
+    pub(crate) unsafe fn new(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        index: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetype,
+            sparse_sets,
             index,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData,
         }
     }
@@ -36,7 +48,13 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     /// pre-existing borrow.
     pub fn get(&mut self) -> Option<<Q::Fetch as Fetch<'_>>::Item> {
         unsafe {
-            let fetch = Q::Fetch::get(self.archetype, self.index)?;
+            let fetch = Q::Fetch::get(
+                self.archetype,
+                self.sparse_sets,
+                self.index,
+                self.last_change_tick,
+                self.change_tick,
+            )?;
             if fetch.should_skip(0) {
                 None
             } else {
@@ -63,7 +81,10 @@ impl<'a, Q: Query> QueryOne<'a, Q> {
     fn transform<R: Query>(self) -> QueryOne<'a, R> {
         QueryOne {
             archetype: self.archetype,
+            sparse_sets: self.sparse_sets,
             index: self.index,
+            last_change_tick: self.last_change_tick,
+            change_tick: self.change_tick,
             _marker: PhantomData,
         }
     }
@@ -75,7 +96,10 @@ unsafe impl<Q: Query> Sync for QueryOne<'_, Q> {}
 /// A read only borrow of a `World` sufficient to execute the query `Q` on a single entity
 pub struct ReadOnlyQueryOne<'a, Q: Query> {
     archetype: &'a Archetype,
+    sparse_sets: &'a SparseSets,
     index: usize,
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
@@ -88,10 +112,19 @@ where
     /// # Safety
     ///
     /// `index` must be in-bounds for `archetype`
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize) -> Self {
+    pub(crate) unsafe fn new(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        index: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetype,
+            sparse_sets,
             index,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData,
         }
     }
@@ -107,7 +140,13 @@ where
         Q::Fetch: ReadOnlyFetch,
     {
         unsafe {
-            let fetch = Q::Fetch::get(self.archetype, self.index)?;
+            let fetch = Q::Fetch::get(
+                self.archetype,
+                self.sparse_sets,
+                self.index,
+                self.last_change_tick,
+                self.change_tick,
+            )?;
             if fetch.should_skip(0) {
                 None
             } else {
@@ -134,7 +173,10 @@ where
     fn transform<R: Query>(self) -> QueryOne<'a, R> {
         QueryOne {
             archetype: self.archetype,
+            sparse_sets: self.sparse_sets,
             index: self.index,
+            last_change_tick: self.last_change_tick,
+            change_tick: self.change_tick,
             _marker: PhantomData,
         }
     }
@@ -0,0 +1,185 @@
+// modified by Bevy contributors
+
+use crate::{
+    alloc::vec::Vec, query::ReadOnlyFetch, ArchetypesGeneration, Fetch, Query, QueryBorrow, World,
+    WorldId,
+};
+use core::marker::PhantomData;
+
+/// The indices of the archetypes matched by a query, updated incrementally as archetypes are
+/// added to the world
+///
+/// This is the part of `QueryState` that doesn't depend on the query type, for storage in places
+/// where the query type isn't `'static`. It must always be updated and queried with the same query
+/// type, and only be used with one `World`, which is checked.
+#[derive(Debug)]
+pub struct MatchedArchetypes {
+    /// The world the archetypes were matched in, set by the first update
+    world_id: Option<WorldId>,
+    archetypes_generation: ArchetypesGeneration,
+    archetype_count: usize,
+    sparse_set_count: usize,
+    indices: Vec<usize>,
+}
+
+impl Default for MatchedArchetypes {
+    fn default() -> Self {
+        Self {
+            world_id: None,
+            archetypes_generation: ArchetypesGeneration(u64::MAX), // MAX forces the first update
+            archetype_count: 0,
+            sparse_set_count: 0,
+            indices: Vec::new(),
+        }
+    }
+}
+
+impl MatchedArchetypes {
+    /// Checks the archetypes created in `world` since the last update against `Q`
+    ///
+    /// Panics if the archetypes were matched in another world.
+    pub fn update<Q: Query>(&mut self, world: &World) {
+        match self.world_id {
+            Some(_) => self.validate_world(world),
+            None => self.world_id = Some(world.id()),
+        }
+        if self.archetypes_generation == world.archetypes_generation() {
+            return;
+        }
+        self.archetypes_generation = world.archetypes_generation();
+
+        // registering a sparse set can change the access of existing archetypes
+        let sparse_sets = world.sparse_sets();
+        if self.sparse_set_count != sparse_sets.len() {
+            self.sparse_set_count = sparse_sets.len();
+            self.archetype_count = 0;
+            self.indices.clear();
+        }
+
+        for (index, archetype) in world.archetypes().enumerate().skip(self.archetype_count) {
+            if Q::Fetch::access(archetype, sparse_sets).is_some() {
+                self.indices.push(index);
+            }
+        }
+        self.archetype_count = world.archetypes().len();
+    }
+
+    /// The indices of the matched archetypes, as of the last update
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Panics if `world` isn't the world the archetypes were matched in. Archetypes that were never
+    /// updated can be used with any world, because they match nothing.
+    fn validate_world(&self, world: &World) {
+        if let Some(world_id) = self.world_id {
+            assert!(
+                world_id == world.id(),
+                "archetypes matched in {:?} cannot be used with {:?}",
+                world_id,
+                world.id()
+            );
+        }
+    }
+
+    /// Like `World::query_with_ticks_unchecked`, but only visits the matched archetypes
+    ///
+    /// # Safety
+    /// This does not check for mutable query correctness. To be safe, make sure mutable queries
+    /// have unique access to the components they query. `Q` must be the query type the archetypes
+    /// were matched with.
+    ///
+    /// Panics if the archetypes were matched in another world.
+    pub unsafe fn query_with_ticks_unchecked<'w, Q: Query>(
+        &'w self,
+        world: &'w World,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> QueryBorrow<'w, Q> {
+        self.validate_world(world);
+        QueryBorrow::new(
+            &world.archetypes,
+            world.sparse_sets(),
+            Some(&self.indices),
+            last_change_tick,
+            change_tick,
+        )
+    }
+}
+
+/// The archetypes matching the query `Q`, cached so that repeated queries don't have to check every
+/// archetype in the world
+///
+/// The cache is updated incrementally: only the archetypes created since the last update are
+/// checked. A `QueryState` can only be used with the `World` it was created from, and panics if it
+/// is given another one.
+///
+/// # Example
+/// ```
+/// # use bevy_hecs::*;
+/// let mut world = World::new();
+/// world.spawn((123, true));
+/// let mut query = QueryState::<(&i32, &bool)>::new(&world);
+/// assert_eq!(query.query(&world).iter().count(), 1);
+///
+/// world.spawn((456, false, "abc"));
+/// assert_eq!(query.query(&world).iter().count(), 2);
+/// ```
+#[derive(Debug)]
+pub struct QueryState<Q: Query> {
+    matched_archetypes: MatchedArchetypes,
+    _marker: PhantomData<fn() -> Q>,
+}
+
+impl<Q: Query> QueryState<Q> {
+    /// Creates the query state, matching the archetypes currently in `world`
+    pub fn new(world: &World) -> Self {
+        let mut state = Self {
+            matched_archetypes: MatchedArchetypes::default(),
+            _marker: PhantomData,
+        };
+        state.update_archetypes(world);
+        state
+    }
+
+    /// Checks the archetypes created in `world` since the last update
+    pub fn update_archetypes(&mut self, world: &World) {
+        self.matched_archetypes.update::<Q>(world);
+    }
+
+    /// The indices of the archetypes matching the query, as of the last update
+    pub fn matched_archetypes(&self) -> &[usize] {
+        self.matched_archetypes.indices()
+    }
+
+    /// Like `World::query`, but only visits the matching archetypes
+    pub fn query<'w>(&'w mut self, world: &'w World) -> QueryBorrow<'w, Q>
+    where
+        Q::Fetch: ReadOnlyFetch,
+    {
+        self.update_archetypes(world);
+        // SAFE: read-only access to world and read only query prevents mutable access
+        unsafe { self.query_unchecked(world) }
+    }
+
+    /// Like `World::query_mut`, but only visits the matching archetypes
+    pub fn query_mut<'w>(&'w mut self, world: &'w mut World) -> QueryBorrow<'w, Q> {
+        self.update_archetypes(world);
+        // SAFE: unique mutable access
+        unsafe { self.query_unchecked(world) }
+    }
+
+    /// Like `World::query_unchecked`, but only visits the matching archetypes. The archetypes are
+    /// not updated.
+    ///
+    /// # Safety
+    /// This does not check for mutable query correctness. To be safe, make sure mutable queries
+    /// have unique access to the components they query.
+    pub unsafe fn query_unchecked<'w>(&'w self, world: &'w World) -> QueryBorrow<'w, Q> {
+        self.matched_archetypes.query_with_ticks_unchecked(
+            world,
+            world.last_change_tick(),
+            world.read_change_tick(),
+        )
+    }
+}
@@ -1,13 +1,132 @@
 // modified by Bevy contributors
 
-use crate::entities::Entity;
-use serde::{Serialize, Serializer};
+use crate::{entities::Entity, EntityMap, MapEntitiesError, NoSuchEntity, World};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How `Entity` and `SerializedEntity` are serialized
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Entity")]
+struct EntityRepr {
+    id: u32,
+    generation: u32,
+}
 
 impl Serialize for Entity {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_u32(self.id())
+        EntityRepr {
+            id: self.id,
+            generation: self.generation,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let EntityRepr { id, generation } = EntityRepr::deserialize(deserializer)?;
+        Ok(Entity { id, generation })
+    }
+}
+
+/// An `Entity` read from a save file or a network message, which has to be checked against a
+/// `World` or mapped to a local entity before it can be used
+///
+/// Serialized the same way as `Entity`, with its generation, so stale references can be told apart
+/// from the live entity that reused their ID.
+///
+/// # Example
+/// ```
+/// # use bevy_hecs::*;
+/// let mut world = World::new();
+/// let a = world.spawn((123,));
+/// let message = SerializedEntity::from(a);
+/// assert_eq!(message.resolve(&world), Ok(a));
+///
+/// world.despawn(a).unwrap();
+/// let b = world.spawn((456,));
+/// assert_eq!(b.id(), a.id());
+/// assert!(message.resolve(&world).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SerializedEntity(Entity);
+
+impl SerializedEntity {
+    /// The entity, if it is still alive in `world`, the world it was serialized from
+    ///
+    /// Unlike `World::contains`, this rejects entities that are only reserved, as well as IDs that
+    /// were never handed out, so it is safe to use on input that can't be trusted.
+    pub fn resolve(self, world: &World) -> Result<Entity, NoSuchEntity> {
+        let entity = self.0;
+        let location = world.get_entity_location(entity).ok_or(NoSuchEntity)?;
+        let archetype = world
+            .archetypes
+            .get(location.archetype as usize)
+            .ok_or(NoSuchEntity)?;
+        if location.index < archetype.len() && archetype.get_entity(location.index) == entity {
+            Ok(entity)
+        } else {
+            Err(NoSuchEntity)
+        }
+    }
+
+    /// The local entity that the entity maps to in `entity_map`, for entities serialized from
+    /// another world
+    pub fn map(self, entity_map: &EntityMap) -> Result<Entity, MapEntitiesError> {
+        entity_map.get(self.0)
+    }
+
+    /// The entity as it was serialized, without any check
+    pub fn unchecked(self) -> Entity {
+        self.0
+    }
+}
+
+impl From<Entity> for SerializedEntity {
+    fn from(entity: Entity) -> Self {
+        SerializedEntity(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SerializedEntity;
+    use crate::{EntityMap, World};
+
+    #[test]
+    fn entity_round_trip() {
+        let mut world = World::new();
+        let a = world.spawn((123,));
+        world.despawn(a).unwrap();
+        let b = world.spawn((456,));
+
+        let serialized = ron::to_string(&b).unwrap();
+        assert_eq!(serialized, "(id:0,generation:1)");
+        assert_eq!(ron::from_str::<crate::Entity>(&serialized).unwrap(), b);
+
+        let stale = ron::from_str::<SerializedEntity>(&ron::to_string(&a).unwrap()).unwrap();
+        assert!(stale.resolve(&world).is_err());
+        let live = ron::from_str::<SerializedEntity>(&serialized).unwrap();
+        assert_eq!(live.resolve(&world), Ok(b));
+
+        let reserved = world.reserve_entity();
+        assert!(SerializedEntity::from(reserved).resolve(&world).is_err());
+        let never_spawned = crate::Entity::from_bits(5);
+        assert!(SerializedEntity::from(never_spawned)
+            .resolve(&world)
+            .is_err());
+
+        let mut other = World::new();
+        let c = other.spawn((789,));
+        let mut entity_map = EntityMap::default();
+        entity_map.insert(b, c);
+        assert_eq!(live.map(&entity_map).unwrap(), c);
+        assert!(stale.map(&entity_map).is_err());
     }
 }
@@ -0,0 +1,156 @@
+// modified by Bevy contributors
+
+use crate::{
+    alloc::vec::Vec, archetype::TypeInfo, Archetype, Component, ComponentId, EntitiesSnapshot,
+};
+use core::{
+    any::type_name,
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// The entities of a `World` and copies of their components, taken by
+/// `World::snapshot_components` and put back by `World::restore_components`
+#[derive(Debug)]
+pub struct ComponentsSnapshot {
+    pub(crate) entities: EntitiesSnapshot,
+    /// The copied columns of each archetype, with the entities of the archetype
+    pub(crate) archetypes: Vec<Archetype>,
+    /// The copied sparse sets, each stored as an archetype with a single column
+    pub(crate) sparse_sets: Vec<Archetype>,
+}
+
+impl ComponentsSnapshot {
+    /// The types of the components in the snapshot. A type is listed once for each column of it.
+    pub fn types(&self) -> impl Iterator<Item = &TypeInfo> {
+        self.archetypes
+            .iter()
+            .chain(&self.sparse_sets)
+            .flat_map(|archetype| archetype.types())
+    }
+}
+
+/// A column of components of a single type, being copied by `World::snapshot_components` or
+/// `World::restore_components`
+pub struct Column<'a> {
+    info: TypeInfo,
+    data: NonNull<u8>,
+    len: usize,
+    marker: PhantomData<&'a ()>,
+}
+
+impl<'a> Column<'a> {
+    /// # Safety
+    /// `data` must point to `len` valid components described by `info`, which live for `'a`
+    pub(crate) unsafe fn new(info: TypeInfo, data: NonNull<u8>, len: usize) -> Self {
+        Self {
+            info,
+            data,
+            len,
+            marker: PhantomData,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn type_info(&self) -> TypeInfo {
+        self.info
+    }
+
+    #[allow(missing_docs)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[allow(missing_docs)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The components of the column, if they are `T`s
+    pub fn get<T: Component>(&self) -> Option<&'a [T]> {
+        if self.info.id() != ComponentId::of::<T>() {
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts(column_ptr::<T>(self.data), self.len) })
+    }
+}
+
+/// Uninitialized storage for a column of components of a single type, which the copy function
+/// given to `World::snapshot_components` or `World::restore_components` fills
+pub struct ColumnWriter<'a> {
+    info: TypeInfo,
+    data: NonNull<u8>,
+    len: usize,
+    written: usize,
+    marker: PhantomData<&'a mut ()>,
+}
+
+impl<'a> ColumnWriter<'a> {
+    /// # Safety
+    /// `data` must point to room for `len` components described by `info`
+    pub(crate) unsafe fn new(info: TypeInfo, data: NonNull<u8>, len: usize) -> Self {
+        Self {
+            info,
+            data,
+            len,
+            written: 0,
+            marker: PhantomData,
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn type_info(&self) -> TypeInfo {
+        self.info
+    }
+
+    /// The number of components the column holds once it is filled
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[allow(missing_docs)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes the next component of the column
+    ///
+    /// Panics if `T` isn't the type of the column, or if the column is already full.
+    pub fn push<T: Component>(&mut self, component: T) {
+        assert!(
+            self.info.id() == ComponentId::of::<T>(),
+            "cannot write {} to a column of {:?}",
+            type_name::<T>(),
+            self.info.id()
+        );
+        assert!(self.written < self.len, "the column is already full");
+        unsafe {
+            ptr::write(column_ptr::<T>(self.data).add(self.written), component);
+        }
+        self.written += 1;
+    }
+
+    /// Panics if the column wasn't filled. The components that were written are leaked then,
+    /// because they aren't part of any storage yet.
+    pub(crate) fn finish(self) {
+        assert!(
+            self.written == self.len,
+            "only {} of the {} components of a column of {:?} were written",
+            self.written,
+            self.len,
+            self.info.id()
+        );
+    }
+}
+
+/// The first component of a column stored at `data`. Zero sized components only need an aligned
+/// pointer, which the storage of a column doesn't always have.
+fn column_ptr<T>(data: NonNull<u8>) -> *mut T {
+    if mem::size_of::<T>() == 0 {
+        NonNull::dangling().as_ptr()
+    } else {
+        data.cast().as_ptr()
+    }
+}
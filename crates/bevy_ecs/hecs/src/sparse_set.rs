@@ -0,0 +1,488 @@
+// modified by Bevy contributors
+
+use crate::{
+    alloc::{
+        alloc::{alloc, dealloc, Layout},
+        vec::Vec,
+    },
+    archetype::{Archetype, ComponentIdMap, TypeInfo},
+    borrow::AtomicBorrow,
+    Column, ColumnWriter, Component, ComponentId, ComponentTicks, Entity, MissingComponent,
+};
+use core::{
+    any::type_name,
+    ptr::{self, NonNull},
+};
+
+/// Where the components of a type are stored
+///
+/// Chosen when the component type is registered with `World::register_component`. Types that
+/// were never registered use `StorageType::Table`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StorageType {
+    /// In the tables of the archetypes, next to the other components of the entity. Fastest to
+    /// iterate, but adding or removing the component moves the entity to another archetype.
+    Table,
+    /// In a sparse set indexed by entity. Adding and removing the component is cheap and doesn't
+    /// move the entity, but queries have to look up every entity they visit. Best suited for
+    /// marker components that are toggled often.
+    SparseSet,
+}
+
+impl Default for StorageType {
+    fn default() -> Self {
+        StorageType::Table
+    }
+}
+
+/// The components of a single type, stored densely and looked up by entity
+#[derive(Debug)]
+pub struct ComponentSparseSet {
+    info: TypeInfo,
+    sparse: Vec<Option<u32>>,
+    entities: Vec<Entity>,
+    component_ticks: Vec<ComponentTicks>,
+    data: NonNull<u8>,
+    capacity: usize,
+    borrow: AtomicBorrow,
+}
+
+impl ComponentSparseSet {
+    /// Creates an empty sparse set for components described by `info`
+    pub fn new(info: TypeInfo) -> Self {
+        Self {
+            info,
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            component_ticks: Vec::new(),
+            data: dangling(info.layout()),
+            capacity: 0,
+            borrow: AtomicBorrow::new(),
+        }
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn type_info(&self) -> TypeInfo {
+        self.info
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// The entities that have a component in this set, in storage order
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    #[inline]
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.id as usize)?)? as usize;
+        if self.entities[index] == entity {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns pointers to the component of `entity` and its change ticks, if it has one
+    #[inline]
+    pub fn get_with_ticks(&self, entity: Entity) -> Option<(NonNull<u8>, NonNull<ComponentTicks>)> {
+        let index = self.dense_index(entity)?;
+        unsafe {
+            Some((
+                NonNull::new_unchecked(self.data.as_ptr().add(index * self.info.layout().size())),
+                NonNull::new_unchecked(self.component_ticks.as_ptr().add(index) as *mut _),
+            ))
+        }
+    }
+
+    pub(crate) fn borrow_state(&self) -> &AtomicBorrow {
+        &self.borrow
+    }
+
+    /// Moves the component pointed to by `component` into the set. If `entity` already has a
+    /// component in this set, the old one is dropped and the replacement counts as a mutation.
+    ///
+    /// # Safety
+    /// `component` must point to a valid component of this set's type, which must not be used
+    /// afterwards
+    pub(crate) unsafe fn insert(&mut self, entity: Entity, component: *mut u8, change_tick: u32) {
+        let size = self.info.layout().size();
+        if let Some(index) = self.dense_index(entity) {
+            let target = self.data.as_ptr().add(index * size);
+            self.info.drop(target);
+            ptr::copy_nonoverlapping(component, target, size);
+            self.component_ticks[index].set_mutated(change_tick);
+            return;
+        }
+
+        let index = self.entities.len();
+        if index == self.capacity {
+            self.grow();
+        }
+        ptr::copy_nonoverlapping(component, self.data.as_ptr().add(index * size), size);
+        self.entities.push(entity);
+        self.component_ticks.push(ComponentTicks::new(change_tick));
+        let id = entity.id as usize;
+        if id >= self.sparse.len() {
+            self.sparse.resize(id + 1, None);
+        }
+        self.sparse[id] = Some(index as u32);
+    }
+
+    /// The components of every entity in the set, in storage order
+    pub(crate) fn column(&self) -> Column<'_> {
+        unsafe { Column::new(self.info, self.data, self.entities.len()) }
+    }
+
+    /// Adds components for `entities`, which must not have one in the set yet, filled in by
+    /// `write`. The entities only become part of the set once the column is filled, so nothing is
+    /// left uninitialized if `write` panics.
+    pub(crate) fn extend(
+        &mut self,
+        entities: &[Entity],
+        change_tick: u32,
+        write: impl FnOnce(&mut ColumnWriter),
+    ) {
+        let start = self.entities.len();
+        while self.capacity < start + entities.len() {
+            self.grow();
+        }
+        let size = self.info.layout().size();
+        let mut writer = unsafe {
+            ColumnWriter::new(
+                self.info,
+                NonNull::new_unchecked(self.data.as_ptr().add(size * start)),
+                entities.len(),
+            )
+        };
+        write(&mut writer);
+        writer.finish();
+
+        for (index, &entity) in (start..).zip(entities) {
+            debug_assert!(!self.contains(entity));
+            let id = entity.id as usize;
+            if id >= self.sparse.len() {
+                self.sparse.resize(id + 1, None);
+            }
+            self.sparse[id] = Some(index as u32);
+        }
+        self.entities.extend_from_slice(entities);
+        self.component_ticks
+            .resize(start + entities.len(), ComponentTicks::new(change_tick));
+    }
+
+    /// Removes the component of `entity` from the set without dropping it. Returns false if
+    /// `entity` doesn't have one.
+    ///
+    /// # Safety
+    /// The component must have been moved out of the set (e.g. with `ptr::read`) beforehand
+    pub(crate) unsafe fn remove_and_forget(&mut self, entity: Entity) -> bool {
+        let index = match self.dense_index(entity) {
+            Some(index) => index,
+            None => return false,
+        };
+        let size = self.info.layout().size();
+        let last = self.entities.len() - 1;
+        if index != last {
+            ptr::copy_nonoverlapping(
+                self.data.as_ptr().add(last * size),
+                self.data.as_ptr().add(index * size),
+                size,
+            );
+        }
+        self.entities.swap_remove(index);
+        self.component_ticks.swap_remove(index);
+        self.sparse[entity.id as usize] = None;
+        if index != last {
+            self.sparse[self.entities[index].id as usize] = Some(index as u32);
+        }
+        true
+    }
+
+    /// Drops and removes the component of `entity`. Returns false if `entity` doesn't have one.
+    pub(crate) fn remove(&mut self, entity: Entity) -> bool {
+        match self.dense_index(entity) {
+            Some(index) => unsafe {
+                self.info
+                    .drop(self.data.as_ptr().add(index * self.info.layout().size()));
+                self.remove_and_forget(entity)
+            },
+            None => false,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let size = self.info.layout().size();
+        for index in 0..self.entities.len() {
+            unsafe {
+                self.info.drop(self.data.as_ptr().add(index * size));
+            }
+        }
+        self.entities.clear();
+        self.component_ticks.clear();
+        self.sparse.clear();
+    }
+
+    /// Clamps the change ticks of every component so that they never get too old to be compared
+    pub fn check_change_ticks(&mut self, change_tick: u32) {
+        for component_ticks in self.component_ticks.iter_mut() {
+            component_ticks.check_ticks(change_tick);
+        }
+    }
+
+    fn grow(&mut self) {
+        let layout = self.info.layout();
+        let new_capacity = (self.capacity * 2).max(4);
+        if layout.size() != 0 {
+            unsafe {
+                let new_data = NonNull::new(alloc(
+                    Layout::from_size_align(layout.size() * new_capacity, layout.align()).unwrap(),
+                ))
+                .unwrap();
+                if self.capacity != 0 {
+                    ptr::copy_nonoverlapping(
+                        self.data.as_ptr(),
+                        new_data.as_ptr(),
+                        layout.size() * self.entities.len(),
+                    );
+                    dealloc(
+                        self.data.as_ptr(),
+                        Layout::from_size_align_unchecked(
+                            layout.size() * self.capacity,
+                            layout.align(),
+                        ),
+                    );
+                }
+                self.data = new_data;
+            }
+        }
+        self.capacity = new_capacity;
+    }
+}
+
+impl Drop for ComponentSparseSet {
+    fn drop(&mut self) {
+        self.clear();
+        let layout = self.info.layout();
+        if layout.size() != 0 && self.capacity != 0 {
+            unsafe {
+                dealloc(
+                    self.data.as_ptr(),
+                    Layout::from_size_align_unchecked(
+                        layout.size() * self.capacity,
+                        layout.align(),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn dangling(layout: Layout) -> NonNull<u8> {
+    // a well aligned pointer, which is all zero sized components need
+    unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }
+}
+
+/// The sparse sets of every component type registered with `StorageType::SparseSet`
+#[derive(Debug, Default)]
+pub struct SparseSets {
+    sets: ComponentIdMap<ComponentSparseSet>,
+}
+
+impl SparseSets {
+    /// Returns true if components of type `ty` are stored in a sparse set
+    #[inline]
+    pub fn contains(&self, ty: ComponentId) -> bool {
+        self.sets.contains_key(&ty)
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn get(&self, ty: ComponentId) -> Option<&ComponentSparseSet> {
+        self.sets.get(&ty)
+    }
+
+    pub(crate) fn get_mut(&mut self, ty: ComponentId) -> Option<&mut ComponentSparseSet> {
+        self.sets.get_mut(&ty)
+    }
+
+    pub(crate) fn insert(&mut self, info: TypeInfo) {
+        self.sets
+            .entry(info.id())
+            .or_insert_with(|| ComponentSparseSet::new(info));
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    #[allow(missing_docs)]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    #[allow(missing_docs)]
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentSparseSet> {
+        self.sets.values()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut ComponentSparseSet> {
+        self.sets.values_mut()
+    }
+
+    /// Borrows the `T` components immutably, if they are stored in a sparse set
+    ///
+    /// Panics if they are already borrowed mutably.
+    #[inline]
+    pub fn borrow<T: Component>(&self) {
+        if self
+            .get(ComponentId::of::<T>())
+            .map_or(false, |x| !x.borrow.borrow())
+        {
+            panic!("{} already borrowed uniquely", type_name::<T>());
+        }
+    }
+
+    /// Borrows the `T` components mutably, if they are stored in a sparse set
+    ///
+    /// Panics if they are already borrowed.
+    #[inline]
+    pub fn borrow_mut<T: Component>(&self) {
+        if self
+            .get(ComponentId::of::<T>())
+            .map_or(false, |x| !x.borrow.borrow_mut())
+        {
+            panic!("{} already borrowed", type_name::<T>());
+        }
+    }
+
+    /// Releases a borrow acquired by `borrow`
+    #[inline]
+    pub fn release<T: Component>(&self) {
+        if let Some(x) = self.get(ComponentId::of::<T>()) {
+            x.borrow.release();
+        }
+    }
+
+    /// Releases a borrow acquired by `borrow_mut`
+    #[inline]
+    pub fn release_mut<T: Component>(&self) {
+        if let Some(x) = self.get(ComponentId::of::<T>()) {
+            x.borrow.release_mut();
+        }
+    }
+
+    /// Finds the `T` component of the entity at `index` in `archetype`, whether it is stored in
+    /// the archetype or in a sparse set, along with its change ticks and borrow state
+    ///
+    /// # Safety
+    /// `index` must be in bounds of `archetype`, or be `usize::MAX` for pending entities
+    #[allow(clippy::type_complexity)]
+    pub(crate) unsafe fn get_component<'a, T: Component>(
+        &'a self,
+        archetype: &'a Archetype,
+        index: usize,
+    ) -> Result<(NonNull<T>, NonNull<ComponentTicks>, &'a AtomicBorrow), MissingComponent> {
+        if let Some((components, type_state)) = archetype.get_with_type_state::<T>() {
+            return Ok((
+                NonNull::new_unchecked(components.as_ptr().add(index)),
+                NonNull::new_unchecked(type_state.component_ticks().as_ptr().add(index)),
+                type_state.borrow_state(),
+            ));
+        }
+        let sparse_set = self
+            .get(ComponentId::of::<T>())
+            .filter(|_| index < archetype.len())
+            .ok_or_else(MissingComponent::new::<T>)?;
+        let (component, component_ticks) = sparse_set
+            .get_with_ticks(archetype.get_entity(index))
+            .ok_or_else(MissingComponent::new::<T>)?;
+        Ok((component.cast(), component_ticks, sparse_set.borrow_state()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+    use std::rc::Rc;
+
+    fn insert<T: 'static>(set: &mut ComponentSparseSet, entity: Entity, mut value: T) {
+        unsafe {
+            set.insert(entity, (&mut value as *mut T).cast(), 0);
+        }
+        mem::forget(value);
+    }
+
+    fn get<T: 'static + Copy>(set: &ComponentSparseSet, entity: Entity) -> Option<T> {
+        set.get_with_ticks(entity)
+            .map(|(ptr, _)| unsafe { *ptr.as_ptr().cast::<T>() })
+    }
+
+    #[test]
+    fn insert_remove() {
+        let mut set = ComponentSparseSet::new(TypeInfo::of::<u64>());
+        let e = |id| Entity { id, generation: 0 };
+        for id in 0..10 {
+            insert(&mut set, e(id), id as u64 * 10);
+        }
+        assert_eq!(set.len(), 10);
+        assert!(set.remove(e(3)));
+        assert!(!set.remove(e(3)));
+        assert_eq!(get::<u64>(&set, e(3)), None);
+        assert_eq!(get::<u64>(&set, e(9)), Some(90));
+        assert_eq!(set.len(), 9);
+
+        insert(&mut set, e(9), 91u64);
+        assert_eq!(get::<u64>(&set, e(9)), Some(91));
+        assert_eq!(set.len(), 9);
+        assert!(!set.contains(Entity {
+            id: 9,
+            generation: 1
+        }));
+    }
+
+    #[test]
+    fn drops_components() {
+        let counter = Rc::new(());
+        let mut set = ComponentSparseSet::new(TypeInfo::of::<Rc<()>>());
+        let e = |id| Entity { id, generation: 0 };
+        insert(&mut set, e(0), counter.clone());
+        insert(&mut set, e(1), counter.clone());
+        insert(&mut set, e(1), counter.clone());
+        assert_eq!(Rc::strong_count(&counter), 3);
+        set.remove(e(0));
+        assert_eq!(Rc::strong_count(&counter), 2);
+        drop(set);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+
+    #[test]
+    fn zero_sized_components() {
+        let mut set = ComponentSparseSet::new(TypeInfo::of::<()>());
+        for id in 0..100 {
+            insert(&mut set, Entity { id, generation: 0 }, ());
+        }
+        assert_eq!(set.len(), 100);
+    }
+}
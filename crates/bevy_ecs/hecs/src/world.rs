@@ -15,11 +15,26 @@
 // modified by Bevy contributors
 
 use crate::{
-    alloc::vec::Vec, borrow::EntityRef, query::ReadOnlyFetch, query_one::ReadOnlyQueryOne,
-    EntityReserver, Mut, RefMut,
+    alloc::vec::Vec,
+    archetype::TypeInfo,
+    borrow::EntityRef,
+    entity_map::EntityMapper,
+    hooks::{HookKind, Hooks},
+    query::ReadOnlyFetch,
+    query_one::ReadOnlyQueryOne,
+    Column, ColumnWriter, ComponentTicks, ComponentsSnapshot, EntitiesSnapshot, EntityMap,
+    EntityReserver, MapEntities, MapEntitiesError, Mut, RefMut, RefMutMany, SparseSets,
+    StorageType, CHECK_TICK_THRESHOLD,
 };
+use alloc::sync::Arc;
 use bevy_utils::{HashMap, HashSet};
-use core::{any::TypeId, fmt, mem, ptr};
+use core::{
+    any::{type_name, TypeId},
+    convert::TryInto,
+    fmt, mem,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
 
 #[cfg(feature = "std")]
 use std::error::Error;
@@ -27,8 +42,8 @@ use std::error::Error;
 use crate::{
     archetype::Archetype,
     entities::{Entities, Location},
-    Bundle, DynamicBundle, Entity, MissingComponent, NoSuchEntity, Query, QueryBorrow, QueryOne,
-    Ref,
+    AtomicBorrow, Bundle, ComponentId, DynamicBundle, DynamicQuery, DynamicQueryBorrow, Entity,
+    MissingComponent, NoSuchEntity, Query, QueryBorrow, QueryOne, Ref,
 };
 
 /// An unordered collection of entities, each having any number of distinctly typed components
@@ -37,15 +52,28 @@ use crate::{
 /// type, but far more efficient to traverse.
 ///
 /// The components of entities who have the same set of component types are stored in contiguous
-/// runs, allowing for extremely fast, cache-friendly iteration.
+/// runs, allowing for extremely fast, cache-friendly iteration. Component types registered with
+/// `StorageType::SparseSet` are stored outside of these runs instead, so adding and removing them
+/// doesn't move the entity.
+///
+/// Changes to components are recorded with the world's change tick. Systems advance the tick every
+/// time they run and only see changes made since their previous run. Queries made directly on the
+/// `World` see the changes made since the last call to `clear_trackers`.
 #[derive(Debug)]
 pub struct World {
+    id: WorldId,
     entities: Entities,
-    index: HashMap<Vec<TypeId>, u32>,
-    removed_components: HashMap<TypeId, Vec<Entity>>,
+    index: HashMap<Vec<ComponentId>, u32>,
+    removed_components: HashMap<ComponentId, Vec<Entity>>,
     #[allow(missing_docs)]
     pub archetypes: Vec<Archetype>,
+    sparse_sets: SparseSets,
+    hooks: Hooks,
+    entity_mappers: HashMap<ComponentId, EntityMapper>,
     archetype_generation: u64,
+    change_tick: AtomicU32,
+    last_change_tick: u32,
+    last_check_tick: u32,
 }
 
 impl World {
@@ -57,11 +85,18 @@ impl World {
         let mut index = HashMap::default();
         index.insert(Vec::new(), 0);
         Self {
+            id: WorldId::new(),
             entities: Entities::default(),
             index,
             archetypes,
+            sparse_sets: SparseSets::default(),
+            hooks: Hooks::default(),
+            entity_mappers: HashMap::default(),
             archetype_generation: 0,
             removed_components: HashMap::default(),
+            change_tick: AtomicU32::new(1),
+            last_change_tick: 0,
+            last_check_tick: 0,
         }
     }
 
@@ -89,21 +124,26 @@ impl World {
         self.flush();
 
         let entity = self.entities.alloc();
-        let archetype_id = components.with_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(components.type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
+        let archetype_id =
+            components.with_ids(|ids| self.get_or_insert_archetype(ids, || components.type_info()));
+        let hooked = components.with_ids(|ids| self.hooks.hooked(ids));
 
+        let change_tick = self.read_change_tick();
         let archetype = &mut self.archetypes[archetype_id as usize];
+        let sparse_sets = &mut self.sparse_sets;
         unsafe {
             let index = archetype.allocate(entity);
             components.put(|ptr, ty, size| {
-                archetype.put_dynamic(ptr, ty, size, index, true);
+                match sparse_sets.get_mut(ty) {
+                    Some(sparse_set) => sparse_set.insert(entity, ptr, change_tick),
+                    None => archetype.put_dynamic(
+                        ptr,
+                        ty,
+                        size,
+                        index,
+                        ComponentTicks::new(change_tick),
+                    ),
+                }
                 true
             });
             self.entities.meta[entity.id as usize].location = Location {
@@ -112,12 +152,14 @@ impl World {
             };
         }
 
+        self.run_hooks(entity, &hooked, HookKind::Insert);
         entity
     }
 
     /// Efficiently spawn a large number of entities with the same components
     ///
-    /// Faster than calling `spawn` repeatedly with the same components.
+    /// Faster than calling `spawn` repeatedly with the same components. The insert hooks of each
+    /// entity run as it is spawned by the returned iterator.
     ///
     /// # Example
     /// ```
@@ -140,12 +182,13 @@ impl World {
         let iter = iter.into_iter();
         let (lower, upper) = iter.size_hint();
         let archetype_id = self.reserve_inner::<I::Item>(upper.unwrap_or(lower) as u32);
+        let hooked = I::Item::with_static_ids(|ids| self.hooks.hooked(ids));
 
         SpawnBatchIter {
             inner: iter,
-            entities: &mut self.entities,
+            world: self,
             archetype_id,
-            archetype: &mut self.archetypes[archetype_id as usize],
+            hooked,
         }
     }
 
@@ -153,6 +196,27 @@ impl World {
     pub fn despawn(&mut self, entity: Entity) -> Result<(), NoSuchEntity> {
         self.flush();
 
+        if let Some(loc) = self.get_entity_location(entity) {
+            let mut types = self.archetypes[loc.archetype as usize]
+                .types()
+                .iter()
+                .map(|ty| ty.id())
+                .collect::<Vec<_>>();
+            types.extend(
+                self.sparse_sets
+                    .iter()
+                    .filter(|sparse_set| sparse_set.contains(entity))
+                    .map(|sparse_set| sparse_set.type_info().id()),
+            );
+            let hooked = self.hooks.hooked(&types);
+            self.run_hooks(entity, &hooked, HookKind::Remove);
+            // the hooks may have despawned the entity themselves
+            self.flush();
+            if !self.contains(entity) {
+                return Ok(());
+            }
+        }
+
         let loc = self.entities.free(entity)?;
         let archetype = &mut self.archetypes[loc.archetype as usize];
         if let Some(moved) = unsafe { archetype.remove(loc.index) } {
@@ -165,6 +229,15 @@ impl World {
                 .or_insert_with(Vec::new);
             removed_entities.push(entity);
         }
+        for sparse_set in self.sparse_sets.iter_mut() {
+            if sparse_set.remove(entity) {
+                let removed_entities = self
+                    .removed_components
+                    .entry(sparse_set.type_info().id())
+                    .or_insert_with(Vec::new);
+                removed_entities.push(entity);
+            }
+        }
         Ok(())
     }
 
@@ -182,35 +255,613 @@ impl World {
         self.flush();
         self.entities.reserve(additional);
 
-        let archetype_id = T::with_static_ids(|ids| {
-            self.index.get(ids).copied().unwrap_or_else(|| {
-                let x = self.archetypes.len() as u32;
-                self.archetypes.push(Archetype::new(T::static_type_info()));
-                self.index.insert(ids.to_vec(), x);
-                self.archetype_generation += 1;
-                x
-            })
-        });
+        let archetype_id =
+            T::with_static_ids(|ids| self.get_or_insert_archetype(ids, T::static_type_info));
 
         self.archetypes[archetype_id as usize].reserve(additional as usize);
         archetype_id
     }
 
+    /// Finds the archetype of entities with the components in `ids`, creating it from the
+    /// `TypeInfo`s returned by `type_info` if it doesn't exist yet. Components stored in sparse
+    /// sets are left out of the archetype.
+    fn get_or_insert_archetype(
+        &mut self,
+        ids: &[ComponentId],
+        type_info: impl FnOnce() -> Vec<TypeInfo>,
+    ) -> u32 {
+        if ids.iter().any(|&id| self.sparse_sets.contains(id)) {
+            let sparse_sets = &self.sparse_sets;
+            let table_ids = ids
+                .iter()
+                .copied()
+                .filter(|&id| !sparse_sets.contains(id))
+                .collect::<Vec<_>>();
+            return self.get_or_insert_archetype(&table_ids, type_info);
+        }
+        self.index.get(ids).copied().unwrap_or_else(|| {
+            let sparse_sets = &self.sparse_sets;
+            let types = type_info()
+                .into_iter()
+                .filter(|ty| !sparse_sets.contains(ty.id()))
+                .collect();
+            let x = self.archetypes.len() as u32;
+            self.archetypes.push(Archetype::new(types));
+            self.index.insert(ids.to_vec(), x);
+            self.archetype_generation += 1;
+            x
+        })
+    }
+
+    /// Chooses where the components of type `T` are stored
+    ///
+    /// Components are stored in archetype tables by default. Storing a type in a sparse set
+    /// instead makes adding and removing it cheap, at the cost of slower queries. The storage type
+    /// has to be chosen before any `T` component is added to the world.
+    ///
+    /// Panics if `T` is already stored differently.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// struct Selected;
+    ///
+    /// let mut world = World::new();
+    /// world.register_component::<Selected>(StorageType::SparseSet);
+    /// let a = world.spawn((123,));
+    /// let archetypes = world.archetypes().len();
+    /// world.insert_one(a, Selected).unwrap();
+    /// assert_eq!(world.archetypes().len(), archetypes);
+    /// assert!(world.get::<Selected>(a).is_ok());
+    /// ```
+    pub fn register_component<T: Component>(&mut self, storage_type: StorageType) {
+        if let Err(current) = self.register_storage(TypeInfo::of::<T>(), storage_type) {
+            panic!(
+                "cannot store {} in {:?} storage, it is already stored in {:?} storage",
+                type_name::<T>(),
+                storage_type,
+                current
+            );
+        }
+    }
+
+    /// Chooses where the components described by `info` are stored, like `register_component`
+    /// does for Rust types
+    ///
+    /// Panics if the components are already stored differently.
+    pub fn register_dynamic_component(&mut self, info: TypeInfo, storage_type: StorageType) {
+        if let Err(current) = self.register_storage(info, storage_type) {
+            panic!(
+                "cannot store {:?} in {:?} storage, it is already stored in {:?} storage",
+                info.id(),
+                storage_type,
+                current
+            );
+        }
+    }
+
+    /// Fails with the current storage type if it isn't `storage_type`
+    fn register_storage(
+        &mut self,
+        info: TypeInfo,
+        storage_type: StorageType,
+    ) -> Result<(), StorageType> {
+        let ty = info.id();
+        let current = if self.sparse_sets.contains(ty) {
+            StorageType::SparseSet
+        } else if self
+            .archetypes
+            .iter()
+            .any(|archetype| archetype.has_dynamic(ty))
+        {
+            StorageType::Table
+        } else {
+            if storage_type == StorageType::SparseSet {
+                self.sparse_sets.insert(info);
+                // queries matching the component now match entities in every archetype
+                self.archetype_generation += 1;
+            }
+            return Ok(());
+        };
+        if current == storage_type {
+            Ok(())
+        } else {
+            Err(current)
+        }
+    }
+
+    /// Where the components of type `ty` are stored
+    pub fn storage_type(&self, ty: ComponentId) -> StorageType {
+        if self.sparse_sets.contains(ty) {
+            StorageType::SparseSet
+        } else {
+            StorageType::Table
+        }
+    }
+
+    /// The sparse sets of the component types registered with `StorageType::SparseSet`
+    pub fn sparse_sets(&self) -> &SparseSets {
+        &self.sparse_sets
+    }
+
+    /// Registers `hook` to run after a `T` component is added to an entity that didn't have one, by
+    /// `spawn`, `insert` or `insert_one`
+    ///
+    /// Hooks run once the operation is complete, so they see a consistent world and may modify it.
+    /// Commands run the hooks of the operations they perform when they are applied, and `spawn_batch`
+    /// runs them for each entity it spawns. A hook that triggers its own component's hooks again on
+    /// the same entity, for example by despawning it, doesn't run a second time.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// struct Collider;
+    /// struct Registered;
+    ///
+    /// let mut world = World::new();
+    /// world.on_insert::<Collider>(|world, entity| {
+    ///     world.insert_one(entity, Registered).unwrap();
+    /// });
+    /// let a = world.spawn((Collider,));
+    /// assert!(world.get::<Registered>(a).is_ok());
+    /// ```
+    pub fn on_insert<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) {
+        self.hooks
+            .add(ComponentId::of::<T>(), HookKind::Insert, Arc::new(hook));
+    }
+
+    /// Registers `hook` to run after a `T` component of an entity is replaced by `insert` or
+    /// `insert_one`
+    ///
+    /// See `on_insert`.
+    pub fn on_replace<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) {
+        self.hooks
+            .add(ComponentId::of::<T>(), HookKind::Replace, Arc::new(hook));
+    }
+
+    /// Registers `hook` to run before a `T` component is removed from an entity by `remove`,
+    /// `remove_one` or `despawn`, while the component can still be read
+    ///
+    /// See `on_insert`. `clear` and `restore_entities` also run remove hooks.
+    pub fn on_remove<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) {
+        self.hooks
+            .add(ComponentId::of::<T>(), HookKind::Remove, Arc::new(hook));
+    }
+
+    /// Runs the `kind` hooks of `types` for `entity`. The hooks of a component that is already
+    /// running them for `entity` are skipped, as are those of components an earlier hook removed.
+    fn run_hooks(&mut self, entity: Entity, types: &[ComponentId], kind: HookKind) {
+        for &ty in types {
+            if !self.has_component(entity, ty) || !self.hooks.start(entity, ty, kind) {
+                continue;
+            }
+            for hook in self.hooks.collect(&[ty], kind) {
+                hook(self, entity);
+            }
+            self.hooks.finish(entity, ty, kind);
+        }
+    }
+
     /// Despawn all entities
     ///
-    /// Preserves allocated storage for reuse.
+    /// Preserves allocated storage for reuse. The remove hooks of all components run before the
+    /// world is emptied.
     pub fn clear(&mut self) {
+        self.flush();
+        self.run_all_hooks(HookKind::Remove);
+        self.clear_storage(true);
+        self.entities.clear();
+    }
+
+    /// Runs the `kind` hooks of every component in the world
+    fn run_all_hooks(&mut self, kind: HookKind) {
+        let mut hooked = Vec::new();
+        for archetype in &self.archetypes {
+            let types = archetype
+                .types()
+                .iter()
+                .map(|ty| ty.id())
+                .collect::<Vec<_>>();
+            let types = self.hooks.hooked(&types);
+            if !types.is_empty() {
+                for &entity in archetype.iter_entities() {
+                    hooked.push((entity, types.clone()));
+                }
+            }
+        }
+        for sparse_set in self.sparse_sets.iter() {
+            let types = self.hooks.hooked(&[sparse_set.type_info().id()]);
+            if !types.is_empty() {
+                for &entity in sparse_set.entities() {
+                    hooked.push((entity, types.clone()));
+                }
+            }
+        }
+        for (entity, types) in hooked {
+            self.run_hooks(entity, &types, kind);
+        }
+    }
+
+    /// Empties every archetype and sparse set, optionally recording the removed components
+    fn clear_storage(&mut self, record_removals: bool) {
         for archetype in &mut self.archetypes {
-            for ty in archetype.types() {
+            if record_removals {
+                for ty in archetype.types() {
+                    let removed_entities = self
+                        .removed_components
+                        .entry(ty.id())
+                        .or_insert_with(Vec::new);
+                    removed_entities.extend(archetype.iter_entities().copied());
+                }
+            }
+            archetype.clear();
+        }
+        for sparse_set in self.sparse_sets.iter_mut() {
+            if record_removals {
                 let removed_entities = self
                     .removed_components
-                    .entry(ty.id())
+                    .entry(sparse_set.type_info().id())
                     .or_insert_with(Vec::new);
-                removed_entities.extend(archetype.iter_entities().copied());
+                removed_entities.extend(sparse_set.entities().iter().copied());
             }
-            archetype.clear();
+            sparse_set.clear();
         }
+    }
+
+    /// Captures the state of the entity allocator, so that the entities alive now can be brought
+    /// back with `restore_entities`
+    ///
+    /// Entities reserved but not yet flushed are not part of the snapshot.
+    pub fn snapshot_entities(&self) -> EntitiesSnapshot {
+        self.entities.snapshot()
+    }
+
+    /// Despawns all entities and restores the entity allocator to `snapshot`. The entities that
+    /// were alive when the snapshot was taken are spawned again without components, with the same
+    /// IDs and generations, so components can be inserted on them directly.
+    ///
+    /// Unlike `clear`, the despawned components are not recorded as removed. Their remove hooks run
+    /// before the world is emptied.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123,));
+    /// let snapshot = world.snapshot_entities();
+    /// world.despawn(a).unwrap();
+    /// let b = world.spawn((456,));
+    ///
+    /// world.restore_entities(&snapshot);
+    /// assert!(world.contains(a));
+    /// assert!(!world.contains(b));
+    /// world.insert_one(a, 123).unwrap();
+    /// ```
+    pub fn restore_entities(&mut self, snapshot: &EntitiesSnapshot) {
+        self.flush();
+        self.run_all_hooks(HookKind::Remove);
+        self.clear_storage(false);
+        let alive = self.entities.restore(snapshot);
+        let arch = &mut self.archetypes[0];
+        for entity in alive {
+            self.entities.meta[entity.id as usize].location.index =
+                unsafe { arch.allocate(entity) };
+        }
+    }
+
+    /// Copies the entities of this world and their components of the types accepted by `filter`,
+    /// one column at a time, so that they can be brought back with `restore_components`
+    ///
+    /// `copy` is called with every column of components to copy, and must push a copy of each
+    /// component in it, in order, to the writer. Entities reserved but not yet flushed are not part
+    /// of the snapshot.
+    ///
+    /// Panics if `copy` doesn't fill a column.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((123, "abc"));
+    /// let copy = |source: &Column, destination: &mut ColumnWriter| {
+    ///     for &x in source.get::<i32>().unwrap() {
+    ///         destination.push(x);
+    ///     }
+    /// };
+    /// let snapshot = world.snapshot_components(|ty| ty.id() == ComponentId::of::<i32>(), copy);
+    ///
+    /// world.despawn(a).unwrap();
+    /// let b = world.spawn((456,));
+    /// world.restore_components(&snapshot, copy);
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 123);
+    /// assert!(world.get::<&str>(a).is_err());
+    /// assert!(!world.contains(b));
+    /// ```
+    pub fn snapshot_components(
+        &self,
+        mut filter: impl FnMut(&TypeInfo) -> bool,
+        mut copy: impl FnMut(&Column, &mut ColumnWriter),
+    ) -> ComponentsSnapshot {
+        let mut snapshot = ComponentsSnapshot {
+            entities: self.snapshot_entities(),
+            archetypes: Vec::new(),
+            sparse_sets: Vec::new(),
+        };
+        let no_ticks = ComponentTicks::default();
+        for archetype in self.archetypes.iter().filter(|x| !x.is_empty()) {
+            // the archetype is kept even without copied components, so that its entities are
+            // brought back
+            let types = archetype.types().iter().copied().filter(&mut filter);
+            let mut copied = Archetype::new(types.collect());
+            copied.extend(archetype.entity_slice(), no_ticks, |ty, writer| {
+                copy(&archetype.column(ty), writer)
+            });
+            snapshot.archetypes.push(copied);
+        }
+        for sparse_set in self.sparse_sets.iter().filter(|x| !x.is_empty()) {
+            let info = sparse_set.type_info();
+            if filter(&info) {
+                let mut copied = Archetype::new(alloc::vec![info]);
+                copied.extend(sparse_set.entities(), no_ticks, |_, writer| {
+                    copy(&sparse_set.column(), writer)
+                });
+                snapshot.sparse_sets.push(copied);
+            }
+        }
+        snapshot
+    }
+
+    /// Despawns all entities and replaces them with the ones in `snapshot`, with the same IDs and
+    /// generations, and copies of their components
+    ///
+    /// The components are copied one column at a time by `copy`, like in `snapshot_components`,
+    /// and count as added. The snapshot can be restored any number of times, in any world that
+    /// stores its component types the same way. Unlike `clear`, the despawned components are not
+    /// recorded as removed. Their remove hooks run before the world is emptied, and the insert
+    /// hooks of the restored components run once all entities are back.
+    ///
+    /// Panics if this world stores a component type of the snapshot in a different `StorageType`
+    /// than the world the snapshot was taken from, or if `copy` doesn't fill a column.
+    pub fn restore_components(
+        &mut self,
+        snapshot: &ComponentsSnapshot,
+        mut copy: impl FnMut(&Column, &mut ColumnWriter),
+    ) {
+        for archetype in &snapshot.archetypes {
+            for ty in archetype.types() {
+                if self.sparse_sets.contains(ty.id()) {
+                    panic!(
+                        "cannot restore {:?} in table storage, it is stored in sparse sets",
+                        ty.id()
+                    );
+                }
+            }
+        }
+        for sparse_set in &snapshot.sparse_sets {
+            let info = sparse_set.types()[0];
+            if self.register_storage(info, StorageType::SparseSet).is_err() {
+                panic!(
+                    "cannot restore {:?} in a sparse set, it is stored in tables",
+                    info.id()
+                );
+            }
+        }
+
+        self.flush();
+        self.run_all_hooks(HookKind::Remove);
+        self.clear_storage(false);
+        // the entities are brought back once their components are in place, so that a panic in
+        // `copy` leaves no entity without a location
         self.entities.clear();
+
+        let change_tick = self.read_change_tick();
+        let component_ticks = ComponentTicks::new(change_tick);
+        let mut locations = Vec::with_capacity(snapshot.archetypes.len());
+        for source in &snapshot.archetypes {
+            let ids = source.types().iter().map(|ty| ty.id()).collect::<Vec<_>>();
+            let target = self.get_or_insert_archetype(&ids, || source.types().to_vec());
+            let start = self.archetypes[target as usize].extend(
+                source.entity_slice(),
+                component_ticks,
+                |ty, writer| copy(&source.column(ty), writer),
+            );
+            locations.push((target, start));
+        }
+        for source in &snapshot.sparse_sets {
+            let info = source.types()[0];
+            let sparse_set = self.sparse_sets.get_mut(info.id()).unwrap();
+            sparse_set.extend(source.entity_slice(), change_tick, |writer| {
+                copy(&source.column(&info), writer)
+            });
+        }
+
+        let alive = self.entities.restore(&snapshot.entities);
+        debug_assert_eq!(
+            alive.len(),
+            snapshot.archetypes.iter().map(|x| x.len()).sum::<usize>()
+        );
+        for (source, &(archetype, start)) in snapshot.archetypes.iter().zip(&locations) {
+            for (index, entity) in (start..).zip(source.entity_slice()) {
+                self.entities.meta[entity.id as usize].location = Location { archetype, index };
+            }
+        }
+        self.run_all_hooks(HookKind::Insert);
+    }
+
+    /// Registers `T` as a component that references other entities, so that `merge` and
+    /// `move_entities_from` remap the references of the `T` components they move into this world
+    pub fn register_map_entities<T: Component + MapEntities>(&mut self) {
+        self.entity_mappers
+            .insert(ComponentId::of::<T>(), EntityMapper::of::<T>());
+    }
+
+    /// Moves all entities of `source` into this world, with all of their components
+    ///
+    /// See `move_entities_from`. Fails without moving anything if a component registered with
+    /// `register_map_entities` references an entity that doesn't exist.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut level = World::new();
+    /// let a = level.spawn((123, "abc"));
+    ///
+    /// let mut world = World::new();
+    /// let entity_map = world.merge(&mut level).unwrap();
+    /// let moved = entity_map.get(a).unwrap();
+    /// assert_eq!(*world.get::<i32>(moved).unwrap(), 123);
+    /// assert!(!level.contains(a));
+    /// ```
+    pub fn merge(&mut self, source: &mut World) -> Result<EntityMap, MoveEntitiesError> {
+        source.flush();
+        let mut entities = source
+            .archetypes
+            .iter()
+            .flat_map(|archetype| archetype.iter_entities().copied())
+            .collect::<Vec<_>>();
+        // moving the last entity of an archetype doesn't move any other entity in its place
+        entities.reverse();
+        self.move_entities_from(source, &entities)
+    }
+
+    /// Moves `entities` from `source` into this world, with all of their components, and returns
+    /// the new entity each of them maps to
+    ///
+    /// Components are moved rather than copied, so any component can be moved, including the ones
+    /// defined at runtime. Components stored in sparse sets in `source` are stored in sparse sets
+    /// here too, unless this world already stores them in tables. Moved components count as added
+    /// in this world. Hooks don't run, and the components aren't recorded as removed from
+    /// `source`.
+    ///
+    /// Afterwards, the components registered with `register_map_entities` are remapped to refer to
+    /// the moved entities.
+    ///
+    /// Fails without moving anything if any of `entities` doesn't exist in `source`, or if one of
+    /// their components registered with `register_map_entities` references an entity that isn't
+    /// moved, like the parent of the root of a moved hierarchy.
+    pub fn move_entities_from(
+        &mut self,
+        source: &mut World,
+        entities: &[Entity],
+    ) -> Result<EntityMap, MoveEntitiesError> {
+        self.flush();
+        source.flush();
+        if let Some(&entity) = entities.iter().find(|&&entity| !source.contains(entity)) {
+            return Err(MoveEntitiesError::NoSuchEntity(entity));
+        }
+
+        let mut selection = EntityMap::default();
+        for &entity in entities {
+            selection.insert(entity, entity);
+        }
+        let checks = self
+            .entity_mappers
+            .values()
+            .map(|mapper| mapper.check)
+            .collect::<Vec<_>>();
+        for &entity in entities {
+            for check in checks.iter() {
+                if let Err(MapEntitiesError::EntityNotFound(referenced)) =
+                    check(source, entity, &selection)
+                {
+                    return Err(MoveEntitiesError::EntityNotMoved { entity, referenced });
+                }
+            }
+        }
+
+        let mut entity_map = EntityMap::default();
+        for &entity in entities {
+            if entity_map.get(entity).is_err() {
+                let moved = self.move_entity_from(source, entity);
+                entity_map.insert(entity, moved);
+            }
+        }
+
+        let mappers = self
+            .entity_mappers
+            .values()
+            .map(|mapper| mapper.map)
+            .collect::<Vec<_>>();
+        for entity in entity_map.values() {
+            for mapper in mappers.iter() {
+                mapper(self, entity, &entity_map)
+                    .expect("references were checked before moving entities");
+            }
+        }
+        Ok(entity_map)
+    }
+
+    /// Moves the components of `entity` to a new entity of this world and despawns it from `source`
+    fn move_entity_from(&mut self, source: &mut World, entity: Entity) -> Entity {
+        let loc = source.entities.free(entity).unwrap();
+        let source_arch = &mut source.archetypes[loc.archetype as usize];
+        let sparse_info = source
+            .sparse_sets
+            .iter()
+            .filter(|sparse_set| sparse_set.contains(entity))
+            .map(|sparse_set| sparse_set.type_info())
+            .collect::<Vec<_>>();
+        for &ty in sparse_info.iter() {
+            // fails if this world stores the component in tables, where it is moved to instead
+            let _ = self.register_storage(ty, StorageType::SparseSet);
+        }
+        let mut info = source_arch.types().to_vec();
+        info.extend(sparse_info);
+        info.sort();
+        let ids = info.iter().map(|ty| ty.id()).collect::<Vec<_>>();
+        let archetype_id = self.get_or_insert_archetype(&ids, || info);
+
+        let moved = self.entities.alloc();
+        let change_tick = *self.change_tick.get_mut();
+        let archetype = &mut self.archetypes[archetype_id as usize];
+        let sparse_sets = &mut self.sparse_sets;
+        unsafe {
+            let index = archetype.allocate(moved);
+            let mut put = |ptr: *mut u8, ty: ComponentId, size: usize| match sparse_sets.get_mut(ty)
+            {
+                Some(sparse_set) => sparse_set.insert(moved, ptr, change_tick),
+                None => {
+                    archetype.put_dynamic(ptr, ty, size, index, ComponentTicks::new(change_tick))
+                }
+            };
+            if let Some(swapped) = source_arch.move_to(loc.index, |ptr, ty, size, _| {
+                put(ptr, ty, size);
+            }) {
+                source.entities.get_mut(swapped).unwrap().index = loc.index;
+            }
+            for sparse_set in source.sparse_sets.iter_mut() {
+                if let Some((ptr, _)) = sparse_set.get_with_ticks(entity) {
+                    let info = sparse_set.type_info();
+                    put(ptr.as_ptr(), info.id(), info.layout().size());
+                    sparse_set.remove_and_forget(entity);
+                }
+            }
+            self.entities.meta[moved.id as usize].location = Location {
+                archetype: archetype_id,
+                index,
+            };
+        }
+        moved
+    }
+
+    /// Uniquely borrows the `T` component of `entity` without marking it as changed
+    pub(crate) fn get_mut_untracked<T: Component>(
+        &mut self,
+        entity: Entity,
+    ) -> Result<&mut T, ComponentError> {
+        unsafe {
+            let loc = self.entities.get(entity)?;
+            Ok(&mut *self.get_component::<T>(loc)?.0.as_ptr())
+        }
     }
 
     /// Whether `entity` still exists
@@ -220,10 +871,19 @@ impl World {
 
     /// Returns true if the given entity has a component with the given type id.
     pub fn has_component_type(&self, entity: Entity, ty: TypeId) -> bool {
-        self.get_entity_location(entity)
-            .map(|location| &self.archetypes[location.archetype as usize])
-            .map(|archetype| archetype.has_type(ty))
-            .unwrap_or(false)
+        self.has_component(entity, ty.into())
+    }
+
+    /// Returns true if the given entity has a component with the given id.
+    pub fn has_component(&self, entity: Entity, ty: ComponentId) -> bool {
+        match self.sparse_sets.get(ty) {
+            Some(sparse_set) => sparse_set.contains(entity),
+            None => self
+                .get_entity_location(entity)
+                .map(|location| &self.archetypes[location.archetype as usize])
+                .map(|archetype| archetype.has_dynamic(ty))
+                .unwrap_or(false),
+        }
     }
 
     /// Efficiently iterate over all entities that have certain components
@@ -328,7 +988,37 @@ impl World {
     /// assert!(entities.contains(&(b, 456, false)));
     /// ```
     pub unsafe fn query_unchecked<Q: Query>(&self) -> QueryBorrow<'_, Q> {
-        QueryBorrow::new(&self.archetypes)
+        self.query_with_ticks_unchecked(self.last_change_tick, self.read_change_tick())
+    }
+
+    /// Like `query_unchecked`, but change detecting queries see the changes made after
+    /// `last_change_tick` and mutations are recorded with `change_tick`. Used by systems, which
+    /// track the tick of their last run.
+    ///
+    /// # Safety
+    /// This does not check for mutable query correctness. To be safe, make sure mutable queries
+    /// have unique access to the components they query.
+    pub unsafe fn query_with_ticks_unchecked<Q: Query>(
+        &self,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> QueryBorrow<'_, Q> {
+        QueryBorrow::new(
+            &self.archetypes,
+            &self.sparse_sets,
+            None,
+            last_change_tick,
+            change_tick,
+        )
+    }
+
+    /// Efficiently iterate over all entities matching a `DynamicQuery`, whose components are only
+    /// known at runtime
+    ///
+    /// The fetched components are borrowed until the returned value is dropped. Panics if they are
+    /// already borrowed in a conflicting way.
+    pub fn query_dynamic(&self, query: &DynamicQuery) -> DynamicQueryBorrow<'_> {
+        DynamicQueryBorrow::new(self, query)
     }
 
     /// Prepare a read only query against a single entity
@@ -355,7 +1045,15 @@ impl World {
         Q::Fetch: ReadOnlyFetch,
     {
         let loc = self.entities.get(entity)?;
-        Ok(unsafe { ReadOnlyQueryOne::new(&self.archetypes[loc.archetype as usize], loc.index) })
+        Ok(unsafe {
+            ReadOnlyQueryOne::new(
+                &self.archetypes[loc.archetype as usize],
+                &self.sparse_sets,
+                loc.index,
+                self.last_change_tick,
+                self.read_change_tick(),
+            )
+        })
     }
 
     /// Prepare a query against a single entity
@@ -411,7 +1109,10 @@ impl World {
         let loc = self.entities.get(entity)?;
         Ok(QueryOne::new(
             &self.archetypes[loc.archetype as usize],
+            &self.sparse_sets,
             loc.index,
+            self.last_change_tick,
+            self.read_change_tick(),
         ))
     }
 
@@ -419,14 +1120,7 @@ impl World {
     pub fn get<T: Component>(&self, entity: Entity) -> Result<&'_ T, ComponentError> {
         unsafe {
             let loc = self.entities.get(entity)?;
-            if loc.archetype == 0 {
-                return Err(MissingComponent::new::<T>().into());
-            }
-            Ok(&*self.archetypes[loc.archetype as usize]
-                .get::<T>()
-                .ok_or_else(MissingComponent::new::<T>)?
-                .as_ptr()
-                .add(loc.index as usize))
+            Ok(&*self.get_component::<T>(loc)?.0.as_ptr())
         }
     }
 
@@ -436,13 +1130,97 @@ impl World {
         unsafe { self.get_mut_unchecked(entity) }
     }
 
+    /// Mutably borrow the `T` component of each of `entities` at once
+    ///
+    /// Fails with `ComponentError::AliasedEntity` if an entity is given more than once.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let a = world.spawn((1,));
+    /// let b = world.spawn((2,));
+    /// let [mut x, mut y] = world.get_many_mut::<i32, 2>([a, b]).unwrap();
+    /// core::mem::swap(&mut *x, &mut *y);
+    /// assert_eq!(*world.get::<i32>(a).unwrap(), 2);
+    /// assert!(world.get_many_mut::<i32, 2>([a, a]).is_err());
+    /// ```
+    pub fn get_many_mut<T: Component, const N: usize>(
+        &mut self,
+        entities: [Entity; N],
+    ) -> Result<[Mut<'_, T>; N], ComponentError> {
+        let change_tick = self.read_change_tick();
+        let locations = self.get_many_locations(entities)?;
+        let mut components = Vec::with_capacity(N);
+        for location in locations.iter() {
+            // SAFE: uniquely borrows world, and the entities are distinct
+            components.push(unsafe { self.get_mut_at_location_unchecked(*location, change_tick)? });
+        }
+        Ok(into_array(components))
+    }
+
+    /// Uniquely borrow the `T` component of each of `entities` at once, without checking if they
+    /// can be mutated
+    ///
+    /// Fails with `ComponentError::AliasedEntity` if an entity is given more than once. Panics if
+    /// the components are already borrowed.
+    ///
+    /// # Safety
+    /// This does not check for mutable access correctness. To be safe, make sure nothing else is
+    /// accessing these entities' T components without a borrow.
+    pub unsafe fn get_many_ref_mut_unchecked<T: Component, const N: usize>(
+        &self,
+        entities: [Entity; N],
+        change_tick: u32,
+    ) -> Result<RefMutMany<'_, T, N>, ComponentError> {
+        let locations = self.get_many_locations(entities)?;
+        let mut components = Vec::with_capacity(N);
+        let mut borrows: Vec<&AtomicBorrow> = Vec::new();
+        for location in locations.iter() {
+            let (target, component_ticks, borrow) = self.get_component::<T>(*location)?;
+            if !borrows.iter().any(|other| ptr::eq(*other, borrow)) {
+                borrows.push(borrow);
+            }
+            components.push(Mut {
+                value: &mut *target.as_ptr(),
+                component_ticks: &mut *component_ticks.as_ptr(),
+                change_tick,
+            });
+        }
+        Ok(RefMutMany::from_raw(into_array(components), borrows))
+    }
+
+    /// Looks up the locations of `entities`, checking that each is only given once
+    fn get_many_locations<const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<[Location; N], ComponentError> {
+        let mut locations = [Location {
+            archetype: 0,
+            index: 0,
+        }; N];
+        for (i, entity) in entities.iter().enumerate() {
+            if entities[..i].contains(entity) {
+                return Err(ComponentError::AliasedEntity(*entity));
+            }
+            locations[i] = self.entities.get(*entity)?;
+        }
+        Ok(locations)
+    }
+
     /// Access an entity regardless of its component types
     ///
     /// Does not immediately borrow any component.
     pub fn entity(&mut self, entity: Entity) -> Result<EntityRef<'_>, NoSuchEntity> {
-        Ok(match self.entities.get(entity)? {
-            Location { archetype: 0, .. } => EntityRef::empty(),
-            loc => unsafe { EntityRef::new(&self.archetypes[loc.archetype as usize], loc.index) },
+        let change_tick = self.read_change_tick();
+        let loc = self.entities.get(entity)?;
+        Ok(unsafe {
+            EntityRef::new(
+                &self.archetypes[loc.archetype as usize],
+                &self.sparse_sets,
+                loc.index,
+                change_tick,
+            )
         })
     }
 
@@ -456,13 +1234,7 @@ impl World {
         entity: Entity,
     ) -> Result<Mut<'_, T>, ComponentError> {
         let loc = self.entities.get(entity)?;
-        if loc.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(Mut::new(
-            &self.archetypes[loc.archetype as usize],
-            loc.index,
-        )?)
+        self.get_mut_at_location_unchecked(loc, self.read_change_tick())
     }
 
     /// Iterate over all entities in the world
@@ -482,16 +1254,30 @@ impl World {
     /// assert!(ids.contains(&b));
     /// ```
     pub fn iter(&mut self) -> Iter<'_> {
-        Iter::new(&self.archetypes, &self.entities)
+        let change_tick = self.read_change_tick();
+        Iter::new(
+            &self.archetypes,
+            &self.sparse_sets,
+            &self.entities,
+            change_tick,
+        )
     }
 
     #[allow(missing_docs)]
     pub fn removed<C: Component>(&self) -> &[Entity] {
         self.removed_components
-            .get(&TypeId::of::<C>())
+            .get(&ComponentId::of::<C>())
             .map_or(&[], |entities| entities.as_slice())
     }
 
+    /// Iterates over the entities that had components removed since the last `clear_trackers`,
+    /// grouped by component type
+    pub fn removed_components(&self) -> impl Iterator<Item = (ComponentId, &[Entity])> + '_ {
+        self.removed_components
+            .iter()
+            .map(|(ty, entities)| (*ty, entities.as_slice()))
+    }
+
     /// Add `components` to `entity`
     ///
     /// Computational cost is proportional to the number of components `entity` has. If an entity
@@ -512,16 +1298,35 @@ impl World {
         &mut self,
         entity: Entity,
         components: impl DynamicBundle,
+    ) -> Result<(), NoSuchEntity> {
+        self.flush();
+        let hooked = components.with_ids(|ids| self.hooks.hooked(ids));
+        let (replaced, inserted): (Vec<_>, Vec<_>) = hooked
+            .into_iter()
+            .partition(|&ty| self.has_component(entity, ty));
+        self.insert_components(entity, components)?;
+        self.run_hooks(entity, &replaced, HookKind::Replace);
+        self.run_hooks(entity, &inserted, HookKind::Insert);
+        Ok(())
+    }
+
+    fn insert_components(
+        &mut self,
+        entity: Entity,
+        components: impl DynamicBundle,
     ) -> Result<(), NoSuchEntity> {
         use std::collections::hash_map::Entry;
 
-        self.flush();
+        let change_tick = *self.change_tick.get_mut();
         let loc = self.entities.get_mut(entity)?;
         unsafe {
             // Assemble Vec<TypeInfo> for the final entity
             let arch = &mut self.archetypes[loc.archetype as usize];
             let mut info = arch.types().to_vec();
             for ty in components.type_info() {
+                if self.sparse_sets.contains(ty.id()) {
+                    continue;
+                }
                 if let Some(ptr) = arch.get_dynamic(ty.id(), ty.layout().size(), loc.index) {
                     ty.drop(ptr.as_ptr());
                 } else {
@@ -543,11 +1348,19 @@ impl World {
                 }
             };
 
+            let sparse_sets = &mut self.sparse_sets;
             if target == loc.archetype {
                 // Update components in the current archetype
                 let arch = &mut self.archetypes[loc.archetype as usize];
                 components.put(|ptr, ty, size| {
-                    arch.put_dynamic(ptr, ty, size, loc.index, false);
+                    if let Some(sparse_set) = sparse_sets.get_mut(ty) {
+                        sparse_set.insert(entity, ptr, change_tick);
+                        return true;
+                    }
+                    let type_state = arch.get_type_state(ty).unwrap();
+                    let mut component_ticks = *type_state.component_ticks().as_ptr().add(loc.index);
+                    component_ticks.set_mutated(change_tick);
+                    arch.put_dynamic(ptr, ty, size, loc.index, component_ticks);
                     true
                 });
                 return Ok(());
@@ -562,19 +1375,28 @@ impl World {
             let target_index = target_arch.allocate(entity);
             loc.archetype = target;
             let old_index = mem::replace(&mut loc.index, target_index);
-            if let Some(moved) =
-                source_arch.move_to(old_index, |ptr, ty, size, is_added, is_mutated| {
-                    target_arch.put_dynamic(ptr, ty, size, target_index, false);
-                    let type_state = target_arch.get_type_state_mut(ty).unwrap();
-                    *type_state.added().as_ptr().add(target_index) = is_added;
-                    *type_state.mutated().as_ptr().add(target_index) = is_mutated;
-                })
-            {
+            if let Some(moved) = source_arch.move_to(old_index, |ptr, ty, size, component_ticks| {
+                target_arch.put_dynamic(ptr, ty, size, target_index, component_ticks);
+            }) {
                 self.entities.get_mut(moved).unwrap().index = old_index;
             }
 
             components.put(|ptr, ty, size| {
-                target_arch.put_dynamic(ptr, ty, size, target_index, true);
+                if let Some(sparse_set) = sparse_sets.get_mut(ty) {
+                    sparse_set.insert(entity, ptr, change_tick);
+                    return true;
+                }
+                let component_ticks = if source_arch.has_dynamic(ty) {
+                    // the component replaces an existing one, which counts as a mutation
+                    let type_state = target_arch.get_type_state(ty).unwrap();
+                    let mut component_ticks =
+                        *type_state.component_ticks().as_ptr().add(target_index);
+                    component_ticks.set_mutated(change_tick);
+                    component_ticks
+                } else {
+                    ComponentTicks::new(change_tick)
+                };
+                target_arch.put_dynamic(ptr, ty, size, target_index, component_ticks);
                 true
             });
         }
@@ -612,32 +1434,47 @@ impl World {
     /// assert_eq!(*world.get::<bool>(e).unwrap(), true);
     /// ```
     pub fn remove<T: Bundle>(&mut self, entity: Entity) -> Result<T, ComponentError> {
-        use std::collections::hash_map::Entry;
-
         self.flush();
+        let hooked = T::with_static_ids(|ids| self.hooks.hooked(ids));
+        if !hooked.is_empty()
+            && T::with_static_ids(|ids| ids.iter().all(|&ty| self.has_component(entity, ty)))
+        {
+            self.run_hooks(entity, &hooked, HookKind::Remove);
+        }
+        self.remove_components(entity)
+    }
+
+    fn remove_components<T: Bundle>(&mut self, entity: Entity) -> Result<T, ComponentError> {
+        let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
+        let target = self
+            .get_or_insert_archetype_without(self.entities.get(entity)?.archetype, |ty| {
+                removed.contains(&ty)
+            });
         let loc = self.entities.get_mut(entity)?;
         unsafe {
-            let removed = T::with_static_ids(|ids| ids.iter().copied().collect::<HashSet<_>>());
-            let info = self.archetypes[loc.archetype as usize]
-                .types()
-                .iter()
-                .cloned()
-                .filter(|x| !removed.contains(&x.id()))
-                .collect::<Vec<_>>();
-            let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
-            let target = match self.index.entry(elements) {
-                Entry::Occupied(x) => *x.get(),
-                Entry::Vacant(x) => {
-                    self.archetypes.push(Archetype::new(info));
-                    let index = (self.archetypes.len() - 1) as u32;
-                    x.insert(index);
-                    self.archetype_generation += 1;
-                    index
-                }
-            };
             let old_index = loc.index;
             let source_arch = &self.archetypes[loc.archetype as usize];
-            let bundle = T::get(|ty, size| source_arch.get_dynamic(ty, size, old_index))?;
+            let sparse_sets = &mut self.sparse_sets;
+            let bundle = T::get(|ty, size| match sparse_sets.get(ty) {
+                Some(sparse_set) => sparse_set.get_with_ticks(entity).map(|(ptr, _)| ptr),
+                None => source_arch.get_dynamic(ty, size, old_index),
+            })?;
+            let removed_components = &mut self.removed_components;
+            T::with_static_ids(|ids| {
+                for &ty in ids {
+                    if let Some(sparse_set) = sparse_sets.get_mut(ty) {
+                        // the component was moved into `bundle`
+                        sparse_set.remove_and_forget(entity);
+                        let removed_entities =
+                            removed_components.entry(ty).or_insert_with(Vec::new);
+                        removed_entities.push(entity);
+                    }
+                }
+            });
+            if target == loc.archetype {
+                // only components stored in sparse sets were removed
+                return Ok(bundle);
+            }
             let (source_arch, target_arch) = index2(
                 &mut self.archetypes,
                 loc.archetype as usize,
@@ -646,28 +1483,130 @@ impl World {
             let target_index = target_arch.allocate(entity);
             loc.archetype = target;
             loc.index = target_index;
-            let removed_components = &mut self.removed_components;
-            if let Some(moved) =
-                source_arch.move_to(old_index, |src, ty, size, is_added, is_mutated| {
-                    // Only move the components present in the target archetype, i.e. the non-removed ones.
-                    if let Some(dst) = target_arch.get_dynamic(ty, size, target_index) {
-                        ptr::copy_nonoverlapping(src, dst.as_ptr(), size);
-                        let state = target_arch.get_type_state_mut(ty).unwrap();
-                        *state.added().as_ptr().add(target_index) = is_added;
-                        *state.mutated().as_ptr().add(target_index) = is_mutated;
-                    } else {
-                        let removed_entities =
-                            removed_components.entry(ty).or_insert_with(Vec::new);
-                        removed_entities.push(entity);
-                    }
-                })
-            {
+            if let Some(moved) = source_arch.move_to(old_index, |src, ty, size, component_ticks| {
+                // Only move the components present in the target archetype, i.e. the non-removed ones.
+                if target_arch.has_dynamic(ty) {
+                    target_arch.put_dynamic(src, ty, size, target_index, component_ticks);
+                } else {
+                    let removed_entities = removed_components.entry(ty).or_insert_with(Vec::new);
+                    removed_entities.push(entity);
+                }
+            }) {
                 self.entities.get_mut(moved).unwrap().index = old_index;
             }
             Ok(bundle)
         }
     }
 
+    /// Remove the components with the given ids from `entity`, dropping them
+    ///
+    /// This is how components defined at runtime are removed, but any component can be removed this
+    /// way. If any of the components is not present in `entity`, no components are removed and an
+    /// error is returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use bevy_hecs::*;
+    /// let mut world = World::new();
+    /// let e = world.spawn((123, "abc", true));
+    /// world.remove_dynamic(e, &[ComponentId::of::<i32>(), ComponentId::of::<bool>()]).unwrap();
+    /// assert!(world.get::<i32>(e).is_err());
+    /// assert_eq!(*world.get::<&str>(e).unwrap(), "abc");
+    /// ```
+    pub fn remove_dynamic(
+        &mut self,
+        entity: Entity,
+        ids: &[ComponentId],
+    ) -> Result<(), ComponentError> {
+        self.flush();
+        let hooked = self.hooks.hooked(ids);
+        if !hooked.is_empty() && ids.iter().all(|&ty| self.has_component(entity, ty)) {
+            self.run_hooks(entity, &hooked, HookKind::Remove);
+        }
+        self.remove_dynamic_components(entity, ids)
+    }
+
+    fn remove_dynamic_components(
+        &mut self,
+        entity: Entity,
+        ids: &[ComponentId],
+    ) -> Result<(), ComponentError> {
+        let source = self.entities.get(entity)?.archetype;
+        if let Some(&missing) = ids.iter().find(|&&ty| !self.has_component(entity, ty)) {
+            return Err(MissingComponent::with_id(missing).into());
+        }
+
+        for &ty in ids {
+            if let Some(sparse_set) = self.sparse_sets.get_mut(ty) {
+                sparse_set.remove(entity);
+                let removed_entities = self.removed_components.entry(ty).or_default();
+                removed_entities.push(entity);
+            }
+        }
+        let target = self.get_or_insert_archetype_without(source, |ty| ids.contains(&ty));
+        if target == source {
+            // only components stored in sparse sets were removed
+            return Ok(());
+        }
+
+        let removed_info = self.archetypes[source as usize]
+            .types()
+            .iter()
+            .copied()
+            .filter(|ty| ids.contains(&ty.id()))
+            .collect::<Vec<_>>();
+        let loc = self.entities.get_mut(entity)?;
+        let (source_arch, target_arch) =
+            index2(&mut self.archetypes, source as usize, target as usize);
+        let removed_components = &mut self.removed_components;
+        unsafe {
+            let target_index = target_arch.allocate(entity);
+            let old_index = mem::replace(&mut loc.index, target_index);
+            loc.archetype = target;
+            if let Some(moved) = source_arch.move_to(old_index, |src, ty, size, component_ticks| {
+                if target_arch.has_dynamic(ty) {
+                    target_arch.put_dynamic(src, ty, size, target_index, component_ticks);
+                } else {
+                    let info = removed_info.iter().find(|info| info.id() == ty).unwrap();
+                    info.drop(src);
+                    let removed_entities = removed_components.entry(ty).or_default();
+                    removed_entities.push(entity);
+                }
+            }) {
+                self.entities.get_mut(moved).unwrap().index = old_index;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the archetype of entities with the components of archetype `source` except the
+    /// `removed` ones, creating it if it doesn't exist yet
+    fn get_or_insert_archetype_without(
+        &mut self,
+        source: u32,
+        removed: impl Fn(ComponentId) -> bool,
+    ) -> u32 {
+        use std::collections::hash_map::Entry;
+
+        let info = self.archetypes[source as usize]
+            .types()
+            .iter()
+            .cloned()
+            .filter(|x| !removed(x.id()))
+            .collect::<Vec<_>>();
+        let elements = info.iter().map(|x| x.id()).collect::<Vec<_>>();
+        match self.index.entry(elements) {
+            Entry::Occupied(x) => *x.get(),
+            Entry::Vacant(x) => {
+                self.archetypes.push(Archetype::new(info));
+                let index = (self.archetypes.len() - 1) as u32;
+                x.insert(index);
+                self.archetype_generation += 1;
+                index
+            }
+        }
+    }
+
     /// Remove the `T` component from `entity`
     ///
     /// See `remove`.
@@ -683,13 +1622,8 @@ impl World {
         &self,
         location: Location,
     ) -> Result<Ref<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(Ref::new(
-            &self.archetypes[location.archetype as usize],
-            location.index,
-        )?)
+        let (target, _, borrow) = self.get_component::<T>(location)?;
+        Ok(Ref::from_raw(&*target.as_ptr(), borrow))
     }
 
     /// Borrow the `T` component at the given location, without safety checks
@@ -701,14 +1635,15 @@ impl World {
     pub unsafe fn get_ref_mut_at_location_unchecked<T: Component>(
         &self,
         location: Location,
+        change_tick: u32,
     ) -> Result<RefMut<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(RefMut::new(
-            &self.archetypes[location.archetype as usize],
-            location.index,
-        )?)
+        let (target, component_ticks, borrow) = self.get_component::<T>(location)?;
+        Ok(RefMut::from_raw(
+            &mut *target.as_ptr(),
+            &mut *component_ticks.as_ptr(),
+            borrow,
+            change_tick,
+        ))
     }
 
     /// Borrow the `T` component at the given location, without safety checks
@@ -718,14 +1653,7 @@ impl World {
         &self,
         location: Location,
     ) -> Result<&T, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(&*self.archetypes[location.archetype as usize]
-            .get::<T>()
-            .ok_or_else(MissingComponent::new::<T>)?
-            .as_ptr()
-            .add(location.index as usize))
+        Ok(&*self.get_component::<T>(location)?.0.as_ptr())
     }
 
     /// Borrow the `T` component at the given location, without safety checks
@@ -736,14 +1664,26 @@ impl World {
     pub unsafe fn get_mut_at_location_unchecked<T: Component>(
         &self,
         location: Location,
+        change_tick: u32,
     ) -> Result<Mut<T>, ComponentError> {
-        if location.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(Mut::new(
+        let (target, component_ticks, _) = self.get_component::<T>(location)?;
+        Ok(Mut {
+            value: &mut *target.as_ptr(),
+            component_ticks: &mut *component_ticks.as_ptr(),
+            change_tick,
+        })
+    }
+
+    /// Finds the `T` component of the entity at `location`, in its archetype or in a sparse set
+    #[allow(clippy::type_complexity)]
+    unsafe fn get_component<T: Component>(
+        &self,
+        location: Location,
+    ) -> Result<(NonNull<T>, NonNull<ComponentTicks>, &AtomicBorrow), MissingComponent> {
+        self.sparse_sets.get_component::<T>(
             &self.archetypes[location.archetype as usize],
             location.index,
-        )?)
+        )
     }
 
     /// Uniquely borrow the `T` component of `entity` without safety checks
@@ -759,14 +1699,7 @@ impl World {
         entity: Entity,
     ) -> Result<&mut T, ComponentError> {
         let loc = self.entities.get(entity)?;
-        if loc.archetype == 0 {
-            return Err(MissingComponent::new::<T>().into());
-        }
-        Ok(&mut *self.archetypes[loc.archetype as usize]
-            .get::<T>()
-            .ok_or_else(MissingComponent::new::<T>)?
-            .as_ptr()
-            .add(loc.index as usize))
+        Ok(&mut *self.get_component::<T>(loc)?.0.as_ptr())
     }
 
     /// Convert all reserved entities into empty entities that can be iterated and accessed
@@ -824,18 +1757,56 @@ impl World {
         ArchetypesGeneration(self.archetype_generation)
     }
 
+    /// The unique identifier of this world, which state derived from it can check against
+    pub fn id(&self) -> WorldId {
+        self.id
+    }
+
     /// Retrieves the entity's current location, if it exists
     pub fn get_entity_location(&self, entity: Entity) -> Option<Location> {
         self.entities.get(entity).ok()
     }
 
-    /// Clears each entity's tracker state. For example, each entity's component "mutated" state will be reset to `false`.
+    /// Clears the removed components and starts a new change detection period for queries made
+    /// directly on the `World`. Systems track their own last change tick and are unaffected.
     pub fn clear_trackers(&mut self) {
+        self.last_change_tick = self.increment_change_tick();
+        self.removed_components.clear();
+    }
+
+    /// Advances the change tick, returning the tick to use for the changes about to be made. Every
+    /// system run gets its own tick.
+    pub fn increment_change_tick(&self) -> u32 {
+        self.change_tick.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// The tick used to record changes made directly on the `World`
+    pub fn read_change_tick(&self) -> u32 {
+        self.change_tick.load(Ordering::Acquire)
+    }
+
+    /// The tick of the last `clear_trackers` call. Queries made directly on the `World` see the
+    /// changes made after this tick.
+    pub fn last_change_tick(&self) -> u32 {
+        self.last_change_tick
+    }
+
+    /// Clamps old component change ticks so that they can still be compared after the change tick
+    /// wraps around. Only does work every [CHECK_TICK_THRESHOLD] ticks. Systems clamp their own
+    /// last change tick with [check_tick](crate::check_tick) when they run.
+    pub fn check_change_ticks(&mut self) {
+        let change_tick = *self.change_tick.get_mut();
+        if change_tick.wrapping_sub(self.last_check_tick) < CHECK_TICK_THRESHOLD {
+            return;
+        }
         for archetype in self.archetypes.iter_mut() {
-            archetype.clear_trackers();
+            archetype.check_change_ticks(change_tick);
         }
-
-        self.removed_components.clear();
+        for sparse_set in self.sparse_sets.iter_mut() {
+            sparse_set.check_change_ticks(change_tick);
+        }
+        crate::check_tick(&mut self.last_change_tick, change_tick);
+        self.last_check_tick = change_tick;
     }
 
     /// Gets an entity reserver, which can be used to reserve entity ids in a multi-threaded context.
@@ -862,6 +1833,13 @@ impl<'a> IntoIterator for &'a mut World {
     }
 }
 
+fn into_array<T, const N: usize>(vec: Vec<T>) -> [T; N] {
+    match vec.try_into() {
+        Ok(array) => array,
+        Err(_) => panic!("expected {} elements", N),
+    }
+}
+
 fn index2<T>(x: &mut [T], i: usize, j: usize) -> (&mut T, &mut T) {
     assert!(i != j);
     assert!(i < x.len());
@@ -877,6 +1855,8 @@ pub enum ComponentError {
     NoSuchEntity,
     /// The entity did not have a requested component
     MissingComponent(MissingComponent),
+    /// The entity was requested more than once in a single mutable access
+    AliasedEntity(Entity),
 }
 
 #[cfg(feature = "std")]
@@ -888,6 +1868,7 @@ impl fmt::Display for ComponentError {
         match *self {
             NoSuchEntity => f.write_str("no such entity"),
             MissingComponent(ref x) => x.fmt(f),
+            AliasedEntity(entity) => write!(f, "entity {:?} was requested more than once", entity),
         }
     }
 }
@@ -904,6 +1885,38 @@ impl From<MissingComponent> for ComponentError {
     }
 }
 
+/// Errors that arise when moving entities between worlds
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum MoveEntitiesError {
+    /// One of the entities to move doesn't exist
+    NoSuchEntity(Entity),
+    /// A component of `entity` references an entity that isn't moved with it
+    EntityNotMoved {
+        /// The entity whose component references `referenced`
+        entity: Entity,
+        /// The entity that isn't moved
+        referenced: Entity,
+    },
+}
+
+#[cfg(feature = "std")]
+impl Error for MoveEntitiesError {}
+
+impl fmt::Display for MoveEntitiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            MoveEntitiesError::NoSuchEntity(entity) => {
+                write!(f, "entity {:?} does not exist", entity)
+            }
+            MoveEntitiesError::EntityNotMoved { entity, referenced } => write!(
+                f,
+                "entity {:?} references entity {:?}, which is not moved",
+                entity, referenced
+            ),
+        }
+    }
+}
+
 /// Types that can be components, implemented automatically for all `Send + Sync + 'static` types
 ///
 /// This is just a convenient shorthand for `Send + Sync + 'static`, and never needs to be
@@ -914,18 +1927,27 @@ impl<T: Send + Sync + 'static> Component for T {}
 /// Iterator over all of a world's entities
 pub struct Iter<'a> {
     archetypes: core::slice::Iter<'a, Archetype>,
+    sparse_sets: &'a SparseSets,
     entities: &'a Entities,
     current: Option<&'a Archetype>,
     index: usize,
+    change_tick: u32,
 }
 
 impl<'a> Iter<'a> {
-    fn new(archetypes: &'a [Archetype], entities: &'a Entities) -> Self {
+    fn new(
+        archetypes: &'a [Archetype],
+        sparse_sets: &'a SparseSets,
+        entities: &'a Entities,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetypes: archetypes.iter(),
+            sparse_sets,
             entities,
             current: None,
             index: 0,
+            change_tick,
         }
     }
 }
@@ -951,7 +1973,9 @@ impl<'a> Iterator for Iter<'a> {
                     let index = self.index;
                     self.index += 1;
                     let id = current.get_entity(index);
-                    return Some((id, unsafe { EntityRef::new(current, index) }));
+                    return Some((id, unsafe {
+                        EntityRef::new(current, self.sparse_sets, index, self.change_tick)
+                    }));
                 }
             }
         }
@@ -985,6 +2009,17 @@ impl<A: DynamicBundle> core::iter::FromIterator<A> for World {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ArchetypesGeneration(pub u64);
 
+/// Identifies a `World`, unique among the worlds created by the process
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WorldId(usize);
+
+impl WorldId {
+    fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        WorldId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 /// Entity IDs created by `World::spawn_batch`
 pub struct SpawnBatchIter<'a, I>
 where
@@ -992,9 +2027,9 @@ where
     I::Item: Bundle,
 {
     inner: I,
-    entities: &'a mut Entities,
+    world: &'a mut World,
     archetype_id: u32,
-    archetype: &'a mut Archetype,
+    hooked: Vec<ComponentId>,
 }
 
 impl<I> Drop for SpawnBatchIter<'_, I>
@@ -1016,18 +2051,36 @@ where
 
     fn next(&mut self) -> Option<Entity> {
         let components = self.inner.next()?;
-        let entity = self.entities.alloc();
+        let world = &mut *self.world;
+        let entity = world.entities.alloc();
         unsafe {
-            let index = self.archetype.allocate(entity);
+            let archetype = &mut world.archetypes[self.archetype_id as usize];
+            let index = archetype.allocate(entity);
+            let sparse_sets = &mut world.sparse_sets;
+            let change_tick = *world.change_tick.get_mut();
             components.put(|ptr, ty, size| {
-                self.archetype.put_dynamic(ptr, ty, size, index, true);
+                match sparse_sets.get_mut(ty) {
+                    Some(sparse_set) => sparse_set.insert(entity, ptr, change_tick),
+                    None => archetype.put_dynamic(
+                        ptr,
+                        ty,
+                        size,
+                        index,
+                        ComponentTicks::new(change_tick),
+                    ),
+                }
                 true
             });
-            self.entities.meta[entity.id as usize].location = Location {
+            world.entities.meta[entity.id as usize].location = Location {
                 archetype: self.archetype_id,
                 index,
             };
         }
+        if !self.hooked.is_empty() {
+            world.run_hooks(entity, &self.hooked, HookKind::Insert);
+            // hooks may reserve entities, which have to be flushed before the next allocation
+            world.flush();
+        }
         Some(entity)
     }
 
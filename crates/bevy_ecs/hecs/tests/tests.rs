@@ -397,3 +397,491 @@ fn added_tracking() {
         .get()
         .is_none());
 }
+
+#[test]
+fn sparse_set_components() {
+    let mut world = World::new();
+    world.register_component::<bool>(StorageType::SparseSet);
+
+    let a = world.spawn((123, true));
+    let b = world.spawn((456,));
+    assert_eq!(world.archetypes().count(), 2);
+
+    world.insert_one(b, false).unwrap();
+    world.remove_one::<bool>(a).unwrap();
+    assert_eq!(
+        world.archetypes().count(),
+        2,
+        "sparse set components do not move entities between archetypes"
+    );
+
+    world.insert_one(a, true).unwrap();
+    let mut ents = world
+        .query::<(Entity, &i32, &bool)>()
+        .iter()
+        .map(|(e, &i, &b)| (e, i, b))
+        .collect::<Vec<_>>();
+    ents.sort();
+    assert_eq!(ents, &[(a, 123, true), (b, 456, false)]);
+
+    world.remove_one::<bool>(b).unwrap();
+    assert_eq!(
+        world
+            .query::<Entity>()
+            .with::<bool>()
+            .iter()
+            .collect::<Vec<_>>(),
+        &[a]
+    );
+    assert_eq!(
+        world
+            .query::<Entity>()
+            .without::<bool>()
+            .iter()
+            .collect::<Vec<_>>(),
+        &[b]
+    );
+    assert_eq!(
+        world
+            .query::<(Entity, Option<&bool>)>()
+            .iter()
+            .collect::<Vec<_>>()
+            .len(),
+        2
+    );
+    assert!(*world.get::<bool>(a).unwrap());
+    assert!(world.get::<bool>(b).is_err());
+
+    world.despawn(a).unwrap();
+    assert_eq!(world.removed::<bool>(), &[a, b, a]);
+    assert_eq!(world.query::<&bool>().iter().count(), 0);
+}
+
+#[test]
+fn sparse_set_change_tracking() {
+    let mut world = World::new();
+    world.register_component::<bool>(StorageType::SparseSet);
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, false));
+
+    assert_eq!(world.query::<Added<bool>>().iter().count(), 2);
+    world.clear_trackers();
+    assert_eq!(world.query::<Added<bool>>().iter().count(), 0);
+    assert_eq!(world.query_mut::<Mutated<bool>>().iter().count(), 0);
+
+    *world.get_mut::<bool>(b).unwrap() = true;
+    assert_eq!(
+        world
+            .query_mut::<(Entity, Mutated<bool>)>()
+            .iter()
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>(),
+        &[b]
+    );
+    assert!(world.query_one::<Changed<bool>>(a).unwrap().get().is_none());
+}
+
+#[test]
+#[should_panic(expected = "i32 already borrowed")]
+fn sparse_query_alias() {
+    let mut world = World::new();
+    world.register_component::<i32>(StorageType::SparseSet);
+    world.spawn((123, true));
+    world.spawn((456,));
+    for _ in world.query_mut::<(&mut i32, &i32)>().iter() {}
+}
+
+#[test]
+fn component_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut world = World::new();
+    world.register_component::<bool>(StorageType::SparseSet);
+    let hook = |kind: &'static str| {
+        let log = log.clone();
+        move |world: &mut World, entity| {
+            let value = world.get::<i32>(entity).map(|x| *x).ok();
+            log.lock().unwrap().push((kind, entity, value));
+        }
+    };
+    world.on_insert::<i32>(hook("insert"));
+    world.on_replace::<i32>(hook("replace"));
+    world.on_remove::<i32>(hook("remove"));
+    let sparse_log = log.clone();
+    world.on_remove::<bool>(move |world, entity| {
+        let value = world.get::<bool>(entity).is_ok();
+        sparse_log
+            .lock()
+            .unwrap()
+            .push(("remove bool", entity, Some(value as i32)));
+    });
+
+    let a = world.spawn((1, true));
+    world.insert_one(a, 2).unwrap();
+    world.insert_one(a, "abc").unwrap();
+    world.remove_one::<i32>(a).unwrap();
+    assert!(world.remove_one::<i32>(a).is_err());
+    world.insert(a, (3, false)).unwrap();
+    world.despawn(a).unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            ("insert", a, Some(1)),
+            ("replace", a, Some(2)),
+            ("remove", a, Some(2)),
+            ("insert", a, Some(3)),
+            ("remove", a, Some(3)),
+            ("remove bool", a, Some(1)),
+        ]
+    );
+
+    log.lock().unwrap().clear();
+    let batch = world.spawn_batch(vec![(4,), (5,)]).collect::<Vec<_>>();
+    world.clear();
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            ("insert", batch[0], Some(4)),
+            ("insert", batch[1], Some(5)),
+            ("remove", batch[0], Some(4)),
+            ("remove", batch[1], Some(5)),
+        ]
+    );
+}
+
+#[test]
+fn spawn_batch_into_partially_filled_archetype() {
+    let mut world = World::new();
+    let a = world.spawn((0, "abc"));
+    let entities = world
+        .spawn_batch((1..1000).map(|i| (i, "abc")))
+        .collect::<Vec<_>>();
+    assert_eq!(*world.get::<i32>(a).unwrap(), 0);
+    for (i, &entity) in (1..).zip(&entities) {
+        assert_eq!(*world.get::<i32>(entity).unwrap(), i);
+    }
+}
+
+#[test]
+fn hook_despawns_own_entity() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let removed = Arc::new(AtomicUsize::new(0));
+    let mut world = World::new();
+    let removed_in_hook = removed.clone();
+    world.on_remove::<i32>(move |world, entity| {
+        removed_in_hook.fetch_add(1, Ordering::Relaxed);
+        world.despawn(entity).unwrap();
+    });
+    let bool_removed = removed.clone();
+    world.on_remove::<bool>(move |_, _| {
+        bool_removed.fetch_add(10, Ordering::Relaxed);
+    });
+
+    let a = world.spawn((1, true));
+    world.despawn(a).unwrap();
+    assert!(!world.contains(a));
+    assert_eq!(removed.load(Ordering::Relaxed), 11);
+
+    let b = world.spawn((2, true));
+    assert!(world.remove_one::<i32>(b).is_err());
+    assert!(!world.contains(b));
+    assert_eq!(removed.load(Ordering::Relaxed), 22);
+}
+
+#[test]
+fn query_state() {
+    let mut world = World::new();
+    world.spawn((1, true));
+    world.spawn(("abc",));
+    let mut query = QueryState::<(Entity, &i32)>::new(&world);
+    assert_eq!(query.matched_archetypes().len(), 1);
+
+    let b = world.spawn((2, "def"));
+    world.spawn((false,));
+    let mut values = query
+        .query(&world)
+        .iter()
+        .map(|(_, &x)| x)
+        .collect::<Vec<_>>();
+    values.sort();
+    assert_eq!(values, &[1, 2]);
+    assert_eq!(query.matched_archetypes().len(), 2);
+
+    let mut query_mut = QueryState::<&mut i32>::new(&world);
+    for mut x in &mut query_mut.query_mut(&mut world) {
+        *x *= 10;
+    }
+    assert_eq!(*world.get::<i32>(b).unwrap(), 20);
+
+    let mut sparse = QueryState::<With<u8, Entity>>::new(&world);
+    assert_eq!(sparse.query(&world).iter().count(), 0);
+    world.register_component::<u8>(StorageType::SparseSet);
+    world.insert_one(b, 0u8).unwrap();
+    assert_eq!(
+        sparse.query(&world).iter().collect::<Vec<_>>(),
+        &[b],
+        "registering a sparse set invalidates the matched archetypes"
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot be used with")]
+fn query_state_other_world() {
+    let mut world = World::new();
+    world.spawn((1,));
+    let mut query = QueryState::<&i32>::new(&world);
+    let other = World::new();
+    query.query(&other);
+}
+
+#[test]
+fn get_many_mut() {
+    let mut world = World::new();
+    let a = world.spawn((1, true));
+    let b = world.spawn((2, true));
+    let c = world.spawn((3,));
+    let d = world.spawn((true,));
+
+    let [mut x, mut y, mut z] = world.get_many_mut::<i32, 3>([c, a, b]).unwrap();
+    *x += 10;
+    *y += 20;
+    *z += 30;
+    assert_eq!(*world.get::<i32>(a).unwrap(), 21);
+    assert_eq!(*world.get::<i32>(b).unwrap(), 32);
+    assert_eq!(*world.get::<i32>(c).unwrap(), 13);
+
+    assert_eq!(
+        world.get_many_mut::<i32, 2>([b, b]).err(),
+        Some(ComponentError::AliasedEntity(b))
+    );
+    assert!(matches!(
+        world.get_many_mut::<i32, 2>([a, d]),
+        Err(ComponentError::MissingComponent(_))
+    ));
+
+    world.register_component::<u8>(StorageType::SparseSet);
+    world.insert_one(a, 1u8).unwrap();
+    world.insert_one(d, 2u8).unwrap();
+    let [mut x, mut y] = world.get_many_mut::<u8, 2>([a, d]).unwrap();
+    core::mem::swap(&mut *x, &mut *y);
+    assert_eq!(*world.get::<u8>(a).unwrap(), 2);
+    assert_eq!(*world.get::<u8>(d).unwrap(), 1);
+}
+
+#[test]
+fn runtime_components() {
+    use core::alloc::Layout;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    unsafe fn drop_arc(ptr: *mut u8) {
+        ptr.cast::<Arc<AtomicUsize>>().drop_in_place()
+    }
+    unsafe fn drop_nothing(_ptr: *mut u8) {}
+
+    let counter = TypeInfo::dynamic(1, Layout::new::<Arc<AtomicUsize>>(), drop_arc);
+    let tag = TypeInfo::dynamic(2, Layout::new::<()>(), drop_nothing);
+    let value = Arc::new(AtomicUsize::new(0));
+
+    let mut world = World::new();
+    world.register_dynamic_component(tag, StorageType::SparseSet);
+    let mut builder = EntityBuilder::new();
+    let mut entities = Vec::new();
+    for i in 0..3 {
+        let mut component = value.clone();
+        unsafe {
+            builder.add_dynamic(counter, (&mut component as *mut Arc<AtomicUsize>).cast());
+        }
+        std::mem::forget(component);
+        builder.add(i);
+        entities.push(world.spawn(builder.build()));
+    }
+    unsafe {
+        builder.add_dynamic(tag, core::ptr::NonNull::<()>::dangling().as_ptr().cast());
+    }
+    world.insert(entities[1], builder.build()).unwrap();
+    assert_eq!(Arc::strong_count(&value), 4);
+
+    let mut query = DynamicQuery::new();
+    query
+        .write(counter.id())
+        .read(ComponentId::of::<i32>())
+        .without(tag.id());
+    let mut seen = Vec::new();
+    for item in &mut world.query_dynamic(&query) {
+        unsafe {
+            let component = &*item.get(0).cast::<Arc<AtomicUsize>>().as_ptr();
+            component.fetch_add(1, Ordering::Relaxed);
+            seen.push(*item.get(1).cast::<i32>().as_ptr());
+        }
+    }
+    seen.sort();
+    assert_eq!(seen, &[0, 2]);
+    assert_eq!(value.load(Ordering::Relaxed), 2);
+
+    let mut tagged = DynamicQuery::new();
+    tagged.with(tag.id());
+    let tagged = world
+        .query_dynamic(&tagged)
+        .iter()
+        .map(|item| item.entity())
+        .collect::<Vec<_>>();
+    assert_eq!(tagged, &[entities[1]]);
+
+    assert!(world.remove_dynamic(entities[0], &[tag.id()]).is_err());
+    world.remove_dynamic(entities[0], &[counter.id()]).unwrap();
+    assert!(!world.has_component(entities[0], counter.id()));
+    assert_eq!(*world.get::<i32>(entities[0]).unwrap(), 0);
+    assert_eq!(Arc::strong_count(&value), 3);
+
+    world.despawn(entities[1]).unwrap();
+    assert_eq!(Arc::strong_count(&value), 2);
+    drop(world);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
+
+#[test]
+#[should_panic(expected = "already borrowed")]
+fn dynamic_query_borrow_conflict() {
+    let mut world = World::new();
+    world.spawn((123,));
+    let mut query = DynamicQuery::new();
+    query.write(ComponentId::of::<i32>());
+    let _borrow = world.query_dynamic(&query);
+    let mut other = DynamicQuery::new();
+    other.read(ComponentId::of::<i32>());
+    world.query_dynamic(&other);
+}
+
+#[test]
+fn restore_entities() {
+    let mut world = World::new();
+    world.register_component::<bool>(StorageType::SparseSet);
+    let a = world.spawn((1, true));
+    let b = world.spawn((2,));
+    world.despawn(b).unwrap();
+    let snapshot = world.snapshot_entities();
+
+    world.despawn(a).unwrap();
+    let c = world.spawn((3,));
+    let d = world.spawn((4, false));
+    assert_eq!(c.id(), a.id());
+
+    world.clear_trackers();
+    world.restore_entities(&snapshot);
+    assert!(world.contains(a));
+    assert!(!world.contains(b));
+    assert!(!world.contains(c));
+    assert!(world.get::<i32>(a).is_err());
+    assert!(world.get::<bool>(a).is_err());
+    assert_eq!(world.removed::<i32>(), &[]);
+    assert_eq!(world.query::<Entity>().iter().collect::<Vec<_>>(), &[a]);
+
+    world.insert(a, (1, true)).unwrap();
+    assert_eq!(*world.get::<i32>(a).unwrap(), 1);
+    assert!(*world.get::<bool>(a).unwrap());
+
+    // the allocator hands out the same entities as it did after the snapshot was taken
+    assert_eq!(world.spawn((5,)), d);
+}
+
+#[test]
+fn move_entities_between_worlds() {
+    struct Parent(Entity);
+
+    impl MapEntities for Parent {
+        fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+            self.0 = entity_map.get(self.0)?;
+            Ok(())
+        }
+    }
+
+    let mut level = World::new();
+    level.register_component::<bool>(StorageType::SparseSet);
+    let root = level.spawn((1, "root"));
+    let child = level.spawn((2, true, Parent(root)));
+    let other = level.spawn((3,));
+
+    let mut world = World::new();
+    world.register_map_entities::<Parent>();
+    let existing = world.spawn((0,));
+    world.clear_trackers();
+
+    let entity_map = world
+        .move_entities_from(&mut level, &[root, child])
+        .unwrap();
+    assert_eq!(entity_map.len(), 2);
+    let (new_root, new_child) = (
+        entity_map.get(root).unwrap(),
+        entity_map.get(child).unwrap(),
+    );
+    assert!(!level.contains(root) && !level.contains(child));
+    assert_eq!(*level.get::<i32>(other).unwrap(), 3);
+    assert_eq!(*world.get::<&str>(new_root).unwrap(), "root");
+    assert_eq!(*world.get::<i32>(new_child).unwrap(), 2);
+    assert_eq!(world.get::<Parent>(new_child).unwrap().0, new_root);
+    assert!(*world.get::<bool>(new_child).unwrap());
+    assert_eq!(
+        world.storage_type(ComponentId::of::<bool>()),
+        StorageType::SparseSet
+    );
+    assert_eq!(
+        world.query::<Added<i32>>().iter().count(),
+        2,
+        "moved components count as added"
+    );
+    assert_eq!(
+        world.move_entities_from(&mut level, &[root]).unwrap_err(),
+        MoveEntitiesError::NoSuchEntity(root)
+    );
+
+    // moving a child without its parent fails before either world is changed
+    let orphan = level.spawn((4, Parent(other)));
+    level.clear_trackers();
+    assert_eq!(
+        world.move_entities_from(&mut level, &[orphan]).unwrap_err(),
+        MoveEntitiesError::EntityNotMoved {
+            entity: orphan,
+            referenced: other
+        }
+    );
+    assert_eq!(level.get::<Parent>(orphan).unwrap().0, other);
+    assert_eq!(level.query::<Changed<Parent>>().iter().count(), 0);
+    assert_eq!(world.query::<Entity>().iter().count(), 3);
+
+    let entity_map = world.merge(&mut level).unwrap();
+    assert_eq!(level.query::<Entity>().iter().count(), 0);
+    assert_eq!(
+        *world.get::<i32>(entity_map.get(other).unwrap()).unwrap(),
+        3
+    );
+    assert_eq!(*world.get::<i32>(existing).unwrap(), 0);
+    assert_eq!(world.query::<Entity>().iter().count(), 5);
+    assert_eq!(
+        world
+            .get::<Parent>(entity_map.get(orphan).unwrap())
+            .unwrap()
+            .0,
+        entity_map.get(other).unwrap()
+    );
+
+    // merging a world with a dangling reference fails without moving anything
+    let dangling = level.spawn((5, Parent(root)));
+    assert_eq!(
+        world.merge(&mut level).unwrap_err(),
+        MoveEntitiesError::EntityNotMoved {
+            entity: dangling,
+            referenced: root
+        }
+    );
+    assert!(level.contains(dangling));
+    assert_eq!(world.query::<Entity>().iter().count(), 5);
+}
@@ -11,12 +11,19 @@ pub use world::*;
 
 pub mod prelude {
     pub use crate::{
-        resource::{ChangedRes, FromResources, Local, OrRes, Res, ResMut, Resource, Resources},
+        resource::{
+            ChangedRes, FromResources, Local, OrRes, RemovedComponents, Res, ResMut, Resource,
+            Resources, SeededRng,
+        },
+        schedule::{
+            IntoRunCriteria, IntoSystemDescriptor, RunCriteria, ShouldRun, State, StateSystem,
+        },
         system::{
-            Commands, IntoForEachSystem, IntoQuerySystem, IntoThreadLocalSystem, Query, System,
+            Commands, In, IntoChainSystem, IntoForEachSystem, IntoQuerySystem,
+            IntoThreadLocalSystem, Query, System,
         },
-        world::WorldBuilderSource,
-        Added, Bundle, Changed, Component, Entity, Mut, Mutated, Or, Ref, RefMut, With, Without,
-        World,
+        world::{Relation, RelationSources, WorldBuilderSource},
+        Added, Bundle, Changed, Component, Entity, Mut, Mutated, Or, Ref, RefMut, SystemParam,
+        With, Without, World,
     };
 }
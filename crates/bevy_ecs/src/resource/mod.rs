@@ -1,5 +1,9 @@
+mod removed_components;
 mod resource_query;
 mod resources;
+mod seeded_rng;
 
+pub use removed_components::*;
 pub use resource_query::*;
 pub use resources::*;
+pub use seeded_rng::*;
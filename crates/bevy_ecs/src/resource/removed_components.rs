@@ -0,0 +1,269 @@
+use super::{FetchResource, ResourceQuery, Resources, UnsafeClone};
+use crate::{
+    system::{SystemId, TypeAccess},
+    ResourceIndex,
+};
+use bevy_hecs::{Component, ComponentId, Entity, World};
+use bevy_utils::HashMap;
+use core::any::TypeId;
+use std::marker::PhantomData;
+
+#[derive(Debug, Default)]
+struct RemovedEntities {
+    entities: Vec<Entity>,
+    /// the event count of the first entity in `entities`
+    start_event_count: usize,
+    /// the event count at the start of the current update
+    update_start_event_count: usize,
+    /// how many of the `World`'s removals for this update have already been recorded
+    recorded: usize,
+}
+
+impl RemovedEntities {
+    fn event_count(&self) -> usize {
+        self.start_event_count + self.entities.len()
+    }
+
+    fn update(&mut self) {
+        let expired = self.update_start_event_count - self.start_event_count;
+        self.entities.drain(..expired);
+        self.start_event_count = self.update_start_event_count;
+        self.update_start_event_count = self.event_count();
+        self.recorded = 0;
+    }
+}
+
+/// A record of the entities that had components removed, kept across the last two updates. This is
+/// filled from the `World` by the schedule after each stage, and read by systems through
+/// [RemovedComponents].
+///
+/// Like `Events`, removals are dropped two updates after they happened, so systems that read at
+/// least once per update never miss one.
+#[derive(Debug, Default)]
+pub struct RemovedComponentEvents {
+    removed: HashMap<ComponentId, RemovedEntities>,
+}
+
+impl RemovedComponentEvents {
+    /// Records the removals made in `world` since the last call
+    pub fn record(&mut self, world: &World) {
+        for (ty, entities) in world.removed_components() {
+            let removed = self.removed.entry(ty).or_default();
+            // the world's removals were cleared without an update
+            if removed.recorded > entities.len() {
+                removed.recorded = 0;
+            }
+            removed
+                .entities
+                .extend_from_slice(&entities[removed.recorded..]);
+            removed.recorded = entities.len();
+        }
+    }
+
+    /// Drops the removals from before the previous update. This should be called once per update,
+    /// right before the `World`'s trackers are cleared.
+    pub fn update(&mut self) {
+        for removed in self.removed.values_mut() {
+            removed.update();
+        }
+    }
+
+    /// Gets a new [RemovedComponentsReader] for `T`. This will include all removals currently recorded.
+    pub fn get_reader<T: Component>(&self) -> RemovedComponentsReader<T> {
+        RemovedComponentsReader::default()
+    }
+
+    fn get(&self, ty: ComponentId) -> Option<&RemovedEntities> {
+        self.removed.get(&ty)
+    }
+}
+
+/// Records the removals made in `world` in the [RemovedComponentEvents] resource, if it exists
+pub(crate) fn record_removed_components(world: &World, resources: &Resources) {
+    if let Some(mut events) = resources.get_mut::<RemovedComponentEvents>() {
+        events.record(world);
+    }
+}
+
+/// Records the removals made in `world` and ends the update for the [RemovedComponentEvents]
+/// resource, if it exists
+pub(crate) fn update_removed_components(world: &World, resources: &Resources) {
+    if let Some(mut events) = resources.get_mut::<RemovedComponentEvents>() {
+        events.record(world);
+        events.update();
+    }
+}
+
+/// Reads the entities that had component `T` removed, in order, and tracks which ones have already
+/// been read.
+#[derive(Debug)]
+pub struct RemovedComponentsReader<T: Component> {
+    last_event_count: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Component> Default for RemovedComponentsReader<T> {
+    fn default() -> Self {
+        Self {
+            last_event_count: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> RemovedComponentsReader<T> {
+    /// Iterates over the entities this reader has not seen yet, and marks them as seen.
+    pub fn iter<'a>(
+        &mut self,
+        events: &'a RemovedComponentEvents,
+    ) -> impl DoubleEndedIterator<Item = Entity> + 'a {
+        let entities = events
+            .get(ComponentId::of::<T>())
+            .map_or(&[][..], |removed| {
+                let index = self
+                    .last_event_count
+                    .saturating_sub(removed.start_event_count);
+                self.last_event_count = removed.event_count();
+                removed.entities.get(index..).unwrap_or(&[])
+            });
+        entities.iter().copied()
+    }
+}
+
+/// A system parameter that yields the entities that had component `T` removed since the system last
+/// read them. Unlike `Query::removed`, this also sees removals made in earlier stages or while the
+/// system wasn't running, as long as it reads at least once every other update.
+///
+/// # Example
+/// ```
+/// use bevy_ecs::prelude::*;
+///
+/// struct RigidBody;
+///
+/// fn free_bodies(mut removed: RemovedComponents<RigidBody>) {
+///     for entity in removed.iter() {
+///         println!("{:?} lost its rigid body", entity);
+///     }
+/// }
+/// # let _ = free_bodies.system();
+/// ```
+#[derive(Debug)]
+pub struct RemovedComponents<'a, T: Component> {
+    events: &'a RemovedComponentEvents,
+    reader: *mut RemovedComponentsReader<T>,
+}
+
+impl<'a, T: Component> RemovedComponents<'a, T> {
+    /// Iterates over the entities this system has not seen yet, and marks them as seen.
+    pub fn iter(&mut self) -> impl DoubleEndedIterator<Item = Entity> + 'a {
+        // SAFE: the reader is a system local, which is uniquely borrowed by this system
+        unsafe { (*self.reader).iter(self.events) }
+    }
+}
+
+impl<'a, T: Component> UnsafeClone for RemovedComponents<'a, T> {
+    unsafe fn unsafe_clone(&self) -> Self {
+        Self {
+            events: self.events,
+            reader: self.reader,
+        }
+    }
+}
+
+unsafe impl<T: Component> Send for RemovedComponents<'_, T> {}
+unsafe impl<T: Component> Sync for RemovedComponents<'_, T> {}
+
+impl<'a, T: Component> ResourceQuery for RemovedComponents<'a, T> {
+    type Fetch = FetchRemovedComponents<T>;
+
+    fn initialize(resources: &mut Resources, id: Option<SystemId>) {
+        let id = id.expect("RemovedComponents<T> can only be used by systems");
+        resources.get_or_insert_with(RemovedComponentEvents::default);
+        resources.insert_local(id, RemovedComponentsReader::<T>::default());
+    }
+}
+
+/// Fetches a `RemovedComponents<T>`
+#[derive(Debug)]
+pub struct FetchRemovedComponents<T>(PhantomData<T>);
+
+impl<'a, T: Component> FetchResource<'a> for FetchRemovedComponents<T> {
+    type Item = RemovedComponents<'a, T>;
+
+    unsafe fn get(
+        resources: &'a Resources,
+        system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Self::Item {
+        let id = system_id.expect("RemovedComponents<T> can only be used by systems");
+        RemovedComponents {
+            events: &*resources
+                .get_unsafe_ref::<RemovedComponentEvents>(ResourceIndex::Global)
+                .as_ptr(),
+            reader: resources
+                .get_unsafe_ref::<RemovedComponentsReader<T>>(ResourceIndex::System(id))
+                .as_ptr(),
+        }
+    }
+
+    fn borrow(resources: &Resources) {
+        resources.borrow::<RemovedComponentEvents>();
+        resources.borrow_mut::<RemovedComponentsReader<T>>();
+    }
+
+    fn release(resources: &Resources) {
+        resources.release::<RemovedComponentEvents>();
+        resources.release_mut::<RemovedComponentsReader<T>>();
+    }
+
+    fn access() -> TypeAccess {
+        let mut access = TypeAccess::default();
+        access
+            .immutable
+            .insert(TypeId::of::<RemovedComponentEvents>());
+        access
+            .mutable
+            .insert(TypeId::of::<RemovedComponentsReader<T>>());
+        access
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct A;
+
+    #[test]
+    fn readers_see_removals_from_the_last_two_updates() {
+        let mut world = World::new();
+        let mut events = RemovedComponentEvents::default();
+        let mut reader = events.get_reader::<A>();
+        let mut late_reader = events.get_reader::<A>();
+
+        let a = world.spawn((A,));
+        let b = world.spawn((A,));
+        world.despawn(a).unwrap();
+        events.record(&world);
+        world.remove_one::<A>(b).unwrap();
+        events.record(&world);
+        assert_eq!(reader.iter(&events).collect::<Vec<_>>(), vec![a, b]);
+
+        events.update();
+        world.clear_trackers();
+        let c = world.spawn((A,));
+        world.despawn(c).unwrap();
+        events.record(&world);
+        assert_eq!(reader.iter(&events).collect::<Vec<_>>(), vec![c]);
+        assert_eq!(reader.iter(&events).count(), 0);
+
+        events.update();
+        world.clear_trackers();
+        assert_eq!(
+            late_reader.iter(&events).collect::<Vec<_>>(),
+            vec![c],
+            "removals are dropped two updates after they happened"
+        );
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     system::{SystemId, TypeAccess},
     Resource, ResourceIndex,
 };
-use bevy_hecs::smaller_tuples_too;
+use bevy_hecs::{smaller_tuples_too, ComponentTicks};
 use core::{
     any::TypeId,
     ops::{Deref, DerefMut},
@@ -12,7 +12,8 @@ use core::{
 use std::marker::PhantomData;
 
 /// A shared borrow of a Resource
-/// that will only return in a query if the Resource has been changed
+/// that will only return in a query if the Resource has been added or mutated since the system
+/// last ran
 #[derive(Debug)]
 pub struct ChangedRes<'a, T: Resource> {
     value: &'a T,
@@ -93,18 +94,25 @@ impl<'a, T: Resource> Deref for Res<'a, T> {
 pub struct ResMut<'a, T: Resource> {
     _marker: PhantomData<&'a T>,
     value: *mut T,
-    mutated: *mut bool,
+    component_ticks: *mut ComponentTicks,
+    change_tick: u32,
 }
 
 impl<'a, T: Resource> ResMut<'a, T> {
-    /// Creates a mutable reference cell to a Resource from a pointer
+    /// Creates a mutable reference cell to a Resource from a pointer. Mutations are recorded in
+    /// `component_ticks` with `change_tick`.
     ///
     /// # Safety
     /// The pointer must have correct lifetime / storage / ownership
-    pub unsafe fn new(value: NonNull<T>, mutated: NonNull<bool>) -> Self {
+    pub unsafe fn new(
+        value: NonNull<T>,
+        component_ticks: NonNull<ComponentTicks>,
+        change_tick: u32,
+    ) -> Self {
         Self {
             value: value.as_ptr(),
-            mutated: mutated.as_ptr(),
+            component_ticks: component_ticks.as_ptr(),
+            change_tick,
             _marker: Default::default(),
         }
     }
@@ -124,7 +132,7 @@ impl<'a, T: Resource> Deref for ResMut<'a, T> {
 impl<'a, T: Resource> DerefMut for ResMut<'a, T> {
     fn deref_mut(&mut self) -> &mut T {
         unsafe {
-            *self.mutated = true;
+            (*self.component_ticks).set_mutated(self.change_tick);
             &mut *self.value
         }
     }
@@ -134,7 +142,8 @@ impl<'a, T: Resource> UnsafeClone for ResMut<'a, T> {
     unsafe fn unsafe_clone(&self) -> Self {
         Self {
             value: self.value,
-            mutated: self.mutated,
+            component_ticks: self.component_ticks,
+            change_tick: self.change_tick,
             _marker: Default::default(),
         }
     }
@@ -187,11 +196,24 @@ pub trait FetchResource<'a>: Sized {
     fn borrow(resources: &Resources);
     fn release(resources: &Resources);
 
+    /// Fetches the resources. Mutations are recorded with `change_tick`.
     #[allow(clippy::missing_safety_doc)]
-    unsafe fn get(resources: &'a Resources, system_id: Option<SystemId>) -> Self::Item;
-
+    unsafe fn get(
+        resources: &'a Resources,
+        system_id: Option<SystemId>,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self::Item;
+
+    /// Returns false if the query should not return. `ChangedRes` only returns if its resource
+    /// changed after `last_change_tick`.
     #[allow(clippy::missing_safety_doc)]
-    unsafe fn is_some(_resources: &'a Resources, _system_id: Option<SystemId>) -> bool {
+    unsafe fn is_some(
+        _resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> bool {
         true
     }
 }
@@ -207,7 +229,12 @@ pub struct FetchResourceRead<T>(NonNull<T>);
 impl<'a, T: Resource> FetchResource<'a> for FetchResourceRead<T> {
     type Item = Res<'a, T>;
 
-    unsafe fn get(resources: &'a Resources, _system_id: Option<SystemId>) -> Self::Item {
+    unsafe fn get(
+        resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Self::Item {
         Res::new(resources.get_unsafe_ref::<T>(ResourceIndex::Global))
     }
 
@@ -237,13 +264,23 @@ pub struct FetchResourceChanged<T>(NonNull<T>);
 impl<'a, T: Resource> FetchResource<'a> for FetchResourceChanged<T> {
     type Item = ChangedRes<'a, T>;
 
-    unsafe fn get(resources: &'a Resources, _system_id: Option<SystemId>) -> Self::Item {
+    unsafe fn get(
+        resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Self::Item {
         ChangedRes::new(resources.get_unsafe_ref::<T>(ResourceIndex::Global))
     }
 
-    unsafe fn is_some(resources: &'a Resources, _system_id: Option<SystemId>) -> bool {
-        let (added, mutated) = resources.get_unsafe_added_and_mutated::<T>(ResourceIndex::Global);
-        *added.as_ptr() || *mutated.as_ptr()
+    unsafe fn is_some(
+        resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> bool {
+        let (_, component_ticks) = resources.get_unsafe_ref_with_ticks::<T>(ResourceIndex::Global);
+        (*component_ticks.as_ptr()).is_changed(last_change_tick, change_tick)
     }
 
     fn borrow(resources: &Resources) {
@@ -272,10 +309,15 @@ pub struct FetchResourceWrite<T>(NonNull<T>);
 impl<'a, T: Resource> FetchResource<'a> for FetchResourceWrite<T> {
     type Item = ResMut<'a, T>;
 
-    unsafe fn get(resources: &'a Resources, _system_id: Option<SystemId>) -> Self::Item {
-        let (value, type_state) =
-            resources.get_unsafe_ref_with_type_state::<T>(ResourceIndex::Global);
-        ResMut::new(value, type_state.mutated())
+    unsafe fn get(
+        resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self::Item {
+        let (value, component_ticks) =
+            resources.get_unsafe_ref_with_ticks::<T>(ResourceIndex::Global);
+        ResMut::new(value, component_ticks, change_tick)
     }
 
     fn borrow(resources: &Resources) {
@@ -310,7 +352,12 @@ pub struct FetchResourceLocalMut<T>(NonNull<T>);
 impl<'a, T: Resource + FromResources> FetchResource<'a> for FetchResourceLocalMut<T> {
     type Item = Local<'a, T>;
 
-    unsafe fn get(resources: &'a Resources, system_id: Option<SystemId>) -> Self::Item {
+    unsafe fn get(
+        resources: &'a Resources,
+        system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        _change_tick: u32,
+    ) -> Self::Item {
         let id = system_id.expect("Local<T> resources can only be used by systems");
         Local {
             value: resources
@@ -351,13 +398,23 @@ macro_rules! tuple_impl {
             }
 
             #[allow(unused_variables)]
-            unsafe fn get(resources: &'a Resources, system_id: Option<SystemId>) -> Self::Item {
-                ($($name::get(resources, system_id),)*)
+            unsafe fn get(
+                resources: &'a Resources,
+                system_id: Option<SystemId>,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> Self::Item {
+                ($($name::get(resources, system_id, last_change_tick, change_tick),)*)
             }
 
             #[allow(unused_variables)]
-            unsafe fn is_some(resources: &'a Resources, system_id: Option<SystemId>) -> bool {
-                true $(&& $name::is_some(resources, system_id))*
+            unsafe fn is_some(
+                resources: &'a Resources,
+                system_id: Option<SystemId>,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> bool {
+                true $(&& $name::is_some(resources, system_id, last_change_tick, change_tick))*
             }
 
             #[allow(unused_mut)]
@@ -412,13 +469,23 @@ macro_rules! tuple_impl_or {
             }
 
             #[allow(unused_variables)]
-            unsafe fn get(resources: &'a Resources, system_id: Option<SystemId>) -> Self::Item {
-                OrRes(($($name::get(resources, system_id),)*))
+            unsafe fn get(
+                resources: &'a Resources,
+                system_id: Option<SystemId>,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> Self::Item {
+                OrRes(($($name::get(resources, system_id, last_change_tick, change_tick),)*))
             }
 
             #[allow(unused_variables)]
-            unsafe fn is_some(resources: &'a Resources, system_id: Option<SystemId>) -> bool {
-                false $(|| $name::is_some(resources, system_id))*
+            unsafe fn is_some(
+                resources: &'a Resources,
+                system_id: Option<SystemId>,
+                last_change_tick: u32,
+                change_tick: u32,
+            ) -> bool {
+                false $(|| $name::is_some(resources, system_id, last_change_tick, change_tick))*
             }
 
             #[allow(unused_mut)]
@@ -1,6 +1,9 @@
 use super::{FetchResource, ResourceQuery};
 use crate::system::SystemId;
-use bevy_hecs::{Archetype, AtomicBorrow, Entity, Ref, RefMut, TypeInfo, TypeState};
+use bevy_hecs::{
+    Archetype, AtomicBorrow, ComponentTicks, Entity, Ref, RefMut, TypeInfo, TypeState,
+    CHECK_TICK_THRESHOLD,
+};
 use bevy_utils::HashMap;
 use core::any::TypeId;
 use downcast_rs::{impl_downcast, Downcast};
@@ -8,6 +11,7 @@ use std::{
     fmt::Debug,
     ops::{Deref, DerefMut},
     ptr::NonNull,
+    sync::atomic::{AtomicU32, Ordering},
     thread::ThreadId,
 };
 
@@ -84,10 +88,16 @@ impl<T: 'static> Default for VecResourceStorage<T> {
 impl<T: 'static> ResourceStorage for VecResourceStorage<T> {}
 
 /// A collection of resource instances identified by their type.
+///
+/// Like the `World`, resources record changes with a change tick that systems advance every time
+/// they run.
 pub struct Resources {
     pub(crate) resource_data: HashMap<TypeId, ResourceData>,
     thread_local_data: HashMap<TypeId, Box<dyn ResourceStorage>>,
     main_thread_id: ThreadId,
+    change_tick: AtomicU32,
+    last_change_tick: u32,
+    last_check_tick: u32,
 }
 
 impl Default for Resources {
@@ -96,6 +106,9 @@ impl Default for Resources {
             resource_data: Default::default(),
             thread_local_data: Default::default(),
             main_thread_id: std::thread::current().id(),
+            change_tick: AtomicU32::new(1),
+            last_change_tick: 0,
+            last_check_tick: 0,
         }
     }
 }
@@ -201,6 +214,7 @@ impl Resources {
             }
         });
 
+        let change_tick = *self.change_tick.get_mut();
         let archetype = &mut data.archetype;
         let mut added = false;
         let index = match resource_index {
@@ -227,13 +241,21 @@ impl Resources {
         }
 
         unsafe {
+            let component_ticks = if added {
+                ComponentTicks::new(change_tick)
+            } else {
+                let type_state = archetype.get_type_state(type_id.into()).unwrap();
+                let mut component_ticks = *type_state.component_ticks().as_ptr().add(index);
+                component_ticks.set_mutated(change_tick);
+                component_ticks
+            };
             let resource_ptr = (&mut resource as *mut T).cast::<u8>();
             archetype.put_dynamic(
                 resource_ptr,
-                type_id,
+                type_id.into(),
                 core::mem::size_of::<T>(),
                 index,
-                added,
+                component_ticks,
             );
             std::mem::forget(resource);
         }
@@ -262,27 +284,39 @@ impl Resources {
                     ResourceIndex::Global => data.default_index?,
                     ResourceIndex::System(id) => *data.system_id_to_archetype_index.get(&id.0)?,
                 };
-                RefMut::new(&data.archetype, index).ok()
+                RefMut::new(&data.archetype, index, self.read_change_tick()).ok()
             })
     }
 
+    /// Queries resources outside of a system. `ChangedRes` sees the changes made since the last
+    /// call to `clear_trackers`.
     pub fn query<Q: ResourceQuery>(&self) -> Option<<Q::Fetch as FetchResource>::Item> {
+        let (last_change_tick, change_tick) = (self.last_change_tick, self.read_change_tick());
         unsafe {
-            if Q::Fetch::is_some(&self, None) {
-                Some(Q::Fetch::get(&self, None))
+            if Q::Fetch::is_some(&self, None, last_change_tick, change_tick) {
+                Some(Q::Fetch::get(&self, None, last_change_tick, change_tick))
             } else {
                 None
             }
         }
     }
 
+    /// Queries resources for the system `id`. `ChangedRes` sees the changes made after
+    /// `last_change_tick`, and mutations are recorded with `change_tick`.
     pub fn query_system<Q: ResourceQuery>(
         &self,
         id: SystemId,
+        last_change_tick: u32,
+        change_tick: u32,
     ) -> Option<<Q::Fetch as FetchResource>::Item> {
         unsafe {
-            if Q::Fetch::is_some(&self, Some(id)) {
-                Some(Q::Fetch::get(&self, Some(id)))
+            if Q::Fetch::is_some(&self, Some(id), last_change_tick, change_tick) {
+                Some(Q::Fetch::get(
+                    &self,
+                    Some(id),
+                    last_change_tick,
+                    change_tick,
+                ))
             } else {
                 None
             }
@@ -323,17 +357,22 @@ impl Resources {
 
     #[inline]
     #[allow(clippy::missing_safety_doc)]
-    pub unsafe fn get_unsafe_added_and_mutated<T: Resource>(
+    pub unsafe fn get_unsafe_ref_with_ticks<T: Resource>(
         &self,
         resource_index: ResourceIndex,
-    ) -> (NonNull<bool>, NonNull<bool>) {
+    ) -> (NonNull<T>, NonNull<ComponentTicks>) {
         self.get_resource_data_index::<T>(resource_index)
             .and_then(|(data, index)| {
-                let type_state = data.archetype.get_type_state(TypeId::of::<T>())?;
-                Some((
-                    NonNull::new_unchecked(type_state.added().as_ptr().add(index)),
-                    NonNull::new_unchecked(type_state.mutated().as_ptr().add(index)),
-                ))
+                data.archetype
+                    .get_with_type_state::<T>()
+                    .map(|(resource, type_state)| {
+                        (
+                            NonNull::new_unchecked(resource.as_ptr().add(index)),
+                            NonNull::new_unchecked(
+                                type_state.component_ticks().as_ptr().add(index),
+                            ),
+                        )
+                    })
             })
             .unwrap_or_else(|| panic!("Resource does not exist {}", std::any::type_name::<T>()))
     }
@@ -378,12 +417,40 @@ impl Resources {
         }
     }
 
-    /// Clears each resource's tracker state.
-    /// For example, each resource's component "mutated" state will be reset to `false`.
+    /// Starts a new change detection period for resource queries made outside of systems. Systems
+    /// track their own last change tick and are unaffected.
     pub fn clear_trackers(&mut self) {
-        for (_, resource_data) in self.resource_data.iter_mut() {
-            resource_data.archetype.clear_trackers();
+        self.last_change_tick = self.increment_change_tick();
+    }
+
+    /// Advances the change tick, returning the tick to use for the changes about to be made. Every
+    /// system run gets its own tick.
+    pub fn increment_change_tick(&self) -> u32 {
+        self.change_tick.fetch_add(1, Ordering::AcqRel)
+    }
+
+    /// The tick used to record changes made outside of systems
+    pub fn read_change_tick(&self) -> u32 {
+        self.change_tick.load(Ordering::Acquire)
+    }
+
+    /// The tick of the last `clear_trackers` call
+    pub fn last_change_tick(&self) -> u32 {
+        self.last_change_tick
+    }
+
+    /// Clamps old resource change ticks so that they can still be compared after the change tick
+    /// wraps around. See `World::check_change_ticks`.
+    pub fn check_change_ticks(&mut self) {
+        let change_tick = *self.change_tick.get_mut();
+        if change_tick.wrapping_sub(self.last_check_tick) < CHECK_TICK_THRESHOLD {
+            return;
+        }
+        for resource_data in self.resource_data.values_mut() {
+            resource_data.archetype.check_change_ticks(change_tick);
         }
+        bevy_hecs::check_tick(&mut self.last_change_tick, change_tick);
+        self.last_check_tick = change_tick;
     }
 }
 
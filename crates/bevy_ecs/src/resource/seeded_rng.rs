@@ -0,0 +1,83 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// A random number generator resource that produces the same sequence of numbers for the same seed
+///
+/// Systems that take their randomness from this resource make the same changes on every run of a
+/// [deterministic](crate::ParallelExecutor::deterministic) executor started from the same seed,
+/// which allows replaying recorded inputs and lockstep simulation. It implements
+/// [RngCore](rand::RngCore), so it can be used with the methods of [Rng](rand::Rng).
+///
+/// The numbers come from ChaCha8, whose output is the same on every platform and won't change with
+/// the version of `rand`.
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed this generator was created or last reseeded with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restarts the sequence of numbers from `seed`
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+}
+
+impl Default for SeededRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeededRng;
+    use rand::RngCore;
+
+    #[test]
+    fn sequence_is_stable() {
+        let mut rng = SeededRng::new(42);
+        let numbers = (0..3).map(|_| rng.next_u64()).collect::<Vec<_>>();
+        rng.reseed(42);
+        assert_eq!(numbers, (0..3).map(|_| rng.next_u64()).collect::<Vec<_>>());
+        // fixed for every platform and version
+        assert_eq!(
+            numbers,
+            vec![
+                12578764544318200737,
+                17529487244874322312,
+                7886285670807131020
+            ]
+        );
+    }
+}
@@ -1,6 +1,14 @@
 mod parallel_executor;
+mod run_criteria;
 #[allow(clippy::module_inception)]
 mod schedule;
+mod schedule_graph;
+mod state;
+mod system_descriptor;
 
 pub use parallel_executor::*;
+pub use run_criteria::*;
 pub use schedule::*;
+pub use schedule_graph::*;
+pub use state::*;
+pub use system_descriptor::*;
@@ -1,10 +1,11 @@
-use super::Schedule;
+use super::{RunCriteria, Schedule, ShouldRun};
 use crate::{
-    resource::Resources,
-    system::{ArchetypeAccess, System, ThreadLocalExecution, TypeAccess},
+    resource::{record_removed_components, update_removed_components, Resources},
+    system::{ArchetypeAccess, System, SystemId, ThreadLocalExecution, TypeAccess},
 };
 use bevy_hecs::{ArchetypesGeneration, World};
 use bevy_tasks::{ComputeTaskPool, CountdownEvent, TaskPool};
+use bevy_utils::HashMap;
 use fixedbitset::FixedBitSet;
 use std::ops::Range;
 
@@ -15,12 +16,20 @@ use std::ops::Range;
 /// * in a given stage, systems the read archetype X cannot run before systems registered before them that write archetype X
 /// * in a given stage, systems that mutate resource Y cannot run before systems registered before them that read/write resource Y
 /// * in a given stage, systems the read resource Y cannot run before systems registered before them that write resource Y
+/// * in a given stage, systems cannot run before the systems they are ordered after with labels
+///
+/// Stages and systems with a [RunCriteria](super::RunCriteria) are skipped without being scheduled
+/// when their criteria returns [ShouldRun::No].
+///
+/// A [deterministic](ParallelExecutor::deterministic) executor instead runs systems one at a time,
+/// in the order they are in the schedule.
 
 #[derive(Debug)]
 pub struct ParallelExecutor {
     stages: Vec<ExecutorStage>,
     last_schedule_generation: usize,
     clear_trackers: bool,
+    deterministic: bool,
 }
 
 impl Default for ParallelExecutor {
@@ -29,6 +38,7 @@ impl Default for ParallelExecutor {
             stages: Default::default(),
             last_schedule_generation: usize::MAX, // MAX forces prepare to run the first time
             clear_trackers: true,
+            deterministic: false,
         }
     }
 }
@@ -41,6 +51,20 @@ impl ParallelExecutor {
         }
     }
 
+    /// Creates an executor that runs the systems of each stage one at a time on the calling thread,
+    /// in schedule order. Every run of a schedule then makes the same changes in the same order,
+    /// including the order in which entities are spawned and commands are applied.
+    pub fn deterministic() -> Self {
+        Self {
+            deterministic: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
     pub fn initialize(&mut self, resources: &mut Resources) {
         if resources.get::<ComputeTaskPool>().is_none() {
             resources.insert(ComputeTaskPool(TaskPool::default()));
@@ -48,25 +72,67 @@ impl ParallelExecutor {
     }
 
     pub fn run(&mut self, schedule: &mut Schedule, world: &mut World, resources: &mut Resources) {
+        if let Err(err) = schedule.order_systems() {
+            panic!("{}", err);
+        }
+
         let schedule_generation = schedule.generation();
         let schedule_changed = schedule.generation() != self.last_schedule_generation;
         if schedule_changed {
+            let deterministic = self.deterministic;
             self.stages.clear();
             self.stages
-                .resize_with(schedule.stage_order.len(), ExecutorStage::default);
+                .resize_with(schedule.stage_order.len(), || ExecutorStage {
+                    deterministic,
+                    ..Default::default()
+                });
         }
         for (stage_name, executor_stage) in schedule.stage_order.iter().zip(self.stages.iter_mut())
         {
             log::trace!("run stage {:?}", stage_name);
             if let Some(stage_systems) = schedule.stages.get_mut(stage_name) {
-                executor_stage.run(world, resources, stage_systems, schedule_changed);
+                let mut stage_run_criteria = schedule.stage_run_criteria.get_mut(stage_name);
+                let order_dependencies = schedule
+                    .stage_order_dependencies
+                    .get(stage_name)
+                    .map_or(&[][..], |dependencies| &dependencies[..]);
+                let mut schedule_changed = schedule_changed;
+                loop {
+                    let should_run = stage_run_criteria
+                        .as_mut()
+                        .map_or(ShouldRun::Yes, |criteria| {
+                            criteria.should_run(world, resources)
+                        });
+                    if should_run == ShouldRun::No {
+                        log::trace!("skip stage {:?}", stage_name);
+                        break;
+                    }
+
+                    executor_stage.run(
+                        world,
+                        resources,
+                        stage_systems,
+                        &mut schedule.system_run_criteria,
+                        order_dependencies,
+                        schedule_changed,
+                    );
+                    schedule_changed = false;
+
+                    if should_run == ShouldRun::Yes {
+                        break;
+                    }
+                }
+                record_removed_components(world, resources);
             }
         }
 
         if self.clear_trackers {
+            update_removed_components(world, resources);
             world.clear_trackers();
             resources.clear_trackers();
         }
+        world.check_change_ticks();
+        resources.check_change_ticks();
 
         self.last_schedule_generation = schedule_generation;
     }
@@ -86,9 +152,13 @@ pub struct ExecutorStage {
     system_dependents: Vec<Vec<usize>>,
     /// stores the indices of thread local systems in this stage, which are used during stage.prepare()
     thread_local_system_indices: Vec<usize>,
+    /// the result of each system's run criteria for the current pass over the stage
+    system_should_run: Vec<ShouldRun>,
     /// When archetypes change a counter is bumped - we cache the state of that counter when it was
     /// last read here so that we can detect when archetypes are changed
     last_archetypes_generation: ArchetypesGeneration,
+    /// whether systems are run one at a time in order, instead of in parallel
+    deterministic: bool,
 }
 
 impl Default for ExecutorStage {
@@ -100,7 +170,9 @@ impl Default for ExecutorStage {
             ready_events_of_dependents: Default::default(),
             system_dependencies: Default::default(),
             thread_local_system_indices: Default::default(),
+            system_should_run: Default::default(),
             last_archetypes_generation: ArchetypesGeneration(u64::MAX), // MAX forces prepare to run the first time
+            deterministic: false,
         }
     }
 }
@@ -115,6 +187,7 @@ impl ExecutorStage {
         &mut self,
         world: &World,
         systems: &mut [Box<dyn System>],
+        order_dependencies: &[Vec<usize>],
         schedule_changed: bool,
         next_thread_local_index: usize,
     ) -> Range<usize> {
@@ -196,6 +269,21 @@ impl ExecutorStage {
                             }
                         }
 
+                        // systems this system is explicitly ordered after. systems before this
+                        // range have already finished running
+                        if let Some(order_dependencies) = order_dependencies.get(system_index) {
+                            for &earlier_system_index in order_dependencies.iter() {
+                                if earlier_system_index >= prepare_system_index_range.start
+                                    && !self.system_dependencies[system_index]
+                                        .contains(earlier_system_index)
+                                {
+                                    self.system_dependents[earlier_system_index].push(system_index);
+                                    self.system_dependencies[system_index]
+                                        .insert(earlier_system_index);
+                                }
+                            }
+                        }
+
                         current_archetype_access.union(archetype_access);
                         current_resource_access.union(resource_access);
 
@@ -279,6 +367,58 @@ impl ExecutorStage {
         }
     }
 
+    /// Clears the state of the stage and fills it with defaults for `systems`. This is mostly zeroing
+    /// out a bunch of arrays parallel to the systems array. They will get repopulated by
+    /// prepare_to_next_thread_local() calls
+    fn reset(&mut self, systems: &[Box<dyn System>]) {
+        self.system_dependencies.clear();
+        self.system_dependencies
+            .resize_with(systems.len(), || FixedBitSet::with_capacity(systems.len()));
+
+        self.system_dependency_count.clear();
+        self.system_dependency_count.resize(systems.len(), 0);
+
+        self.thread_local_system_indices = Vec::new();
+
+        self.system_dependents.clear();
+        self.system_dependents.resize(systems.len(), Vec::new());
+
+        self.ready_events.resize(systems.len(), None);
+        self.ready_events_of_dependents
+            .resize(systems.len(), Vec::new());
+
+        for (system_index, system) in systems.iter().enumerate() {
+            if system.thread_local_execution() == ThreadLocalExecution::Immediate {
+                self.thread_local_system_indices.push(system_index);
+            }
+        }
+    }
+
+    /// Prepares every batch of `systems` the way a run of the stage does, without running them, so
+    /// that their [dependencies](ExecutorStage::system_dependencies) can be inspected
+    pub fn prepare_all(
+        &mut self,
+        world: &World,
+        systems: &mut [Box<dyn System>],
+        order_dependencies: &[Vec<usize>],
+    ) {
+        self.reset(systems);
+        for next_thread_local_index in 0..=self.thread_local_system_indices.len() {
+            self.prepare_to_next_thread_local(
+                world,
+                systems,
+                order_dependencies,
+                true,
+                next_thread_local_index,
+            );
+        }
+    }
+
+    /// The systems each system of the stage waits for, as of the last time they were prepared
+    pub fn system_dependencies(&self) -> &[FixedBitSet] {
+        &self.system_dependencies
+    }
+
     fn reset_system_ready_events(&mut self, prepare_system_index_range: Range<usize>) {
         for system_index in prepare_system_index_range {
             let dependency_count = self.system_dependency_count[system_index];
@@ -291,15 +431,75 @@ impl ExecutorStage {
         }
     }
 
-    /// Runs the non-thread-local systems in the given prepared_system_range range
+    /// Evaluates the run criteria of the systems in `system_index_range` that haven't been skipped
+    /// in the current pass over the stage. No systems may be running.
+    fn evaluate_run_criteria(
+        &mut self,
+        world: &World,
+        resources: &Resources,
+        systems: &[Box<dyn System>],
+        system_run_criteria: &mut HashMap<SystemId, RunCriteria>,
+        system_index_range: Range<usize>,
+    ) {
+        for system_index in system_index_range {
+            let should_run = &mut self.system_should_run[system_index];
+            if *should_run == ShouldRun::No {
+                continue;
+            }
+            if let Some(criteria) = system_run_criteria.get_mut(&systems[system_index].id()) {
+                *should_run = criteria.should_run(world, resources);
+            }
+        }
+    }
+
+    /// Runs the non-thread-local systems in the given prepared_system_range range. Their run
+    /// criteria are evaluated right before the systems are started, so they see the changes made by
+    /// the systems that ran before them in the stage.
     pub fn run_systems(
-        &self,
+        &mut self,
         world: &World,
         resources: &Resources,
         systems: &mut [Box<dyn System>],
+        system_run_criteria: &mut HashMap<SystemId, RunCriteria>,
         prepared_system_range: Range<usize>,
         compute_pool: &TaskPool,
     ) {
+        if self.deterministic {
+            // every dependency is on an earlier system, so running in order satisfies them
+            log::trace!("running systems {:?} in order", prepared_system_range);
+            for system_index in prepared_system_range {
+                self.evaluate_run_criteria(
+                    world,
+                    resources,
+                    systems,
+                    system_run_criteria,
+                    system_index..system_index + 1,
+                );
+                let system = &mut systems[system_index];
+                if self.system_should_run[system_index] == ShouldRun::No {
+                    log::trace!("skip {}", system.name());
+                    continue;
+                }
+                log::trace!("run {}", system.name());
+                #[cfg(feature = "profiler")]
+                crate::profiler_start(resources, system.name().clone());
+                system.run(world, resources);
+                #[cfg(feature = "profiler")]
+                crate::profiler_stop(resources, system.name().clone());
+            }
+            return;
+        }
+
+        // the systems of a batch run in parallel, so their criteria are evaluated before any of them
+        // starts
+        self.evaluate_run_criteria(
+            world,
+            resources,
+            systems,
+            system_run_criteria,
+            prepared_system_range.clone(),
+        );
+
         // Generate tasks for systems in the given range and block until they are complete
         log::trace!("running systems {:?}", prepared_system_range);
         compute_pool.scope(|scope| {
@@ -360,6 +560,17 @@ impl ExecutorStage {
                     }
                 }
 
+                // Systems skipped by their run criteria are never scheduled. Their dependents are
+                // released right away
+                if self.system_should_run[system_index] == ShouldRun::No {
+                    log::trace!("skip {}", system.name());
+                    for trigger_event in trigger_events {
+                        trigger_event.decrement();
+                    }
+                    system_index += 1;
+                    continue;
+                }
+
                 // Spawn the task
                 scope.spawn(async move {
                     // Wait until our dependencies are done
@@ -388,41 +599,65 @@ impl ExecutorStage {
         });
     }
 
+    /// Runs the stage's systems. The run criteria of each system are evaluated when the batch of
+    /// systems it belongs to starts (or right before it runs, for thread local systems and in
+    /// deterministic mode), so they see the changes made earlier in the stage. Systems whose criteria
+    /// returns [ShouldRun::YesAndLoop] are evaluated again (and possibly run again) after the stage
+    /// finishes.
     pub fn run(
         &mut self,
         world: &mut World,
         resources: &mut Resources,
         systems: &mut [Box<dyn System>],
+        system_run_criteria: &mut HashMap<SystemId, RunCriteria>,
+        order_dependencies: &[Vec<usize>],
+        schedule_changed: bool,
+    ) {
+        self.system_should_run.clear();
+        self.system_should_run.resize(systems.len(), ShouldRun::Yes);
+        let mut schedule_changed = schedule_changed;
+        loop {
+            self.run_once(
+                world,
+                resources,
+                systems,
+                system_run_criteria,
+                order_dependencies,
+                schedule_changed,
+            );
+            schedule_changed = false;
+
+            // only systems whose criteria asked to loop are evaluated again
+            for should_run in self.system_should_run.iter_mut() {
+                if *should_run == ShouldRun::Yes {
+                    *should_run = ShouldRun::No;
+                }
+            }
+            if self
+                .system_should_run
+                .iter()
+                .all(|should_run| *should_run == ShouldRun::No)
+            {
+                break;
+            }
+        }
+    }
+
+    fn run_once(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+        systems: &mut [Box<dyn System>],
+        system_run_criteria: &mut HashMap<SystemId, RunCriteria>,
+        order_dependencies: &[Vec<usize>],
         schedule_changed: bool,
     ) {
         let start_archetypes_generation = world.archetypes_generation();
         let compute_pool = resources.get_cloned::<ComputeTaskPool>().unwrap();
 
         // if the schedule has changed, clear executor state / fill it with new defaults
-        // This is mostly zeroing out a bunch of arrays parallel to the systems array. They will get
-        // repopulated by prepare_to_next_thread_local() calls
         if schedule_changed {
-            self.system_dependencies.clear();
-            self.system_dependencies
-                .resize_with(systems.len(), || FixedBitSet::with_capacity(systems.len()));
-
-            self.system_dependency_count.clear();
-            self.system_dependency_count.resize(systems.len(), 0);
-
-            self.thread_local_system_indices = Vec::new();
-
-            self.system_dependents.clear();
-            self.system_dependents.resize(systems.len(), Vec::new());
-
-            self.ready_events.resize(systems.len(), None);
-            self.ready_events_of_dependents
-                .resize(systems.len(), Vec::new());
-
-            for (system_index, system) in systems.iter().enumerate() {
-                if system.thread_local_execution() == ThreadLocalExecution::Immediate {
-                    self.thread_local_system_indices.push(system_index);
-                }
-            }
+            self.reset(systems);
         }
 
         // index of next thread local system in thread_local_system_indices. (always incremented by one
@@ -435,6 +670,7 @@ impl ExecutorStage {
             let prepared_system_range = self.prepare_to_next_thread_local(
                 world,
                 systems,
+                order_dependencies,
                 schedule_changed,
                 next_thread_local_index,
             );
@@ -444,6 +680,7 @@ impl ExecutorStage {
                 world,
                 resources,
                 systems,
+                system_run_criteria,
                 prepared_system_range,
                 &*compute_pool,
             );
@@ -460,10 +697,19 @@ impl ExecutorStage {
                 self.thread_local_system_indices[next_thread_local_index];
             {
                 // if a thread local system is ready to run, run it exclusively on the main thread
+                self.evaluate_run_criteria(
+                    world,
+                    resources,
+                    systems,
+                    system_run_criteria,
+                    thread_local_system_index..thread_local_system_index + 1,
+                );
                 let system = systems[thread_local_system_index].as_mut();
-                log::trace!("running thread local system {}", system.name());
-                system.run(world, resources);
-                system.run_thread_local(world, resources);
+                if self.system_should_run[thread_local_system_index] != ShouldRun::No {
+                    log::trace!("running thread local system {}", system.name());
+                    system.run(world, resources);
+                    system.run_thread_local(world, resources);
+                }
             }
 
             // Now that the previous thread local system has run, time to advance to the next one
@@ -474,6 +720,7 @@ impl ExecutorStage {
             let run_ready_system_index_range = self.prepare_to_next_thread_local(
                 world,
                 systems,
+                order_dependencies,
                 schedule_changed,
                 next_thread_local_index,
             );
@@ -483,13 +730,17 @@ impl ExecutorStage {
                 world,
                 resources,
                 systems,
+                system_run_criteria,
                 run_ready_system_index_range,
                 &*compute_pool,
             );
         }
 
         // "flush"
-        for system in systems.iter_mut() {
+        for (system, should_run) in systems.iter_mut().zip(self.system_should_run.iter()) {
+            if *should_run == ShouldRun::No {
+                continue;
+            }
             match system.thread_local_execution() {
                 ThreadLocalExecution::NextFlush => system.run_thread_local(world, resources),
                 ThreadLocalExecution::Immediate => { /* already ran */ }
@@ -508,15 +759,16 @@ impl ExecutorStage {
 mod tests {
     use super::ParallelExecutor;
     use crate::{
-        resource::{Res, ResMut, Resources},
-        schedule::Schedule,
+        resource::{Local, Res, ResMut, Resources, SeededRng},
+        schedule::{IntoSystemDescriptor, Schedule, ScheduleError},
         system::{IntoQuerySystem, IntoThreadLocalSystem, Query},
         Commands,
     };
-    use bevy_hecs::{Entity, World};
+    use bevy_hecs::{Entity, Mut, World};
     use bevy_tasks::{ComputeTaskPool, TaskPool};
     use fixedbitset::FixedBitSet;
     use parking_lot::Mutex;
+    use rand::RngCore;
     use std::{collections::HashSet, sync::Arc};
 
     #[derive(Default)]
@@ -825,4 +1077,174 @@ mod tests {
             run_executor_and_validate(&mut executor, &mut schedule, &mut world, &mut resources);
         }
     }
+
+    #[test]
+    fn system_ordering() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+
+        #[derive(Default)]
+        struct Log(Mutex<Vec<&'static str>>);
+        resources.insert(Log::default());
+
+        // none of these systems conflict, so only the labels order them
+        fn physics(log: Res<Log>) {
+            log.0.lock().push("physics");
+        }
+        fn collisions(log: Res<Log>) {
+            log.0.lock().push("collisions");
+        }
+        fn render(log: Res<Log>) {
+            log.0.lock().push("render");
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", render.system().label("render"));
+        schedule.add_system_to_stage(
+            "update",
+            collisions
+                .system()
+                .after("physics")
+                .before("render")
+                .label("collisions"),
+        );
+        schedule.add_system_to_stage("update", physics.system().label("physics"));
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        for _ in 0..100 {
+            executor.run(&mut schedule, &mut world, &mut resources);
+            let log = resources.get::<Log>().unwrap();
+            let mut log = log.0.lock();
+            assert_eq!(*log, vec!["physics", "collisions", "render"]);
+            log.clear();
+        }
+    }
+
+    #[test]
+    fn system_ordering_keeps_insertion_order_after_reordering() {
+        fn system_a() {}
+        fn system_b() {}
+        fn system_c() {}
+        fn system_d() {}
+
+        let (a, b, c, d) = (
+            system_a.system(),
+            system_b.system(),
+            system_c.system(),
+            system_d.system(),
+        );
+        let ids = [a.id(), b.id(), c.id(), d.id()];
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", a.after("c"));
+        schedule.add_system_to_stage("update", b.label("b"));
+        schedule.add_system_to_stage("update", c.label("c"));
+        schedule.order_systems().unwrap();
+        let order = |schedule: &Schedule| {
+            schedule.stages["update"]
+                .iter()
+                .map(|system| ids.iter().position(|&id| id == system.id()).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(&schedule), vec![1, 2, 0]);
+
+        // a and b become ready together, and a was added first
+        schedule.add_system_to_stage("update", d.label("c").before("b"));
+        schedule.order_systems().unwrap();
+        assert_eq!(order(&schedule), vec![2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn system_ordering_errors() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+
+        fn system_a() {}
+        fn system_b() {}
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system_a.system().after("missing"));
+        match schedule.try_initialize(&mut world, &mut resources) {
+            Err(ScheduleError::UnknownLabel { stage, label, .. }) => {
+                assert_eq!(stage, "update");
+                assert_eq!(label, "missing");
+            }
+            result => panic!("expected an unknown label, got {:?}", result),
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system_a.system().label("a").after("b"));
+        schedule.add_system_to_stage("update", system_b.system().label("b").after("a"));
+        match schedule.try_initialize(&mut world, &mut resources) {
+            Err(ScheduleError::DependencyCycle { stage, systems }) => {
+                assert_eq!(stage, "update");
+                assert_eq!(systems.len(), 2);
+            }
+            result => panic!("expected a dependency cycle, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn deterministic_executor_replays_identically() {
+        fn spawn_random(mut commands: Commands, mut rng: ResMut<SeededRng>) {
+            for _ in 0..4 {
+                commands.spawn((rng.next_u32(),));
+            }
+        }
+
+        fn spawn_counter(mut commands: Commands, mut count: Local<u64>) {
+            *count += 1;
+            commands.spawn((*count,));
+        }
+
+        fn scramble(mut rng: ResMut<SeededRng>, mut query: Query<Mut<u32>>) {
+            for mut value in &mut query.iter() {
+                *value ^= rng.next_u32();
+            }
+        }
+
+        type Snapshot = (Vec<(Entity, u32)>, Vec<(Entity, u64)>);
+
+        fn run(seed: u64) -> Snapshot {
+            let mut world = World::new();
+            let mut resources = Resources::default();
+            resources.insert(ComputeTaskPool(TaskPool::default()));
+            resources.insert(SeededRng::new(seed));
+
+            let mut schedule = Schedule::default();
+            schedule.add_stage("update");
+            schedule.add_system_to_stage("update", spawn_random.system());
+            schedule.add_system_to_stage("update", spawn_counter.system());
+            schedule.add_system_to_stage("update", scramble.system());
+
+            let mut executor = ParallelExecutor::deterministic();
+            schedule.initialize(&mut world, &mut resources);
+            for _ in 0..10 {
+                executor.run(&mut schedule, &mut world, &mut resources);
+            }
+
+            let values = world
+                .query::<(Entity, &u32)>()
+                .iter()
+                .map(|(entity, value)| (entity, *value))
+                .collect();
+            let counts = world
+                .query::<(Entity, &u64)>()
+                .iter()
+                .map(|(entity, value)| (entity, *value))
+                .collect();
+            (values, counts)
+        }
+
+        let (values, counts) = run(42);
+        assert_eq!(values.len(), 40);
+        assert_eq!(counts.len(), 10);
+        assert_eq!(run(42), (values.clone(), counts));
+        assert_ne!(run(7).0, values);
+    }
 }
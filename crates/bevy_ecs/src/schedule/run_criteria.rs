@@ -0,0 +1,281 @@
+use crate::{
+    resource::{FetchResource, ResourceQuery, Resources, UnsafeClone},
+    system::SystemId,
+};
+use bevy_hecs::{check_tick, World};
+use std::borrow::Cow;
+
+/// The result of evaluating a [RunCriteria]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ShouldRun {
+    /// Skip the system or stage
+    No,
+    /// Run the system or stage once
+    Yes,
+    /// Run the system or stage, then evaluate the criteria again
+    YesAndLoop,
+}
+
+impl From<bool> for ShouldRun {
+    fn from(should_run: bool) -> Self {
+        if should_run {
+            ShouldRun::Yes
+        } else {
+            ShouldRun::No
+        }
+    }
+}
+
+type RunCriteriaFn = dyn FnMut(&World, &Resources) -> ShouldRun + Send + Sync;
+
+/// A predicate that decides whether a system or a stage should run. Run criteria are evaluated on the
+/// main thread before the system or stage they guard is scheduled.
+pub struct RunCriteria {
+    id: SystemId,
+    name: Cow<'static, str>,
+    func: Box<RunCriteriaFn>,
+    init_func: Box<dyn FnMut(&mut Resources) + Send + Sync>,
+}
+
+impl RunCriteria {
+    /// Creates a run criteria from a function that has direct access to the [World] and [Resources]
+    pub fn new<F>(name: impl Into<Cow<'static, str>>, func: F) -> Self
+    where
+        F: FnMut(&World, &Resources) -> ShouldRun + Send + Sync + 'static,
+    {
+        Self {
+            id: SystemId::new(),
+            name: name.into(),
+            func: Box::new(func),
+            init_func: Box::new(|_| {}),
+        }
+    }
+
+    pub fn id(&self) -> SystemId {
+        self.id
+    }
+
+    pub fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+
+    pub fn should_run(&mut self, world: &World, resources: &Resources) -> ShouldRun {
+        (self.func)(world, resources)
+    }
+
+    pub fn initialize(&mut self, resources: &mut Resources) {
+        (self.init_func)(resources);
+    }
+}
+
+impl std::fmt::Debug for RunCriteria {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RunCriteria")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Converts `Self` into a [RunCriteria]. Implemented for functions that take resource queries
+/// (`Res`, `ResMut`, `ChangedRes`, `Local`, ...) and return a [ShouldRun].
+pub trait IntoRunCriteria<R> {
+    fn run_criteria(self) -> RunCriteria;
+}
+
+macro_rules! impl_into_run_criteria {
+    ($($resource: ident),*) => {
+        impl<Func, $($resource,)*> IntoRunCriteria<($($resource,)*)> for Func
+        where
+            Func:
+                FnMut($($resource,)*) -> ShouldRun +
+                FnMut($(<<$resource as ResourceQuery>::Fetch as FetchResource>::Item,)*) -> ShouldRun +
+                Send + Sync + 'static,
+            $($resource: ResourceQuery,)*
+        {
+            #[allow(non_snake_case)]
+            #[allow(unused_variables)]
+            #[allow(unused_unsafe)]
+            fn run_criteria(mut self) -> RunCriteria {
+                let id = SystemId::new();
+                let mut last_change_tick = 0;
+                RunCriteria {
+                    id,
+                    name: core::any::type_name::<Self>().into(),
+                    func: Box::new(move |_world, resources| {
+                        let change_tick = resources.increment_change_tick();
+                        check_tick(&mut last_change_tick, change_tick);
+                        let should_run = if let Some(($($resource,)*)) =
+                            resources.query_system::<($($resource,)*)>(id, last_change_tick, change_tick)
+                        {
+                            // SAFE: run criteria are evaluated on the main thread while no systems are running
+                            unsafe { self($($resource.unsafe_clone(),)*) }
+                        } else {
+                            ShouldRun::No
+                        };
+                        last_change_tick = change_tick;
+                        should_run
+                    }),
+                    init_func: Box::new(move |resources| {
+                        <($($resource,)*)>::initialize(resources, Some(id));
+                    }),
+                }
+            }
+        }
+    };
+}
+
+impl_into_run_criteria!();
+impl_into_run_criteria!(Ra);
+impl_into_run_criteria!(Ra, Rb);
+impl_into_run_criteria!(Ra, Rb, Rc);
+impl_into_run_criteria!(Ra, Rb, Rc, Rd);
+impl_into_run_criteria!(Ra, Rb, Rc, Rd, Re);
+impl_into_run_criteria!(Ra, Rb, Rc, Rd, Re, Rf);
+impl_into_run_criteria!(Ra, Rb, Rc, Rd, Re, Rf, Rg);
+impl_into_run_criteria!(Ra, Rb, Rc, Rd, Re, Rf, Rg, Rh);
+
+#[cfg(test)]
+mod tests {
+    use super::{IntoRunCriteria, ShouldRun};
+    use crate::{
+        resource::{Local, Res, ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        system::{IntoQuerySystem, IntoThreadLocalSystem},
+    };
+    use bevy_hecs::World;
+    use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+    struct Paused(bool);
+
+    #[derive(Default)]
+    struct Counter(usize);
+
+    fn not_paused(paused: Res<Paused>) -> ShouldRun {
+        (!paused.0).into()
+    }
+
+    fn count(mut counter: ResMut<Counter>) {
+        counter.0 += 1;
+    }
+
+    fn count_thread_local(_world: &mut World, resources: &mut Resources) {
+        resources.get_mut::<Counter>().unwrap().0 += 10;
+    }
+
+    #[test]
+    fn system_run_criteria() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        resources.insert(Paused(false));
+        resources.insert(Counter::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage_with_run_criteria(
+            "update",
+            count.system(),
+            not_paused.run_criteria(),
+        );
+        schedule.add_system_to_stage_with_run_criteria(
+            "update",
+            count_thread_local.thread_local_system(),
+            not_paused.run_criteria(),
+        );
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(resources.get::<Counter>().unwrap().0, 11);
+
+        resources.get_mut::<Paused>().unwrap().0 = true;
+        executor.run(&mut schedule, &mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Counter>().unwrap().0, 11);
+
+        resources.get_mut::<Paused>().unwrap().0 = false;
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Counter>().unwrap().0, 22);
+    }
+
+    #[test]
+    fn run_criteria_see_earlier_systems_of_the_stage() {
+        fn pause(mut paused: ResMut<Paused>) {
+            paused.0 = true;
+        }
+
+        fn pause_thread_local(_world: &mut World, resources: &mut Resources) {
+            resources.get_mut::<Paused>().unwrap().0 = true;
+        }
+
+        for &deterministic in &[false, true] {
+            let mut world = World::new();
+            let mut resources = Resources::default();
+            resources.insert(ComputeTaskPool(TaskPool::default()));
+            resources.insert(Paused(false));
+            resources.insert(Counter::default());
+
+            let mut schedule = Schedule::default();
+            schedule.add_stage("update");
+            if deterministic {
+                schedule.add_system_to_stage("update", pause.system());
+            } else {
+                schedule.add_system_to_stage("update", pause_thread_local.thread_local_system());
+            }
+            schedule.add_system_to_stage_with_run_criteria(
+                "update",
+                count.system(),
+                not_paused.run_criteria(),
+            );
+            schedule.add_system_to_stage_with_run_criteria(
+                "update",
+                count_thread_local.thread_local_system(),
+                not_paused.run_criteria(),
+            );
+
+            let mut executor = if deterministic {
+                ParallelExecutor::deterministic()
+            } else {
+                ParallelExecutor::default()
+            };
+            schedule.initialize(&mut world, &mut resources);
+            executor.run(&mut schedule, &mut world, &mut resources);
+            assert_eq!(resources.get::<Counter>().unwrap().0, 0);
+        }
+    }
+
+    #[test]
+    fn looping_run_criteria() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        resources.insert(Counter::default());
+
+        fn three_times(mut runs: Local<usize>) -> ShouldRun {
+            *runs += 1;
+            match *runs % 4 {
+                0 => ShouldRun::No,
+                _ => ShouldRun::YesAndLoop,
+            }
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("stage");
+        schedule.add_stage("system");
+        schedule.set_stage_run_criteria("stage", three_times.run_criteria());
+        schedule.add_system_to_stage("stage", count.system());
+        schedule.add_system_to_stage_with_run_criteria(
+            "system",
+            count.system(),
+            three_times.run_criteria(),
+        );
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(resources.get::<Counter>().unwrap().0, 6);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Counter>().unwrap().0, 12);
+    }
+}
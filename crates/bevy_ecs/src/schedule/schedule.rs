@@ -1,10 +1,31 @@
+use super::{
+    ExecutorStage, IntoSystemDescriptor, RunCriteria, ScheduleGraph, ShouldRun, StageGraph,
+    SystemDescriptor, SystemOrdering,
+};
 use crate::{
-    resource::Resources,
+    resource::{record_removed_components, update_removed_components, Resources},
     system::{System, SystemId, ThreadLocalExecution},
 };
 use bevy_hecs::World;
 use bevy_utils::{HashMap, HashSet};
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, cmp::Reverse, collections::BinaryHeap, fmt};
+use thiserror::Error;
+
+/// An error in the way systems were configured in a [Schedule]
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum ScheduleError {
+    #[error("System {system} in stage {stage} is ordered relative to the unknown label {label}.")]
+    UnknownLabel {
+        stage: Cow<'static, str>,
+        system: Cow<'static, str>,
+        label: Cow<'static, str>,
+    },
+    #[error("Systems in stage {stage} have cyclic ordering constraints: {systems:?}")]
+    DependencyCycle {
+        stage: Cow<'static, str>,
+        systems: Vec<Cow<'static, str>>,
+    },
+}
 
 /// An ordered collection of stages, which each contain an ordered list of [System]s.
 /// Schedules are essentially the "execution plan" for an App's systems.
@@ -14,8 +35,17 @@ pub struct Schedule {
     pub(crate) stages: HashMap<Cow<'static, str>, Vec<Box<dyn System>>>,
     pub(crate) stage_order: Vec<Cow<'static, str>>,
     pub(crate) system_ids: HashSet<SystemId>,
+    pub(crate) stage_run_criteria: HashMap<Cow<'static, str>, RunCriteria>,
+    pub(crate) system_run_criteria: HashMap<SystemId, RunCriteria>,
+    pub(crate) system_orderings: HashMap<SystemId, SystemOrdering>,
+    /// for each stage, the ids of its systems in the order they were added
+    pub(crate) stage_insertion_orders: HashMap<Cow<'static, str>, Vec<SystemId>>,
+    /// for each stage, the indices of the systems each system must run after because of its ordering
+    /// constraints
+    pub(crate) stage_order_dependencies: HashMap<Cow<'static, str>, Vec<Vec<usize>>>,
     generation: usize,
     last_initialize_generation: usize,
+    last_order_generation: usize,
 }
 
 impl fmt::Debug for Schedule {
@@ -99,22 +129,19 @@ impl Schedule {
     pub fn add_system_to_stage(
         &mut self,
         stage_name: impl Into<Cow<'static, str>>,
-        system: Box<dyn System>,
+        system: impl IntoSystemDescriptor,
     ) -> &mut Self {
+        let system = self.register_system(system.descriptor());
         let stage_name = stage_name.into();
-        let systems = self
-            .stages
+        let system_id = system.id();
+        self.stages
             .get_mut(&stage_name)
-            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name));
-        if self.system_ids.contains(&system.id()) {
-            panic!(
-                "System with id {:?} ({}) already exists",
-                system.id(),
-                system.name()
-            );
-        }
-        self.system_ids.insert(system.id());
-        systems.push(system);
+            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name))
+            .push(system);
+        self.stage_insertion_orders
+            .entry(stage_name)
+            .or_default()
+            .push(system_id);
 
         self.generation += 1;
         self
@@ -123,13 +150,26 @@ impl Schedule {
     pub fn add_system_to_stage_front(
         &mut self,
         stage_name: impl Into<Cow<'static, str>>,
-        system: Box<dyn System>,
+        system: impl IntoSystemDescriptor,
     ) -> &mut Self {
+        let system = self.register_system(system.descriptor());
         let stage_name = stage_name.into();
-        let systems = self
-            .stages
+        let system_id = system.id();
+        self.stages
             .get_mut(&stage_name)
-            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name));
+            .unwrap_or_else(|| panic!("Stage does not exist: {}", stage_name))
+            .insert(0, system);
+        self.stage_insertion_orders
+            .entry(stage_name)
+            .or_default()
+            .insert(0, system_id);
+
+        self.generation += 1;
+        self
+    }
+
+    fn register_system(&mut self, descriptor: SystemDescriptor) -> Box<dyn System> {
+        let SystemDescriptor { system, ordering } = descriptor;
         if self.system_ids.contains(&system.id()) {
             panic!(
                 "System with id {:?} ({}) already exists",
@@ -138,61 +178,359 @@ impl Schedule {
             );
         }
         self.system_ids.insert(system.id());
-        systems.insert(0, system);
+        if !ordering.is_empty() {
+            self.system_orderings.insert(system.id(), ordering);
+        }
+        system
+    }
+
+    pub fn add_system_to_stage_with_run_criteria(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        system: impl IntoSystemDescriptor,
+        run_criteria: RunCriteria,
+    ) -> &mut Self {
+        let system = system.descriptor();
+        let system_id = system.system.id();
+        self.add_system_to_stage(stage_name, system);
+        self.set_system_run_criteria(system_id, run_criteria)
+    }
+
+    /// Sets the [RunCriteria] that decides whether the stage runs. Replaces any existing criteria.
+    pub fn set_stage_run_criteria(
+        &mut self,
+        stage_name: impl Into<Cow<'static, str>>,
+        run_criteria: RunCriteria,
+    ) -> &mut Self {
+        let stage_name = stage_name.into();
+        if !self.stages.contains_key(&stage_name) {
+            panic!("Stage does not exist: {}", stage_name);
+        }
+        self.stage_run_criteria.insert(stage_name, run_criteria);
+
+        self.generation += 1;
+        self
+    }
+
+    /// Sets the [RunCriteria] that decides whether the system runs. Replaces any existing criteria.
+    pub fn set_system_run_criteria(
+        &mut self,
+        system_id: SystemId,
+        run_criteria: RunCriteria,
+    ) -> &mut Self {
+        if !self.system_ids.contains(&system_id) {
+            panic!("System with id {:?} does not exist", system_id);
+        }
+        self.system_run_criteria.insert(system_id, run_criteria);
 
         self.generation += 1;
         self
     }
 
     pub fn run(&mut self, world: &mut World, resources: &mut Resources) {
+        if let Err(err) = self.order_systems() {
+            panic!("{}", err);
+        }
+
         for stage_name in self.stage_order.iter() {
             if let Some(stage_systems) = self.stages.get_mut(stage_name) {
-                for system in stage_systems.iter_mut() {
-                    #[cfg(feature = "profiler")]
-                    crate::profiler_start(resources, system.name().clone());
-                    system.update_archetype_access(world);
-                    match system.thread_local_execution() {
-                        ThreadLocalExecution::NextFlush => system.run(world, resources),
-                        ThreadLocalExecution::Immediate => {
-                            system.run(world, resources);
-                            // NOTE: when this is made parallel a full sync is required here
-                            system.run_thread_local(world, resources);
-                        }
+                let mut stage_run_criteria = self.stage_run_criteria.get_mut(stage_name);
+                loop {
+                    let should_run = stage_run_criteria
+                        .as_mut()
+                        .map_or(ShouldRun::Yes, |criteria| {
+                            criteria.should_run(world, resources)
+                        });
+                    if should_run == ShouldRun::No {
+                        break;
                     }
-                    #[cfg(feature = "profiler")]
-                    crate::profiler_stop(resources, system.name().clone());
-                }
 
-                // "flush"
-                // NOTE: when this is made parallel a full sync is required here
-                for system in stage_systems.iter_mut() {
-                    match system.thread_local_execution() {
-                        ThreadLocalExecution::NextFlush => {
-                            system.run_thread_local(world, resources)
-                        }
-                        ThreadLocalExecution::Immediate => { /* already ran immediate */ }
+                    Self::run_stage(
+                        world,
+                        resources,
+                        stage_systems,
+                        &mut self.system_run_criteria,
+                    );
+
+                    if should_run == ShouldRun::Yes {
+                        break;
                     }
                 }
+                record_removed_components(world, resources);
             }
         }
 
+        update_removed_components(world, resources);
         world.clear_trackers();
         resources.clear_trackers();
+        world.check_change_ticks();
+        resources.check_change_ticks();
     }
 
-    // TODO: move this code to ParallelExecutor
-    pub fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+    fn run_stage(
+        world: &mut World,
+        resources: &mut Resources,
+        stage_systems: &mut [Box<dyn System>],
+        system_run_criteria: &mut HashMap<SystemId, RunCriteria>,
+    ) {
+        let mut should_run = vec![ShouldRun::Yes; stage_systems.len()];
+        loop {
+            for (system, should_run) in stage_systems.iter().zip(should_run.iter_mut()) {
+                if *should_run == ShouldRun::No {
+                    continue;
+                }
+                if let Some(criteria) = system_run_criteria.get_mut(&system.id()) {
+                    *should_run = criteria.should_run(world, resources);
+                }
+            }
+
+            if should_run
+                .iter()
+                .all(|should_run| *should_run == ShouldRun::No)
+            {
+                break;
+            }
+
+            for (system, should_run) in stage_systems.iter_mut().zip(should_run.iter()) {
+                if *should_run == ShouldRun::No {
+                    continue;
+                }
+                #[cfg(feature = "profiler")]
+                crate::profiler_start(resources, system.name().clone());
+                system.update_archetype_access(world);
+                match system.thread_local_execution() {
+                    ThreadLocalExecution::NextFlush => system.run(world, resources),
+                    ThreadLocalExecution::Immediate => {
+                        system.run(world, resources);
+                        // NOTE: when this is made parallel a full sync is required here
+                        system.run_thread_local(world, resources);
+                    }
+                }
+                #[cfg(feature = "profiler")]
+                crate::profiler_stop(resources, system.name().clone());
+            }
+
+            // "flush"
+            // NOTE: when this is made parallel a full sync is required here
+            for (system, should_run) in stage_systems.iter_mut().zip(should_run.iter()) {
+                if *should_run == ShouldRun::No {
+                    continue;
+                }
+                match system.thread_local_execution() {
+                    ThreadLocalExecution::NextFlush => system.run_thread_local(world, resources),
+                    ThreadLocalExecution::Immediate => { /* already ran immediate */ }
+                }
+            }
+
+            // only systems whose criteria asked to loop are evaluated again
+            for should_run in should_run.iter_mut() {
+                if *should_run == ShouldRun::Yes {
+                    *should_run = ShouldRun::No;
+                }
+            }
+        }
+    }
+
+    /// Sorts the systems of each stage so that their ordering constraints are satisfied. Systems
+    /// without constraints between them keep their insertion order.
+    pub fn order_systems(&mut self) -> Result<(), ScheduleError> {
+        if self.last_order_generation == self.generation {
+            return Ok(());
+        }
+
+        for (stage_name, systems) in self.stages.iter_mut() {
+            let insertion_order = self
+                .stage_insertion_orders
+                .get(stage_name)
+                .map_or(&[][..], |insertion_order| &insertion_order[..]);
+            let order_dependencies = Self::order_stage_systems(
+                stage_name.clone(),
+                systems,
+                insertion_order,
+                &self.system_orderings,
+            )?;
+            self.stage_order_dependencies
+                .insert(stage_name.clone(), order_dependencies);
+        }
+
+        self.last_order_generation = self.generation;
+        Ok(())
+    }
+
+    /// Orders the systems, updates their archetype access for `world` and returns the dependencies
+    /// the [ParallelExecutor](super::ParallelExecutor) computes between them, along with the pairs
+    /// of systems whose relative order is ambiguous. Resource access is only accurate once the
+    /// schedule is initialized.
+    pub fn graph(&mut self, world: &World) -> Result<ScheduleGraph, ScheduleError> {
+        self.order_systems()?;
+
+        let mut graph = ScheduleGraph::default();
+        for stage_name in self.stage_order.iter() {
+            let systems = self.stages.get_mut(stage_name).unwrap();
+            let order_dependencies = self
+                .stage_order_dependencies
+                .get(stage_name)
+                .map_or(&[][..], |dependencies| &dependencies[..]);
+            let mut executor_stage = ExecutorStage::default();
+            executor_stage.prepare_all(world, systems, order_dependencies);
+            graph.stages.push(StageGraph::new(
+                stage_name.clone(),
+                systems,
+                order_dependencies,
+                executor_stage.system_dependencies(),
+            ));
+        }
+        Ok(graph)
+    }
+
+    fn order_stage_systems(
+        stage_name: Cow<'static, str>,
+        systems: &mut Vec<Box<dyn System>>,
+        insertion_order: &[SystemId],
+        system_orderings: &HashMap<SystemId, SystemOrdering>,
+    ) -> Result<Vec<Vec<usize>>, ScheduleError> {
+        let orderings = systems
+            .iter()
+            .map(|system| system_orderings.get(&system.id()))
+            .collect::<Vec<_>>();
+        if orderings.iter().all(|ordering| ordering.is_none()) {
+            return Ok(vec![Vec::new(); systems.len()]);
+        }
+
+        let mut labeled_systems = HashMap::<&str, Vec<usize>>::default();
+        for (index, ordering) in orderings.iter().enumerate() {
+            for label in ordering.iter().flat_map(|ordering| ordering.labels.iter()) {
+                labeled_systems.entry(label).or_default().push(index);
+            }
+        }
+
+        // edges point from a system to the systems that must run after it
+        let mut edges = HashSet::<(usize, usize)>::default();
+        for (index, ordering) in orderings.iter().enumerate() {
+            let ordering = match ordering {
+                Some(ordering) => ordering,
+                None => continue,
+            };
+            let constraints = ordering
+                .before
+                .iter()
+                .map(|label| (label, true))
+                .chain(ordering.after.iter().map(|label| (label, false)));
+            for (label, before) in constraints {
+                let others = labeled_systems.get(label.as_ref()).ok_or_else(|| {
+                    ScheduleError::UnknownLabel {
+                        stage: stage_name.clone(),
+                        system: systems[index].name(),
+                        label: label.clone(),
+                    }
+                })?;
+                for &other in others.iter().filter(|&&other| other != index) {
+                    edges.insert(if before {
+                        (index, other)
+                    } else {
+                        (other, index)
+                    });
+                }
+            }
+        }
+
+        let mut successors = vec![Vec::new(); systems.len()];
+        let mut dependency_count = vec![0; systems.len()];
+        for &(from, to) in edges.iter() {
+            successors[from].push(to);
+            dependency_count[to] += 1;
+        }
+
+        // `systems` may have been reordered already, so ties are broken by the insertion order
+        let insertion_indices = insertion_order
+            .iter()
+            .enumerate()
+            .map(|(insertion_index, &id)| (id, insertion_index))
+            .collect::<HashMap<_, _>>();
+        let ready_entry = |index: usize| Reverse((insertion_indices[&systems[index].id()], index));
+
+        // Kahn's algorithm, always picking the earliest inserted system that is ready
+        let mut ready = (0..systems.len())
+            .filter(|&index| dependency_count[index] == 0)
+            .map(ready_entry)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::with_capacity(systems.len());
+        while let Some(Reverse((_, index))) = ready.pop() {
+            order.push(index);
+            for &successor in successors[index].iter() {
+                dependency_count[successor] -= 1;
+                if dependency_count[successor] == 0 {
+                    ready.push(ready_entry(successor));
+                }
+            }
+        }
+
+        if order.len() != systems.len() {
+            return Err(ScheduleError::DependencyCycle {
+                stage: stage_name,
+                systems: (0..systems.len())
+                    .filter(|&index| dependency_count[index] > 0)
+                    .map(|index| systems[index].name())
+                    .collect(),
+            });
+        }
+
+        let mut new_indices = vec![0; systems.len()];
+        for (new_index, &old_index) in order.iter().enumerate() {
+            new_indices[old_index] = new_index;
+        }
+
+        let mut order_dependencies = vec![Vec::new(); systems.len()];
+        for &(from, to) in edges.iter() {
+            order_dependencies[new_indices[to]].push(new_indices[from]);
+        }
+
+        let mut unordered_systems = systems.drain(..).map(Some).collect::<Vec<_>>();
+        systems.extend(
+            order
+                .iter()
+                .map(|&index| unordered_systems[index].take().unwrap()),
+        );
+
+        Ok(order_dependencies)
+    }
+
+    /// Initializes new systems and run criteria and orders systems by their constraints. Returns an
+    /// error if the ordering constraints of a stage can't be satisfied.
+    pub fn try_initialize(
+        &mut self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), ScheduleError> {
         if self.last_initialize_generation == self.generation {
-            return;
+            return Ok(());
         }
 
+        self.order_systems()?;
+
         for stage in self.stages.values_mut() {
             for system in stage.iter_mut() {
                 system.initialize(world, resources);
             }
         }
 
+        for run_criteria in self
+            .stage_run_criteria
+            .values_mut()
+            .chain(self.system_run_criteria.values_mut())
+        {
+            run_criteria.initialize(resources);
+        }
+
         self.last_initialize_generation = self.generation;
+        Ok(())
+    }
+
+    // TODO: move this code to ParallelExecutor
+    pub fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        if let Err(err) = self.try_initialize(world, resources) {
+            panic!("{}", err);
+        }
     }
 
     pub fn generation(&self) -> usize {
@@ -0,0 +1,315 @@
+use crate::system::{ArchetypeAccess, System, SystemId, ThreadLocalExecution, TypeAccess};
+use fixedbitset::FixedBitSet;
+use std::{any::TypeId, borrow::Cow, fmt::Write};
+
+/// Why a system waits for another one in a [StageGraph]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DependencyKind {
+    /// The systems are ordered with labels
+    Ordering,
+    /// The accesses of the systems conflict, so the one added to the stage later waits
+    Access,
+    /// One of the systems is a thread local system, which runs exclusively
+    ThreadLocal,
+}
+
+/// A system in a [StageGraph], with the accesses its dependencies were computed from
+#[derive(Debug, Clone)]
+pub struct SystemNode {
+    pub name: Cow<'static, str>,
+    pub id: SystemId,
+    pub thread_local_execution: ThreadLocalExecution,
+    pub resource_access: TypeAccess,
+    pub archetype_access: ArchetypeAccess,
+}
+
+/// The system at index `after` in a stage can't start until the system at index `before` finished
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SystemDependency {
+    pub before: usize,
+    pub after: usize,
+    pub kind: DependencyKind,
+}
+
+/// Two systems in a stage with conflicting access and no ordering constraint between them. They run
+/// in the order they were added to the stage, which is easy to change by accident.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SystemAmbiguity {
+    pub systems: (usize, usize),
+    /// Resources one of the systems writes and the other reads or writes
+    pub resources: Vec<TypeId>,
+    /// Components stored in sparse sets that one of the systems writes and the other reads or writes
+    pub components: Vec<TypeId>,
+    /// Archetypes one of the systems writes and the other reads or writes
+    pub archetypes: Vec<usize>,
+}
+
+/// The systems of a stage and the dependencies the [ParallelExecutor](super::ParallelExecutor)
+/// computes between them
+#[derive(Debug, Clone)]
+pub struct StageGraph {
+    pub name: Cow<'static, str>,
+    /// The systems in the order they run in when there is no parallelism
+    pub systems: Vec<SystemNode>,
+    pub dependencies: Vec<SystemDependency>,
+    pub ambiguities: Vec<SystemAmbiguity>,
+}
+
+impl StageGraph {
+    pub(crate) fn new(
+        name: Cow<'static, str>,
+        systems: &[Box<dyn System>],
+        order_dependencies: &[Vec<usize>],
+        system_dependencies: &[FixedBitSet],
+    ) -> Self {
+        let order_dependencies = |index: usize| {
+            order_dependencies
+                .get(index)
+                .map_or(&[][..], |dependencies| &dependencies[..])
+        };
+        let is_thread_local = |index: usize| {
+            systems[index].thread_local_execution() == ThreadLocalExecution::Immediate
+        };
+
+        let mut dependencies = Vec::new();
+        for (index, system_dependencies) in system_dependencies.iter().enumerate() {
+            for earlier_index in system_dependencies.ones() {
+                let kind = if is_thread_local(index) || is_thread_local(earlier_index) {
+                    DependencyKind::ThreadLocal
+                } else if order_dependencies(index).contains(&earlier_index) {
+                    DependencyKind::Ordering
+                } else {
+                    DependencyKind::Access
+                };
+                dependencies.push(SystemDependency {
+                    before: earlier_index,
+                    after: index,
+                    kind,
+                });
+            }
+        }
+
+        // the systems each system is transitively ordered after
+        let mut ordered_after = vec![FixedBitSet::with_capacity(systems.len()); systems.len()];
+        for index in 0..systems.len() {
+            for &earlier_index in order_dependencies(index) {
+                let earlier_ordered_after = ordered_after[earlier_index].clone();
+                ordered_after[index].union_with(&earlier_ordered_after);
+                ordered_after[index].insert(earlier_index);
+            }
+        }
+
+        // thread local systems always run exclusively, so they can't be ambiguous
+        let mut ambiguities = Vec::new();
+        for (index, system) in systems.iter().enumerate() {
+            if system.thread_local_execution() == ThreadLocalExecution::Immediate {
+                continue;
+            }
+            for (earlier_index, earlier_system) in systems.iter().enumerate().take(index) {
+                if earlier_system.thread_local_execution() == ThreadLocalExecution::Immediate
+                    || ordered_after[index].contains(earlier_index)
+                    || is_compatible(earlier_system.as_ref(), system.as_ref())
+                {
+                    continue;
+                }
+                let (earlier_archetypes, archetypes) =
+                    (earlier_system.archetype_access(), system.archetype_access());
+                let mut conflicting_archetypes = earlier_archetypes
+                    .mutable
+                    .intersection(&archetypes.accessed)
+                    .chain(
+                        earlier_archetypes
+                            .accessed
+                            .intersection(&archetypes.mutable),
+                    )
+                    .collect::<Vec<_>>();
+                conflicting_archetypes.sort_unstable();
+                conflicting_archetypes.dedup();
+                ambiguities.push(SystemAmbiguity {
+                    systems: (earlier_index, index),
+                    resources: conflicts(
+                        earlier_system.resource_access(),
+                        system.resource_access(),
+                    ),
+                    components: conflicts(&earlier_archetypes.sparse_sets, &archetypes.sparse_sets),
+                    archetypes: conflicting_archetypes,
+                });
+            }
+        }
+
+        Self {
+            name,
+            systems: systems
+                .iter()
+                .map(|system| SystemNode {
+                    name: system.name(),
+                    id: system.id(),
+                    thread_local_execution: system.thread_local_execution(),
+                    resource_access: system.resource_access().clone(),
+                    archetype_access: system.archetype_access().clone(),
+                })
+                .collect(),
+            dependencies,
+            ambiguities,
+        }
+    }
+}
+
+fn is_compatible(a: &dyn System, b: &dyn System) -> bool {
+    a.archetype_access().is_compatible(b.archetype_access())
+        && a.resource_access().is_compatible(b.resource_access())
+}
+
+/// The types `a` writes and `b` reads or writes, or the other way around
+fn conflicts(a: &TypeAccess, b: &TypeAccess) -> Vec<TypeId> {
+    let mut conflicts = a
+        .mutable
+        .iter()
+        .filter(|ty| b.mutable.contains(ty) || b.immutable.contains(ty))
+        .chain(a.immutable.iter().filter(|ty| b.mutable.contains(ty)))
+        .copied()
+        .collect::<Vec<_>>();
+    conflicts.sort_unstable();
+    conflicts.dedup();
+    conflicts
+}
+
+/// The dependencies between the systems of each stage of a [Schedule](super::Schedule), as
+/// returned by [Schedule::graph](super::Schedule::graph)
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleGraph {
+    pub stages: Vec<StageGraph>,
+}
+
+impl ScheduleGraph {
+    /// All pairs of systems with conflicting access and no ordering constraint between them, by
+    /// stage name
+    pub fn ambiguities(&self) -> impl Iterator<Item = (&str, &SystemAmbiguity)> {
+        self.stages.iter().flat_map(|stage| {
+            stage
+                .ambiguities
+                .iter()
+                .map(move |ambiguity| (stage.name.as_ref(), ambiguity))
+        })
+    }
+
+    /// Renders the graph in the DOT language, with a cluster for each stage. Ordering dependencies
+    /// are solid edges, access dependencies dashed and thread local dependencies dotted. Ambiguous
+    /// pairs of systems are joined by red edges.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph schedule {{").unwrap();
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            writeln!(dot, "    subgraph cluster_{} {{", stage_index).unwrap();
+            writeln!(dot, "        label=\"{}\";", escape(&stage.name)).unwrap();
+            for (system_index, system) in stage.systems.iter().enumerate() {
+                let shape = match system.thread_local_execution {
+                    ThreadLocalExecution::NextFlush => "ellipse",
+                    ThreadLocalExecution::Immediate => "box",
+                };
+                writeln!(
+                    dot,
+                    "        s{}_{} [label=\"{}\", shape={}];",
+                    stage_index,
+                    system_index,
+                    escape(&system.name),
+                    shape
+                )
+                .unwrap();
+            }
+            writeln!(dot, "    }}").unwrap();
+
+            for dependency in stage.dependencies.iter() {
+                let style = match dependency.kind {
+                    DependencyKind::Ordering => "solid",
+                    DependencyKind::Access => "dashed",
+                    DependencyKind::ThreadLocal => "dotted",
+                };
+                writeln!(
+                    dot,
+                    "    s{0}_{1} -> s{0}_{2} [style={3}];",
+                    stage_index, dependency.before, dependency.after, style
+                )
+                .unwrap();
+            }
+            for ambiguity in stage.ambiguities.iter() {
+                writeln!(
+                    dot,
+                    "    s{0}_{1} -> s{0}_{2} [dir=none, color=red, constraint=false];",
+                    stage_index, ambiguity.systems.0, ambiguity.systems.1
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DependencyKind, SystemDependency};
+    use crate::{
+        resource::{Res, ResMut},
+        schedule::{IntoSystemDescriptor, Schedule},
+        system::IntoQuerySystem,
+    };
+    use bevy_hecs::World;
+    use std::any::TypeId;
+
+    struct A;
+    struct B;
+
+    #[test]
+    fn stage_dependencies_and_ambiguities() {
+        fn write_a(_a: ResMut<A>) {}
+        fn read_a(_a: Res<A>) {}
+        fn read_b(_b: Res<B>) {}
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", write_a.system().label("write"));
+        schedule.add_system_to_stage("update", read_a.system().after("write"));
+        schedule.add_system_to_stage("update", write_a.system());
+        schedule.add_system_to_stage("update", read_b.system());
+
+        let graph = schedule.graph(&World::default()).unwrap();
+        let stage = &graph.stages[0];
+        assert_eq!(stage.systems.len(), 4);
+        assert_eq!(
+            stage.dependencies,
+            vec![
+                SystemDependency {
+                    before: 0,
+                    after: 1,
+                    kind: DependencyKind::Ordering
+                },
+                SystemDependency {
+                    before: 0,
+                    after: 2,
+                    kind: DependencyKind::Access
+                },
+                SystemDependency {
+                    before: 1,
+                    after: 2,
+                    kind: DependencyKind::Access
+                },
+            ]
+        );
+
+        let ambiguities = graph.ambiguities().collect::<Vec<_>>();
+        assert_eq!(ambiguities.len(), 2);
+        assert_eq!(ambiguities[0].0, "update");
+        assert_eq!(ambiguities[0].1.systems, (0, 2));
+        assert_eq!(ambiguities[0].1.resources, vec![TypeId::of::<A>()]);
+        assert_eq!(ambiguities[1].1.systems, (1, 2));
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("s0_0 -> s0_1 [style=solid];"));
+        assert!(dot.contains("s0_1 -> s0_2 [dir=none, color=red, constraint=false];"));
+    }
+}
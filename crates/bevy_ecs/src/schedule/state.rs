@@ -0,0 +1,301 @@
+use crate::{
+    resource::{ResMut, Resources},
+    system::{ArchetypeAccess, System, SystemId, ThreadLocalExecution, TypeAccess},
+};
+use bevy_hecs::World;
+use std::{any::TypeId, borrow::Cow};
+
+/// A finite state machine stored as a resource. Transitions requested with [State::set_next] are
+/// queued and applied by [State::transition_system], which makes the enter / exit systems of the
+/// affected states run for exactly one update.
+#[derive(Debug)]
+pub struct State<T> {
+    current: T,
+    previous: Option<T>,
+    queued: Option<T>,
+    entered: bool,
+    exited: Option<T>,
+    initial_enter_pending: bool,
+}
+
+impl<T: Clone + Eq + Send + Sync + 'static> State<T> {
+    /// Creates a new state machine. The enter systems of `initial` run on the first transition update.
+    pub fn new(initial: T) -> Self {
+        Self {
+            current: initial,
+            previous: None,
+            queued: None,
+            entered: false,
+            exited: None,
+            initial_enter_pending: true,
+        }
+    }
+
+    /// The state that is currently active
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// The state that was active before the last transition, if any
+    pub fn previous(&self) -> Option<&T> {
+        self.previous.as_ref()
+    }
+
+    /// The state that will become active on the next transition update, if any
+    pub fn queued(&self) -> Option<&T> {
+        self.queued.as_ref()
+    }
+
+    /// Queues a transition to `state`. Replaces any transition queued earlier in the same update.
+    pub fn set_next(&mut self, state: T) {
+        self.queued = Some(state);
+    }
+
+    /// Returns true if `state` was entered during the current update
+    pub fn just_entered(&self, state: &T) -> bool {
+        self.entered && self.current == *state
+    }
+
+    /// Returns true if `state` was exited during the current update
+    pub fn just_exited(&self, state: &T) -> bool {
+        self.exited.as_ref() == Some(state)
+    }
+
+    /// Applies the queued transition (if any). Queuing the active state is a no-op.
+    pub fn apply_transition(&mut self) {
+        self.entered = false;
+        self.exited = None;
+
+        if std::mem::take(&mut self.initial_enter_pending) {
+            self.entered = true;
+        }
+
+        if let Some(next) = self.queued.take() {
+            if next != self.current {
+                let previous = std::mem::replace(&mut self.current, next);
+                self.previous = Some(previous.clone());
+                self.exited = Some(previous);
+                self.entered = true;
+            }
+        }
+    }
+
+    /// A system that applies queued transitions. It should run once per update, before any state systems.
+    pub fn transition_system(mut state: ResMut<State<T>>) {
+        state.apply_transition();
+    }
+}
+
+/// The part of a state's lifecycle a [StateSystem] runs in
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StatePhase {
+    /// Runs once, in the update the state becomes active
+    Enter,
+    /// Runs every update while the state is active
+    Update,
+    /// Runs once, in the update the state stops being active
+    Exit,
+}
+
+/// Wraps a [System] so that it only runs in the given [StatePhase] of a [State] value
+pub struct StateSystem<T> {
+    system: Box<dyn System>,
+    state: T,
+    phase: StatePhase,
+    resource_access: TypeAccess,
+    should_run: bool,
+}
+
+impl<T: Clone + Eq + Send + Sync + 'static> StateSystem<T> {
+    /// Wraps `system` to run in `phase` of `state`, checked after the queued transition is applied
+    pub fn new(state: T, phase: StatePhase, system: Box<dyn System>) -> Self {
+        let mut state_system = Self {
+            system,
+            state,
+            phase,
+            resource_access: TypeAccess::default(),
+            should_run: false,
+        };
+        state_system.update_resource_access();
+        state_system
+    }
+
+    /// Runs `system` once, in the update that applies a queued transition to `state`
+    pub fn on_enter(state: T, system: Box<dyn System>) -> Box<dyn System> {
+        Box::new(Self::new(state, StatePhase::Enter, system))
+    }
+
+    /// Runs `system` every update `state` is active in after applying the queued transition
+    pub fn on_update(state: T, system: Box<dyn System>) -> Box<dyn System> {
+        Box::new(Self::new(state, StatePhase::Update, system))
+    }
+
+    /// Runs `system` once, in the update that applies a queued transition away from `state`
+    pub fn on_exit(state: T, system: Box<dyn System>) -> Box<dyn System> {
+        Box::new(Self::new(state, StatePhase::Exit, system))
+    }
+
+    /// The phase the wrapped system runs in
+    pub fn phase(&self) -> StatePhase {
+        self.phase
+    }
+
+    fn update_resource_access(&mut self) {
+        self.resource_access.clear();
+        self.resource_access.union(self.system.resource_access());
+        self.resource_access
+            .immutable
+            .insert(TypeId::of::<State<T>>());
+    }
+
+    fn is_active(&self, resources: &Resources) -> bool {
+        let state = resources
+            .get::<State<T>>()
+            .expect("State resource does not exist. Add it before registering state systems.");
+        match self.phase {
+            StatePhase::Enter => state.just_entered(&self.state),
+            StatePhase::Update => *state.current() == self.state,
+            StatePhase::Exit => state.just_exited(&self.state),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Send + Sync + 'static> System for StateSystem<T> {
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn id(&self) -> SystemId {
+        self.system.id()
+    }
+
+    fn update_archetype_access(&mut self, world: &World) {
+        self.system.update_archetype_access(world);
+    }
+
+    fn archetype_access(&self) -> &ArchetypeAccess {
+        self.system.archetype_access()
+    }
+
+    fn resource_access(&self) -> &TypeAccess {
+        &self.resource_access
+    }
+
+    fn thread_local_execution(&self) -> ThreadLocalExecution {
+        self.system.thread_local_execution()
+    }
+
+    fn run(&mut self, world: &World, resources: &Resources) {
+        self.should_run = self.is_active(resources);
+        if self.should_run {
+            self.system.run(world, resources);
+        }
+    }
+
+    fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
+        if self.should_run {
+            self.system.run_thread_local(world, resources);
+        }
+    }
+
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        self.system.initialize(world, resources);
+        self.update_resource_access();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{State, StateSystem};
+    use crate::{
+        resource::{Res, ResMut, Resources},
+        schedule::{ParallelExecutor, Schedule},
+        system::IntoQuerySystem,
+    };
+    use bevy_hecs::World;
+    use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    enum GameState {
+        Menu,
+        InGame,
+    }
+
+    #[test]
+    fn state_transitions() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        resources.insert(ComputeTaskPool(TaskPool::default()));
+        resources.insert(State::new(GameState::Menu));
+        resources.insert(Vec::<&'static str>::new());
+
+        fn enter_menu(mut log: ResMut<Vec<&'static str>>) {
+            log.push("enter_menu");
+        }
+        fn update_menu(mut log: ResMut<Vec<&'static str>>) {
+            log.push("update_menu");
+        }
+        fn exit_menu(mut log: ResMut<Vec<&'static str>>) {
+            log.push("exit_menu");
+        }
+        fn enter_game(mut log: ResMut<Vec<&'static str>>) {
+            log.push("enter_game");
+        }
+        fn update_game(mut log: ResMut<Vec<&'static str>>, state: Res<State<GameState>>) {
+            assert_eq!(state.previous(), Some(&GameState::Menu));
+            log.push("update_game");
+        }
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("transition");
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("transition", State::<GameState>::transition_system.system());
+        schedule
+            .add_system_to_stage(
+                "update",
+                StateSystem::on_exit(GameState::Menu, exit_menu.system()),
+            )
+            .add_system_to_stage(
+                "update",
+                StateSystem::on_enter(GameState::Menu, enter_menu.system()),
+            )
+            .add_system_to_stage(
+                "update",
+                StateSystem::on_update(GameState::Menu, update_menu.system()),
+            )
+            .add_system_to_stage(
+                "update",
+                StateSystem::on_enter(GameState::InGame, enter_game.system()),
+            )
+            .add_system_to_stage(
+                "update",
+                StateSystem::on_update(GameState::InGame, update_game.system()),
+            );
+
+        let mut executor = ParallelExecutor::default();
+        schedule.initialize(&mut world, &mut resources);
+
+        executor.run(&mut schedule, &mut world, &mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["enter_menu", "update_menu", "update_menu"]
+        );
+
+        resources.get_mut::<Vec<&'static str>>().unwrap().clear();
+        resources
+            .get_mut::<State<GameState>>()
+            .unwrap()
+            .set_next(GameState::InGame);
+        executor.run(&mut schedule, &mut world, &mut resources);
+        executor.run(&mut schedule, &mut world, &mut resources);
+        assert_eq!(
+            *resources.get::<Vec<&'static str>>().unwrap(),
+            vec!["exit_menu", "enter_game", "update_game", "update_game"]
+        );
+        assert_eq!(
+            *resources.get::<State<GameState>>().unwrap().current(),
+            GameState::InGame
+        );
+    }
+}
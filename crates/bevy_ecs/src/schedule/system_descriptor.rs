@@ -0,0 +1,88 @@
+use crate::system::System;
+use std::borrow::Cow;
+
+/// Labels and ordering constraints of a system within its stage
+#[derive(Debug, Default, Clone)]
+pub struct SystemOrdering {
+    pub labels: Vec<Cow<'static, str>>,
+    /// Labels of systems that must run after this system
+    pub before: Vec<Cow<'static, str>>,
+    /// Labels of systems that must run before this system
+    pub after: Vec<Cow<'static, str>>,
+}
+
+impl SystemOrdering {
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.before.is_empty() && self.after.is_empty()
+    }
+
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|l| l == label)
+    }
+}
+
+/// A [System] along with its labels and ordering constraints. Created with the methods of
+/// [IntoSystemDescriptor].
+pub struct SystemDescriptor {
+    pub system: Box<dyn System>,
+    pub ordering: SystemOrdering,
+}
+
+impl From<Box<dyn System>> for SystemDescriptor {
+    fn from(system: Box<dyn System>) -> Self {
+        SystemDescriptor {
+            system,
+            ordering: SystemOrdering::default(),
+        }
+    }
+}
+
+/// Adds labels and ordering constraints to a system. Constraints only apply to systems in the same stage.
+///
+/// # Example
+/// ```
+/// # use bevy_ecs::{prelude::*, IntoSystemDescriptor, Schedule};
+/// # fn physics() {}
+/// # fn collisions() {}
+/// let mut schedule = Schedule::default();
+/// schedule.add_stage("update");
+/// schedule.add_system_to_stage("update", collisions.system().after("physics"));
+/// schedule.add_system_to_stage("update", physics.system().label("physics"));
+/// ```
+pub trait IntoSystemDescriptor: Sized {
+    fn descriptor(self) -> SystemDescriptor;
+
+    /// Gives the system a label that other systems can refer to in `before` and `after`. Several
+    /// systems may share the same label.
+    fn label(self, label: impl Into<Cow<'static, str>>) -> SystemDescriptor {
+        let mut descriptor = self.descriptor();
+        descriptor.ordering.labels.push(label.into());
+        descriptor
+    }
+
+    /// Makes the system run before all systems with the given label
+    fn before(self, label: impl Into<Cow<'static, str>>) -> SystemDescriptor {
+        let mut descriptor = self.descriptor();
+        descriptor.ordering.before.push(label.into());
+        descriptor
+    }
+
+    /// Makes the system run after all systems with the given label
+    fn after(self, label: impl Into<Cow<'static, str>>) -> SystemDescriptor {
+        let mut descriptor = self.descriptor();
+        descriptor.ordering.after.push(label.into());
+        descriptor
+    }
+}
+
+impl IntoSystemDescriptor for SystemDescriptor {
+    fn descriptor(self) -> SystemDescriptor {
+        self
+    }
+}
+
+impl IntoSystemDescriptor for Box<dyn System> {
+    fn descriptor(self) -> SystemDescriptor {
+        self.into()
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{
+    resource::Resources,
+    system::{ArchetypeAccess, IntoPipeSystem, System, SystemId, ThreadLocalExecution, TypeAccess},
+};
+use bevy_hecs::World;
+use std::borrow::Cow;
+
+/// The input of a system created with [IntoPipeSystem]. This must be the first parameter of the
+/// system function.
+#[derive(Debug)]
+pub struct In<T>(pub T);
+
+/// A [System] that takes an input before it runs and leaves an output after it runs
+///
+/// A system whose input hasn't been set doesn't run. Systems that take no input have an `In` of
+/// `()`, and always run.
+pub trait PipeSystem: System {
+    type In;
+    type Out;
+    /// Sets the input of the next run
+    fn set_input(&mut self, input: Self::In);
+    /// Takes the output of the last run, if it ran
+    fn take_output(&mut self) -> Option<Self::Out>;
+}
+
+/// Two systems run one after the other as a single [System], with the output of the first piped
+/// into the input of the second. The access of the chain is the union of the access of both.
+pub struct ChainSystem<In, Mid, Out> {
+    a: Box<dyn PipeSystem<In = In, Out = Mid>>,
+    b: Box<dyn PipeSystem<In = Mid, Out = Out>>,
+    name: Cow<'static, str>,
+    id: SystemId,
+    archetype_access: ArchetypeAccess,
+    resource_access: TypeAccess,
+}
+
+impl<In, Mid, Out> ChainSystem<In, Mid, Out> {
+    pub fn new(
+        a: Box<dyn PipeSystem<In = In, Out = Mid>>,
+        b: Box<dyn PipeSystem<In = Mid, Out = Out>>,
+    ) -> Self {
+        let mut chain_system = Self {
+            name: format!("Chain({}, {})", a.name(), b.name()).into(),
+            a,
+            b,
+            id: SystemId::new(),
+            archetype_access: ArchetypeAccess::default(),
+            resource_access: TypeAccess::default(),
+        };
+        chain_system.update_resource_access();
+        chain_system
+    }
+
+    fn update_resource_access(&mut self) {
+        self.resource_access.clear();
+        self.resource_access.union(self.a.resource_access());
+        self.resource_access.union(self.b.resource_access());
+    }
+}
+
+impl<In, Mid, Out> System for ChainSystem<In, Mid, Out> {
+    fn name(&self) -> Cow<'static, str> {
+        self.name.clone()
+    }
+
+    fn id(&self) -> SystemId {
+        self.id
+    }
+
+    fn update_archetype_access(&mut self, world: &World) {
+        self.a.update_archetype_access(world);
+        self.b.update_archetype_access(world);
+        self.archetype_access.clear();
+        self.archetype_access.union(self.a.archetype_access());
+        self.archetype_access.union(self.b.archetype_access());
+    }
+
+    fn archetype_access(&self) -> &ArchetypeAccess {
+        &self.archetype_access
+    }
+
+    fn resource_access(&self) -> &TypeAccess {
+        &self.resource_access
+    }
+
+    fn thread_local_execution(&self) -> ThreadLocalExecution {
+        if self.a.thread_local_execution() == ThreadLocalExecution::Immediate
+            || self.b.thread_local_execution() == ThreadLocalExecution::Immediate
+        {
+            ThreadLocalExecution::Immediate
+        } else {
+            ThreadLocalExecution::NextFlush
+        }
+    }
+
+    fn run(&mut self, world: &World, resources: &Resources) {
+        self.a.run(world, resources);
+        if let Some(output) = self.a.take_output() {
+            self.b.set_input(output);
+            self.b.run(world, resources);
+        }
+    }
+
+    fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
+        self.a.run_thread_local(world, resources);
+        self.b.run_thread_local(world, resources);
+    }
+
+    fn initialize(&mut self, world: &mut World, resources: &mut Resources) {
+        self.a.initialize(world, resources);
+        self.b.initialize(world, resources);
+        self.update_resource_access();
+    }
+}
+
+impl<In, Mid, Out> PipeSystem for ChainSystem<In, Mid, Out> {
+    type In = In;
+    type Out = Out;
+
+    fn set_input(&mut self, input: In) {
+        self.a.set_input(input);
+    }
+
+    fn take_output(&mut self) -> Option<Out> {
+        self.b.take_output()
+    }
+}
+
+/// Converts a pair of system functions into a [ChainSystem]
+///
+/// # Example
+/// ```
+/// use bevy_ecs::prelude::*;
+///
+/// struct Health(f32);
+///
+/// fn validate(mut query: Query<&Health>) -> Result<(), String> {
+///     for health in &mut query.iter() {
+///         if health.0.is_nan() {
+///             return Err("health is NaN".to_string());
+///         }
+///     }
+///     Ok(())
+/// }
+///
+/// fn log_errors(In(result): In<Result<(), String>>) {
+///     if let Err(error) = result {
+///         println!("{}", error);
+///     }
+/// }
+/// # let _ = validate.chain(log_errors);
+/// ```
+pub trait IntoChainSystem<AParams, BParams, B> {
+    /// Pipes the output of this system into the input of `system`, and runs both as one system
+    fn chain(self, system: B) -> Box<dyn System>;
+}
+
+impl<A, B, ACommands, AR, AQ, Mid, BCommands, BR, BQ>
+    IntoChainSystem<(ACommands, AR, AQ, Mid), (BCommands, BR, BQ), B> for A
+where
+    A: IntoPipeSystem<ACommands, AR, AQ, (), Mid>,
+    B: IntoPipeSystem<BCommands, BR, BQ, Mid, ()>,
+    Mid: 'static,
+{
+    fn chain(self, system: B) -> Box<dyn System> {
+        Box::new(ChainSystem::new(self.pipe_system(), system.pipe_system()))
+    }
+}
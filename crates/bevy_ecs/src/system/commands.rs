@@ -1,14 +1,103 @@
 use super::SystemId;
 use crate::resource::{Resource, Resources};
-use bevy_hecs::{Bundle, Component, DynamicBundle, Entity, EntityReserver, World};
+use bevy_hecs::{Bundle, Component, ComponentError, DynamicBundle, Entity, EntityReserver, World};
 use parking_lot::Mutex;
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+use thiserror::Error;
 
 /// A [World] mutation
 pub trait Command: Send + Sync {
     fn write(self: Box<Self>, world: &mut World, resources: &mut Resources);
 }
 
+/// An error that occurred while a [Command] was applied to an entity, like the entity having been
+/// despawned since the command was queued
+#[derive(Debug, Error)]
+#[error("The {command} command on entity {entity:?} failed: {error}")]
+pub struct CommandError {
+    /// The name of the command, like `"insert"`
+    pub command: &'static str,
+    pub entity: Entity,
+    pub error: ComponentError,
+}
+
+impl CommandError {
+    pub fn new(command: &'static str, entity: Entity, error: impl Into<ComponentError>) -> Self {
+        Self {
+            command,
+            entity,
+            error: error.into(),
+        }
+    }
+
+    /// Handles the error as the [CommandErrorPolicy] resource says, or logs it if there is none
+    pub fn report(self, resources: &mut Resources) {
+        let policy = resources
+            .get::<CommandErrorPolicy>()
+            .map(|policy| *policy)
+            .unwrap_or_default();
+        match policy {
+            CommandErrorPolicy::Ignore => {}
+            CommandErrorPolicy::Log => log::warn!("{}", self),
+            CommandErrorPolicy::Panic => panic!("{}", self),
+            CommandErrorPolicy::Collect => {
+                if resources.get::<CommandErrors>().is_none() {
+                    resources.insert(CommandErrors::default());
+                }
+                resources
+                    .get_mut::<CommandErrors>()
+                    .unwrap()
+                    .errors
+                    .push(self);
+            }
+        }
+    }
+}
+
+/// A resource that chooses what happens to [CommandError]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandErrorPolicy {
+    Ignore,
+    Log,
+    Panic,
+    /// Adds the errors to the [CommandErrors] resource
+    Collect,
+}
+
+impl Default for CommandErrorPolicy {
+    fn default() -> Self {
+        CommandErrorPolicy::Log
+    }
+}
+
+/// The [CommandError]s collected under [CommandErrorPolicy::Collect] that haven't been drained yet
+#[derive(Debug, Default)]
+pub struct CommandErrors {
+    errors: Vec<CommandError>,
+}
+
+impl CommandErrors {
+    pub fn iter(&self) -> impl Iterator<Item = &CommandError> {
+        self.errors.iter()
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = CommandError> + '_ {
+        self.errors.drain(..)
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Spawn<T>
 where
@@ -50,9 +139,9 @@ pub(crate) struct Despawn {
 }
 
 impl Command for Despawn {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        if let Err(e) = world.despawn(self.entity) {
-            log::debug!("Failed to despawn entity {:?}: {}", self.entity, e);
+    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources) {
+        if let Err(error) = world.despawn(self.entity) {
+            CommandError::new("despawn", self.entity, error).report(resources);
         }
     }
 }
@@ -69,8 +158,10 @@ impl<T> Command for Insert<T>
 where
     T: DynamicBundle + Send + Sync + 'static,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.insert(self.entity, self.components).unwrap();
+    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources) {
+        if let Err(error) = world.insert(self.entity, self.components) {
+            CommandError::new("insert", self.entity, error).report(resources);
+        }
     }
 }
 
@@ -87,8 +178,10 @@ impl<T> Command for InsertOne<T>
 where
     T: Component,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.insert(self.entity, (self.component,)).unwrap();
+    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources) {
+        if let Err(error) = world.insert(self.entity, (self.component,)) {
+            CommandError::new("insert", self.entity, error).report(resources);
+        }
     }
 }
 
@@ -105,9 +198,9 @@ impl<T> Command for RemoveOne<T>
 where
     T: Component,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        if world.get::<T>(self.entity).is_ok() {
-            world.remove_one::<T>(self.entity).unwrap();
+    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources) {
+        if let Err(error) = world.remove_one::<T>(self.entity) {
+            CommandError::new("remove", self.entity, error).report(resources);
         }
     }
 }
@@ -125,8 +218,10 @@ impl<T> Command for Remove<T>
 where
     T: Bundle + Send + Sync + 'static,
 {
-    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
-        world.remove::<T>(self.entity).unwrap();
+    fn write(self: Box<Self>, world: &mut World, resources: &mut Resources) {
+        if let Err(error) = world.remove::<T>(self.entity) {
+            CommandError::new("remove", self.entity, error).report(resources);
+        }
     }
 }
 
@@ -134,6 +229,20 @@ pub trait ResourcesWriter: Send + Sync {
     fn write(self: Box<Self>, resources: &mut Resources);
 }
 
+pub(crate) struct AddHook<F> {
+    hook: F,
+    register: fn(&mut World, F),
+}
+
+impl<F> Command for AddHook<F>
+where
+    F: Send + Sync + 'static,
+{
+    fn write(self: Box<Self>, world: &mut World, _resources: &mut Resources) {
+        (self.register)(world, self.hook);
+    }
+}
+
 pub struct InsertResource<T: Resource> {
     resource: T,
 }
@@ -214,12 +323,26 @@ pub struct Commands {
 }
 
 impl Commands {
-    pub fn spawn(&mut self, components: impl DynamicBundle + Send + Sync + 'static) -> &mut Self {
-        {
+    /// Reserves a new entity and queues spawning it with `components`. The entity is also made the
+    /// current entity.
+    pub fn spawn(
+        &mut self,
+        components: impl DynamicBundle + Send + Sync + 'static,
+    ) -> EntityCommands<'_> {
+        let entity = {
             let mut commands = self.commands.lock();
             commands.spawn(components);
+            commands.current_entity.unwrap()
+        };
+        self.entity(entity)
+    }
+
+    /// Queues commands for `entity`
+    pub fn entity(&mut self, entity: Entity) -> EntityCommands<'_> {
+        EntityCommands {
+            entity,
+            commands: self,
         }
-        self
     }
 
     pub fn spawn_batch<I>(&mut self, components_iter: I) -> &mut Self
@@ -270,6 +393,42 @@ impl Commands {
         self.add_command(InsertResource { resource })
     }
 
+    /// Queues registering `hook` to run after a `T` component is added to an entity. See
+    /// [World::on_insert].
+    pub fn on_insert<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_command(AddHook {
+            hook,
+            register: |world, hook| world.on_insert::<T>(hook),
+        })
+    }
+
+    /// Queues registering `hook` to run after a `T` component of an entity is replaced. See
+    /// [World::on_replace].
+    pub fn on_replace<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_command(AddHook {
+            hook,
+            register: |world, hook| world.on_replace::<T>(hook),
+        })
+    }
+
+    /// Queues registering `hook` to run before a `T` component is removed from an entity. See
+    /// [World::on_remove].
+    pub fn on_remove<T: Component>(
+        &mut self,
+        hook: impl Fn(&mut World, Entity) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.add_command(AddHook {
+            hook,
+            register: |world, hook| world.on_remove::<T>(hook),
+        })
+    }
+
     pub fn insert_local_resource<T: Resource>(
         &mut self,
         system_id: SystemId,
@@ -339,9 +498,84 @@ impl Commands {
     }
 }
 
+/// Queues commands for a single entity, returned by [Commands::spawn] and [Commands::entity]
+///
+/// It dereferences to the [Commands] it came from, so other commands can be chained after it.
+pub struct EntityCommands<'a> {
+    entity: Entity,
+    commands: &'a mut Commands,
+}
+
+impl<'a> EntityCommands<'a> {
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn insert(&mut self, components: impl DynamicBundle + Send + Sync + 'static) -> &mut Self {
+        self.commands.insert(self.entity, components);
+        self
+    }
+
+    pub fn insert_one(&mut self, component: impl Component) -> &mut Self {
+        self.commands.insert_one(self.entity, component);
+        self
+    }
+
+    /// Same as [EntityCommands::insert_one]
+    pub fn with(&mut self, component: impl Component) -> &mut Self {
+        self.insert_one(component)
+    }
+
+    /// Same as [EntityCommands::insert]
+    pub fn with_bundle(
+        &mut self,
+        components: impl DynamicBundle + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.insert(components)
+    }
+
+    pub fn remove<T>(&mut self) -> &mut Self
+    where
+        T: Bundle + Send + Sync + 'static,
+    {
+        self.commands.remove::<T>(self.entity);
+        self
+    }
+
+    pub fn remove_one<T>(&mut self) -> &mut Self
+    where
+        T: Component,
+    {
+        self.commands.remove_one::<T>(self.entity);
+        self
+    }
+
+    pub fn despawn(&mut self) {
+        self.commands.despawn(self.entity);
+    }
+
+    pub fn commands(&mut self) -> &mut Commands {
+        self.commands
+    }
+}
+
+impl<'a> Deref for EntityCommands<'a> {
+    type Target = Commands;
+
+    fn deref(&self) -> &Commands {
+        self.commands
+    }
+}
+
+impl<'a> DerefMut for EntityCommands<'a> {
+    fn deref_mut(&mut self) -> &mut Commands {
+        self.commands
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Commands;
+    use super::{CommandErrorPolicy, CommandErrors, Commands};
     use crate::resource::Resources;
     use bevy_hecs::World;
 
@@ -373,4 +607,80 @@ mod tests {
             .collect::<Vec<_>>();
         assert_eq!(results2, vec![]);
     }
+
+    #[test]
+    fn commands_run_component_hooks() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.on_insert::<u32>(|world, entity| {
+            world.insert_one(entity, "inserted").unwrap();
+        });
+        world.on_remove::<u32>(|world, entity| {
+            let value = *world.get::<u32>(entity).unwrap();
+            world.insert_one(entity, value as u64).unwrap();
+        });
+
+        let mut command_buffer = Commands::default();
+        command_buffer.set_entity_reserver(world.get_entity_reserver());
+        command_buffer.spawn((1u32,));
+        let entity = command_buffer.current_entity().unwrap();
+        command_buffer.apply(&mut world, &mut resources);
+        assert_eq!(*world.get::<&str>(entity).unwrap(), "inserted");
+
+        command_buffer.remove_one::<u32>(entity);
+        command_buffer.apply(&mut world, &mut resources);
+        assert!(world.get::<u32>(entity).is_err());
+        assert_eq!(*world.get::<u64>(entity).unwrap(), 1);
+    }
+
+    #[test]
+    fn commands_register_component_hooks() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        commands
+            .on_insert::<u32>(|world, entity| {
+                world.insert_one(entity, "inserted").unwrap();
+            })
+            .on_remove::<u32>(|world, entity| {
+                let value = *world.get::<u32>(entity).unwrap();
+                world.insert_one(entity, value as u64).unwrap();
+            });
+
+        let entity = commands.spawn((1u32,)).id();
+        commands.remove_one::<u32>(entity);
+        commands.apply(&mut world, &mut resources);
+        assert_eq!(*world.get::<&str>(entity).unwrap(), "inserted");
+        assert_eq!(*world.get::<u64>(entity).unwrap(), 1);
+    }
+
+    #[test]
+    fn entity_commands_report_failures() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(CommandErrorPolicy::Collect);
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+
+        let entity = commands.spawn((1u32,)).with(2u64).id();
+        commands.entity(entity).insert_one("a").remove_one::<u32>();
+        commands.apply(&mut world, &mut resources);
+        assert!(world.get::<u32>(entity).is_err());
+        assert_eq!(*world.get::<&str>(entity).unwrap(), "a");
+        assert!(resources.get::<CommandErrors>().is_none());
+
+        commands.entity(entity).remove_one::<u32>().despawn();
+        commands.entity(entity).insert_one(3u32).despawn();
+        commands.apply(&mut world, &mut resources);
+        let mut errors = resources.get_mut::<CommandErrors>().unwrap();
+        let errors = errors
+            .drain()
+            .map(|error| (error.command, error.entity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![("remove", entity), ("insert", entity), ("despawn", entity)]
+        );
+    }
 }
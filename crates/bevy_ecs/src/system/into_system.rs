@@ -2,15 +2,19 @@ pub use super::Query;
 use super::TypeAccess;
 use crate::{
     resource::{FetchResource, ResourceQuery, Resources, UnsafeClone},
-    system::{ArchetypeAccess, Commands, System, SystemId, ThreadLocalExecution},
+    system::{
+        ArchetypeAccess, Commands, FetchSystemParam, In, ParamGroup, PipeSystem, System,
+        SystemChangeTicks, SystemId, SystemParam, SystemParamFetch, SystemParamState,
+        ThreadLocalExecution,
+    },
 };
-use bevy_hecs::{Fetch, Query as HecsQuery, World};
+use bevy_hecs::{Fetch, MatchedArchetypes, Query as HecsQuery, World};
 use std::borrow::Cow;
 
 #[derive(Debug)]
 pub(crate) struct SystemFn<State, F, ThreadLocalF, Init, SetArchetypeAccess>
 where
-    F: FnMut(&World, &Resources, &ArchetypeAccess, &mut State) + Send + Sync,
+    F: FnMut(&World, &Resources, &ArchetypeAccess, SystemChangeTicks, &mut State) + Send + Sync,
     ThreadLocalF: FnMut(&mut World, &mut Resources, &mut State) + Send + Sync,
     Init: FnMut(&mut World, &mut Resources, &mut State) + Send + Sync,
     SetArchetypeAccess: FnMut(&World, &mut ArchetypeAccess, &mut State) + Send + Sync,
@@ -26,12 +30,13 @@ where
     pub id: SystemId,
     pub archetype_access: ArchetypeAccess,
     pub set_archetype_access: SetArchetypeAccess,
+    pub change_ticks: SystemChangeTicks,
 }
 
 impl<State, F, ThreadLocalF, Init, SetArchetypeAccess> System
     for SystemFn<State, F, ThreadLocalF, Init, SetArchetypeAccess>
 where
-    F: FnMut(&World, &Resources, &ArchetypeAccess, &mut State) + Send + Sync,
+    F: FnMut(&World, &Resources, &ArchetypeAccess, SystemChangeTicks, &mut State) + Send + Sync,
     ThreadLocalF: FnMut(&mut World, &mut Resources, &mut State) + Send + Sync,
     Init: FnMut(&mut World, &mut Resources, &mut State) + Send + Sync,
     SetArchetypeAccess: FnMut(&World, &mut ArchetypeAccess, &mut State) + Send + Sync,
@@ -59,7 +64,14 @@ where
 
     #[inline]
     fn run(&mut self, world: &World, resources: &Resources) {
-        (self.func)(world, resources, &self.archetype_access, &mut self.state);
+        self.change_ticks = self.change_ticks.next_run(world, resources);
+        (self.func)(
+            world,
+            resources,
+            &self.archetype_access,
+            self.change_ticks,
+            &mut self.state,
+        );
     }
 
     fn run_thread_local(&mut self, world: &mut World, resources: &mut Resources) {
@@ -100,44 +112,100 @@ macro_rules! impl_into_foreach_system {
             fn system(mut self) -> Box<dyn System> {
                 let id = SystemId::new();
                 Box::new(SystemFn {
-                    state: Commands::default(),
+                    state: ForEachSystemState {
+                        commands: Commands::default(),
+                        matched_archetypes: MatchedArchetypes::default(),
+                    },
                     thread_local_execution: ThreadLocalExecution::NextFlush,
                     name: core::any::type_name::<Self>().into(),
                     id,
-                    func: move |world, resources, _archetype_access, state| {
+                    func: move |world, resources, _archetype_access, change_ticks, state| {
                         {
-                            if let Some(($($resource,)*)) = resources.query_system::<($($resource,)*)>(id) {
+                            if let Some(($($resource,)*)) = resources.query_system::<($($resource,)*)>(
+                                id,
+                                change_ticks.last_resource_change_tick,
+                                change_ticks.resource_change_tick,
+                            ) {
                                 // SAFE: the scheduler has ensured that there is no archetype clashing here
                                 unsafe {
-                                    for ($($component,)*) in world.query_unchecked::<($($component,)*)>().iter() {
-                                        fn_call!(self, ($($commands, state)*), ($($resource),*), ($($component),*))
+                                    let commands = &state.commands;
+                                    for ($($component,)*) in state.matched_archetypes.query_with_ticks_unchecked::<($($component,)*)>(
+                                        world,
+                                        change_ticks.last_change_tick,
+                                        change_ticks.change_tick,
+                                    ).iter() {
+                                        fn_call!(self, (), ($($commands, commands)*), ($($resource),*), ($($component),*))
                                     }
                                 }
                             }
                         }
                     },
                     thread_local_func: move |world, resources, state| {
-                        state.apply(world, resources);
+                        state.commands.apply(world, resources);
                     },
                     init_func: move |world, resources, state| {
                         <($($resource,)*)>::initialize(resources, Some(id));
-                        state.set_entity_reserver(world.get_entity_reserver())
+                        state.commands.set_entity_reserver(world.get_entity_reserver())
                     },
                     resource_access: <<($($resource,)*) as ResourceQuery>::Fetch as FetchResource>::access(),
                     archetype_access: ArchetypeAccess::default(),
-                    set_archetype_access: |world, archetype_access, _state| {
+                    set_archetype_access: |world, archetype_access, state| {
                         archetype_access.clear();
-                        archetype_access.set_access_for_query::<($($component,)*)>(world);
+                        state.matched_archetypes.update::<($($component,)*)>(world);
+                        archetype_access.set_access_for_matched_archetypes::<($($component,)*)>(
+                            world,
+                            state.matched_archetypes.indices(),
+                        );
                     },
+                    change_ticks: SystemChangeTicks::default(),
                 })
             }
         }
     };
 }
 
-struct QuerySystemState {
+struct ForEachSystemState {
+    commands: Commands,
+    matched_archetypes: MatchedArchetypes,
+}
+
+struct QuerySystemState<In, Out> {
     archetype_accesses: Vec<ArchetypeAccess>,
+    matched_archetypes: Vec<MatchedArchetypes>,
     commands: Commands,
+    params: SystemParamState,
+    input: Option<In>,
+    output: Option<Out>,
+}
+
+impl<In, Out, F, ThreadLocalF, Init, SetArchetypeAccess> PipeSystem
+    for SystemFn<QuerySystemState<In, Out>, F, ThreadLocalF, Init, SetArchetypeAccess>
+where
+    F: FnMut(
+            &World,
+            &Resources,
+            &ArchetypeAccess,
+            SystemChangeTicks,
+            &mut QuerySystemState<In, Out>,
+        ) + Send
+        + Sync,
+    ThreadLocalF: FnMut(&mut World, &mut Resources, &mut QuerySystemState<In, Out>) + Send + Sync,
+    Init: FnMut(&mut World, &mut Resources, &mut QuerySystemState<In, Out>) + Send + Sync,
+    SetArchetypeAccess:
+        FnMut(&World, &mut ArchetypeAccess, &mut QuerySystemState<In, Out>) + Send + Sync,
+    In: Send + Sync,
+    Out: Send + Sync,
+{
+    type In = In;
+    type Out = Out;
+
+    fn set_input(&mut self, input: In) {
+        self.state.input = Some(input);
+    }
+
+    fn take_output(&mut self) -> Option<Out> {
+        self.state.output.take()
+    }
 }
 
 /// Converts `Self` into a Query System
@@ -145,49 +213,118 @@ pub trait IntoQuerySystem<Commands, R, Q> {
     fn system(self) -> Box<dyn System>;
 }
 
+impl<Func, Commands, R, Q> IntoQuerySystem<Commands, R, Q> for Func
+where
+    Func: IntoPipeSystem<Commands, R, Q, (), ()>,
+{
+    fn system(self) -> Box<dyn System> {
+        self.pipe_system()
+    }
+}
+
+/// Converts `Self` into a Query System that returns an output and optionally takes an [In] as its
+/// first parameter, so that it can be chained with other systems
+pub trait IntoPipeSystem<Commands, R, Q, In, Out> {
+    fn pipe_system(self) -> Box<dyn PipeSystem<In = In, Out = Out>>;
+}
+
 macro_rules! impl_into_query_system {
-    (($($commands: ident)*), ($($resource: ident),*), ($($query: ident),*)) => {
-        impl<Func, $($resource,)* $($query,)*> IntoQuerySystem<($($commands,)*), ($($resource,)*), ($($query,)*)> for Func where
+    (($($input: ident)*), ($($commands: ident)*), ($($resource: ident),*), ($($query: ident),*), ($($param: ident)*)) => {
+        #[allow(unused_parens)]
+        impl<Func, Out, $($input,)* $($resource,)* $($query,)* $($param,)*> IntoPipeSystem<($(In<$input>,)* $($commands,)*), ($($resource,)*), ($($query,)* $(ParamGroup<$param>,)*), ($($input)*), Out> for Func where
             Func:
-                FnMut($($commands,)* $($resource,)* $(Query<$query>,)*) +
+                FnMut($(In<$input>,)* $($commands,)* $($resource,)* $(Query<$query>,)* $($param,)*) -> Out +
                 FnMut(
+                    $(In<$input>,)*
                     $($commands,)*
                     $(<<$resource as ResourceQuery>::Fetch as FetchResource>::Item,)*
-                    $(Query<$query>,)*) +
+                    $(Query<$query>,)*
+                    $(<<$param as FetchSystemParam>::Fetch as SystemParamFetch>::Item,)*) -> Out +
                 Send + Sync +'static,
+            Out: Send + Sync + 'static,
+            $($input: Send + Sync + 'static,)*
             $($query: HecsQuery,)*
             $($resource: ResourceQuery,)*
+            $($param: SystemParam,)*
         {
             #[allow(non_snake_case)]
             #[allow(unused_variables)]
             #[allow(unused_unsafe)]
             #[allow(unused_assignments)]
             #[allow(unused_mut)]
-            fn system(mut self) -> Box<dyn System> {
+            fn pipe_system(mut self) -> Box<dyn PipeSystem<In = ($($input)*), Out = Out>> {
                 let id = SystemId::new();
                 $(let $query = ArchetypeAccess::default();)*
+                let archetype_accesses = vec![
+                    $($query,)*
+                ];
+                let matched_archetypes = archetype_accesses
+                    .iter()
+                    .map(|_| MatchedArchetypes::default())
+                    .collect();
+                let commands = Commands::default();
+                let mut params = SystemParamState::new(
+                    id,
+                    commands.clone(),
+                    <<($($resource,)*) as ResourceQuery>::Fetch as FetchResource>::access(),
+                );
+                $(<<$param as FetchSystemParam>::Fetch as SystemParamFetch>::init(&mut params);)*
+                let resource_access = params.resource_access().clone();
                 Box::new(SystemFn {
                     state: QuerySystemState {
-                        archetype_accesses: vec![
-                            $($query,)*
-                        ],
-                        commands: Commands::default(),
+                        archetype_accesses,
+                        matched_archetypes,
+                        commands,
+                        params,
+                        input: None,
+                        output: None,
                     },
                     thread_local_execution: ThreadLocalExecution::NextFlush,
                     id,
                     name: core::any::type_name::<Self>().into(),
-                    func: move |world, resources, archetype_access, state| {
-                        {
-                            if let Some(($($resource,)*)) = resources.query_system::<($($resource,)*)>(id) {
-                                let mut i = 0;
-                                $(
-                                    let $query = Query::<$query>::new(world, &state.archetype_accesses[i]);
-                                    i += 1;
-                                )*
-
-                                let commands = &state.commands;
-                                fn_call!(self, ($($commands, commands)*), ($($resource),*), ($($query),*))
-                            }
+                    func: move |world, resources, archetype_access, change_ticks, state| {
+                        $(
+                            let $input = match state.input.take() {
+                                Some(input) => In(input),
+                                None => return,
+                            };
+                        )*
+                        if let Some(($($resource,)*)) = resources.query_system::<($($resource,)*)>(
+                            id,
+                            change_ticks.last_resource_change_tick,
+                            change_ticks.resource_change_tick,
+                        ) {
+                            let mut i = 0;
+                            $(
+                                let $query = Query::<$query>::new(
+                                    world,
+                                    &state.archetype_accesses[i],
+                                    state.matched_archetypes[i].indices(),
+                                    change_ticks.last_change_tick,
+                                    change_ticks.change_tick,
+                                );
+                                i += 1;
+                            )*
+
+                            state.params.change_ticks = change_ticks;
+                            let mut query_index = 0;
+                            $(
+                                // SAFE: the scheduler has ensured that there is no archetype clashing here
+                                let $param = match unsafe {
+                                    <<$param as FetchSystemParam>::Fetch as SystemParamFetch>::get_param(
+                                        &state.params,
+                                        &mut query_index,
+                                        world,
+                                        resources,
+                                    )
+                                } {
+                                    Some(param) => param,
+                                    None => return,
+                                };
+                            )*
+
+                            let commands = &state.commands;
+                            state.output = Some(fn_call!(self, ($($input)*), ($($commands, commands)*), ($($resource),*), ($($query),*), ($($param)*)));
                         }
                     },
                     thread_local_func: move |world, resources, state| {
@@ -195,10 +332,11 @@ macro_rules! impl_into_query_system {
                     },
                     init_func: move |world, resources, state| {
                         <($($resource,)*)>::initialize(resources, Some(id));
+                        $(<<$param as FetchSystemParam>::Fetch as SystemParamFetch>::initialize(&state.params, world, resources);)*
                         state.commands.set_entity_reserver(world.get_entity_reserver())
 
                     },
-                    resource_access: <<($($resource,)*) as ResourceQuery>::Fetch as FetchResource>::access(),
+                    resource_access,
                     archetype_access: ArchetypeAccess::default(),
                     set_archetype_access: |world, archetype_access, state| {
                         archetype_access.clear();
@@ -207,11 +345,17 @@ macro_rules! impl_into_query_system {
                         $(
                             access = &mut state.archetype_accesses[i];
                             access.clear();
-                            access.set_access_for_query::<$query>(world);
+                            state.matched_archetypes[i].update::<$query>(world);
+                            access.set_access_for_matched_archetypes::<$query>(
+                                world,
+                                state.matched_archetypes[i].indices(),
+                            );
                             archetype_access.union(access);
                             i += 1;
                          )*
+                        state.params.update_archetype_access(world, archetype_access);
                     },
+                    change_ticks: SystemChangeTicks::default(),
                 })
             }
         }
@@ -219,20 +363,32 @@ macro_rules! impl_into_query_system {
 }
 
 macro_rules! fn_call {
-    ($self:ident, ($($commands: ident, $commands_var: ident)*), ($($resource: ident),*), ($($a: ident),*)) => {
-        unsafe { $self($($commands_var.clone(),)* $($resource.unsafe_clone(),)* $($a,)*) }
+    ($self:ident, ($($input: ident)*), ($($commands: ident, $commands_var: ident)*), ($($resource: ident),*), ($($a: ident),*)) => {
+        fn_call!($self, ($($input)*), ($($commands, $commands_var)*), ($($resource),*), ($($a),*), ())
     };
-    ($self:ident, (), ($($resource: ident),*), ($($a: ident),*)) => {
-        unsafe { $self($($resource.unsafe_clone(),)* $($a,)*) }
+    ($self:ident, ($($input: ident)*), ($($commands: ident, $commands_var: ident)*), ($($resource: ident),*), ($($a: ident),*), ($($param: ident)*)) => {
+        unsafe { $self($($input,)* $($commands_var.clone(),)* $($resource.unsafe_clone(),)* $($a,)* $($param,)*) }
     };
 }
 
 macro_rules! impl_into_query_systems {
     (($($resource: ident,)*), ($($query: ident),*)) => {
         #[rustfmt::skip]
-        impl_into_query_system!((), ($($resource),*), ($($query),*));
+        impl_into_query_system!((), (), ($($resource),*), ($($query),*), ());
+        #[rustfmt::skip]
+        impl_into_query_system!((), (Commands), ($($resource),*), ($($query),*), ());
+        #[rustfmt::skip]
+        impl_into_query_system!((Input), (), ($($resource),*), ($($query),*), ());
+        #[rustfmt::skip]
+        impl_into_query_system!((Input), (Commands), ($($resource),*), ($($query),*), ());
         #[rustfmt::skip]
-        impl_into_query_system!((Commands), ($($resource),*), ($($query),*));
+        impl_into_query_system!((), (), ($($resource),*), ($($query),*), (P));
+        #[rustfmt::skip]
+        impl_into_query_system!((), (Commands), ($($resource),*), ($($query),*), (P));
+        #[rustfmt::skip]
+        impl_into_query_system!((Input), (), ($($resource),*), ($($query),*), (P));
+        #[rustfmt::skip]
+        impl_into_query_system!((Input), (Commands), ($($resource),*), ($($query),*), (P));
     }
 }
 
@@ -319,7 +475,7 @@ where
             thread_local_func: move |world, resources, _| {
                 self.run(world, resources);
             },
-            func: |_, _, _, _| {},
+            func: |_, _, _, _, _| {},
             init_func: |_, _, _| {},
             set_archetype_access: |_, _, _| {},
             thread_local_execution: ThreadLocalExecution::Immediate,
@@ -327,6 +483,7 @@ where
             id: SystemId::new(),
             resource_access: TypeAccess::default(),
             archetype_access: ArchetypeAccess::default(),
+            change_ticks: SystemChangeTicks::default(),
         })
     }
 }
@@ -349,11 +506,12 @@ where
 mod tests {
     use super::{IntoForEachSystem, IntoQuerySystem, Query};
     use crate::{
-        resource::{ResMut, Resources},
-        schedule::Schedule,
-        ChangedRes, Mut,
+        resource::{Local, RemovedComponents, Res, ResMut, Resources},
+        schedule::{IntoRunCriteria, Schedule, ShouldRun},
+        system::{In, IntoChainSystem},
+        ChangedRes, Commands, Mut, SystemParam,
     };
-    use bevy_hecs::{Entity, With, World};
+    use bevy_hecs::{Changed, Entity, StorageType, With, Without, World};
 
     struct A;
     struct B;
@@ -447,4 +605,279 @@ mod tests {
         schedule.run(&mut world, &mut resources);
         assert_eq!(*(world.get::<i32>(ent).unwrap()), 2);
     }
+
+    #[test]
+    #[should_panic(expected = "already borrowed")]
+    fn sparse_query_conflict_panics() {
+        fn sys(mut query: Query<(&mut A, &A)>) {
+            for _ in &mut query.iter() {}
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        world.register_component::<A>(StorageType::SparseSet);
+        world.spawn((A, B));
+        world.spawn((A,));
+
+        let mut system = sys.system();
+        system.initialize(&mut world, &mut resources);
+        system.update_archetype_access(&world);
+        system.run(&world, &resources);
+    }
+
+    #[derive(Default)]
+    struct Seen(Vec<i32>);
+
+    fn record_changed(mut seen: ResMut<Seen>, mut query: Query<Changed<i32>>) {
+        for value in &mut query.iter() {
+            seen.0.push(*value);
+        }
+    }
+
+    fn increment(mut query: Query<Mut<i32>>) {
+        for mut value in &mut query.iter() {
+            *value += 1;
+        }
+    }
+
+    #[test]
+    fn changes_made_after_a_system_ran_are_seen_next_frame() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Seen::default());
+        world.spawn((0,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", record_changed.system());
+        schedule.add_system_to_stage("update", increment.system());
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Seen>().unwrap().0, vec![0]);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Seen>().unwrap().0, vec![0, 1]);
+    }
+
+    #[test]
+    fn skipped_systems_see_changes_since_their_last_run() {
+        fn every_other_frame(mut frame: Local<usize>) -> ShouldRun {
+            *frame += 1;
+            (*frame % 2 == 1).into()
+        }
+
+        fn count_changed_res(changed: ChangedRes<i32>, mut seen: ResMut<Seen>) {
+            seen.0.push(*changed);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Seen::default());
+        resources.insert(0i32);
+        let entity = world.spawn((0,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage_with_run_criteria(
+            "update",
+            record_changed.system(),
+            every_other_frame.run_criteria(),
+        );
+        schedule.add_system_to_stage_with_run_criteria(
+            "update",
+            count_changed_res.system(),
+            every_other_frame.run_criteria(),
+        );
+
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Seen>().unwrap().0, vec![0, 0]);
+
+        // the systems are skipped this frame and don't run again until after these changes
+        schedule.run(&mut world, &mut resources);
+        *world.get_mut::<i32>(entity).unwrap() = 5;
+        *resources.get_mut::<i32>().unwrap() = 7;
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Seen>().unwrap().0, vec![0, 0, 5, 7]);
+
+        schedule.run(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<Seen>().unwrap().0, vec![0, 0, 5, 7]);
+    }
+
+    #[test]
+    fn removals_in_later_stages_are_seen_next_frame() {
+        #[derive(Default)]
+        struct SeenRemoved(Vec<Entity>);
+
+        fn record_removed(mut removed: RemovedComponents<A>, mut seen: ResMut<SeenRemoved>) {
+            seen.0.extend(removed.iter());
+        }
+
+        fn despawn_a(mut commands: Commands, mut query: Query<With<A, Entity>>) {
+            for entity in &mut query.iter() {
+                commands.despawn(entity);
+            }
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(SeenRemoved::default());
+        let entity = world.spawn((A,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("first");
+        schedule.add_stage("last");
+        schedule.add_system_to_stage("first", record_removed.system());
+        schedule.add_system_to_stage("last", despawn_a.system());
+
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert!(resources.get::<SeenRemoved>().unwrap().0.is_empty());
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<SeenRemoved>().unwrap().0, vec![entity]);
+
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(resources.get::<SeenRemoved>().unwrap().0, vec![entity]);
+    }
+
+    #[test]
+    fn query_get_many_mut() {
+        struct Pair(Entity, Entity);
+
+        fn swap_pair(pair: Res<Pair>, query: Query<&mut i32>) {
+            let mut values = query.get_many_mut::<i32, 2>([pair.0, pair.1]).unwrap();
+            let [mut x, mut y] = values.get_mut();
+            std::mem::swap(&mut *x, &mut *y);
+            assert!(query.get_many_mut::<i32, 2>([pair.0, pair.0]).is_err());
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let a = world.spawn((1,));
+        let b = world.spawn((2,));
+        resources.insert(Pair(a, b));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", swap_pair.system());
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(*world.get::<i32>(a).unwrap(), 2);
+        assert_eq!(*world.get::<i32>(b).unwrap(), 1);
+    }
+
+    #[test]
+    fn chained_systems_pipe_outputs() {
+        #[derive(Debug, PartialEq)]
+        struct NegativeValue(Entity);
+
+        fn check_values(mut query: Query<(Entity, &i32)>) -> Result<usize, NegativeValue> {
+            let mut count = 0;
+            for (entity, value) in &mut query.iter() {
+                if *value < 0 {
+                    return Err(NegativeValue(entity));
+                }
+                count += 1;
+            }
+            Ok(count)
+        }
+
+        fn handle_errors(
+            In(result): In<Result<usize, NegativeValue>>,
+            mut results: ResMut<Vec<Result<usize, NegativeValue>>>,
+        ) {
+            results.push(result);
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(Vec::<Result<usize, NegativeValue>>::new());
+        world.spawn((1,));
+        world.spawn((2,));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", check_values.chain(handle_errors));
+        schedule.run(&mut world, &mut resources);
+
+        let negative = world.spawn((-1,));
+        schedule.run(&mut world, &mut resources);
+
+        assert_eq!(
+            *resources
+                .get::<Vec<Result<usize, NegativeValue>>>()
+                .unwrap(),
+            vec![Ok(2), Err(NegativeValue(negative))]
+        );
+    }
+
+    #[test]
+    fn grouped_system_params() {
+        use crate as bevy_ecs;
+
+        #[derive(SystemParam)]
+        struct Counting<'a> {
+            count: ResMut<'a, usize>,
+            calls: Local<'a, usize>,
+            values: Query<'a, &'a i32>,
+            commands: Commands,
+        }
+
+        #[derive(SystemParam)]
+        struct Nested<'a> {
+            counting: Counting<'a>,
+            label: Res<'a, &'static str>,
+        }
+
+        fn count_values(mut flags: Query<&mut bool>, mut nested: Nested) {
+            let counting = &mut nested.counting;
+            *counting.calls += 1;
+            *counting.count = counting.values.iter().iter().count() + *counting.calls;
+            for mut flag in &mut flags.iter() {
+                *flag = true;
+            }
+            assert_eq!(*nested.label, "values");
+            nested.counting.commands.spawn((1,));
+        }
+
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        resources.insert(0usize);
+        resources.insert("values");
+        world.spawn((1, false));
+
+        let system = count_values.system();
+        let resource_access = system.resource_access();
+        assert!(resource_access
+            .mutable
+            .contains(&std::any::TypeId::of::<usize>()));
+        assert!(resource_access
+            .immutable
+            .contains(&std::any::TypeId::of::<&'static str>()));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage("update");
+        schedule.add_system_to_stage("update", system);
+        schedule.initialize(&mut world, &mut resources);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 2);
+        schedule.run(&mut world, &mut resources);
+        assert_eq!(*resources.get::<usize>().unwrap(), 4);
+        assert_eq!(world.query::<&i32>().iter().count(), 3);
+
+        // only the query of the group accesses the archetype without flags
+        fn read_values(_values: Query<Without<bool, &i32>>) {}
+        fn write_values(_values: Query<Without<bool, &mut i32>>) {}
+        let mut system = count_values.system();
+        let mut reader = read_values.system();
+        let mut writer = write_values.system();
+        for system in [&mut system, &mut reader, &mut writer].iter_mut() {
+            system.update_archetype_access(&world);
+        }
+        let access = system.archetype_access();
+        assert!(access.is_compatible(reader.archetype_access()));
+        assert!(!access.is_compatible(writer.archetype_access()));
+    }
 }
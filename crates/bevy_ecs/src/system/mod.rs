@@ -1,3 +1,4 @@
+mod chain_system;
 mod commands;
 mod into_system;
 #[cfg(feature = "profiler")]
@@ -5,10 +6,13 @@ mod profiler;
 mod query;
 #[allow(clippy::module_inception)]
 mod system;
+mod system_param;
 
+pub use chain_system::*;
 pub use commands::*;
 pub use into_system::*;
 #[cfg(feature = "profiler")]
 pub use profiler::*;
 pub use query::*;
 pub use system::*;
+pub use system_param::*;
@@ -1,16 +1,19 @@
 use crate::ArchetypeAccess;
 use bevy_hecs::{
-    Archetype, Component, ComponentError, Entity, Fetch, Query as HecsQuery, Ref, RefMut, With,
-    Without, World,
+    Archetype, Component, ComponentError, ComponentId, Entity, Fetch, Query as HecsQuery, Ref,
+    RefMut, RefMutMany, SparseSets, With, Without, World,
 };
 use bevy_tasks::ParallelIterator;
-use std::{fmt, marker::PhantomData};
+use std::{any::TypeId, fmt, marker::PhantomData};
 
 /// Provides scoped access to a World according to a given [HecsQuery]
 #[derive(Debug)]
 pub struct Query<'a, Q: HecsQuery> {
     pub(crate) world: &'a World,
     pub(crate) archetype_access: &'a ArchetypeAccess,
+    matched_archetypes: &'a [usize],
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
@@ -24,18 +27,37 @@ pub enum QueryError {
 }
 
 impl<'a, Q: HecsQuery> Query<'a, Q> {
+    /// Creates a query for a system run, iterating over the archetypes at `matched_archetypes`.
+    /// Change detecting queries see the changes made after `last_change_tick`, and mutations are
+    /// recorded with `change_tick`.
     #[inline]
-    pub fn new(world: &'a World, archetype_access: &'a ArchetypeAccess) -> Self {
+    pub fn new(
+        world: &'a World,
+        archetype_access: &'a ArchetypeAccess,
+        matched_archetypes: &'a [usize],
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             world,
             archetype_access,
+            matched_archetypes,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData::default(),
         }
     }
 
     #[inline]
     pub fn iter(&mut self) -> QueryBorrowChecked<'_, Q> {
-        QueryBorrowChecked::new(&self.world.archetypes, self.archetype_access)
+        QueryBorrowChecked::new(
+            &self.world.archetypes,
+            self.world.sparse_sets(),
+            self.matched_archetypes,
+            self.archetype_access,
+            self.last_change_tick,
+            self.change_tick,
+        )
     }
 
     // TODO: find a way to make `iter`, `get`, `get_mut`, and `entity` safe without using tracking pointers with global locks
@@ -44,11 +66,16 @@ impl<'a, Q: HecsQuery> Query<'a, Q> {
     /// the given component type or if the given component type does not match this query.
     pub fn get<T: Component>(&self, entity: Entity) -> Result<Ref<T>, QueryError> {
         if let Some(location) = self.world.get_entity_location(entity) {
-            if self
-                .archetype_access
-                .accessed
-                .contains(location.archetype as usize)
-            {
+            let can_read = if self.world.sparse_sets().contains(ComponentId::of::<T>()) {
+                let access = &self.archetype_access.sparse_sets;
+                access.immutable.contains(&TypeId::of::<T>())
+                    || access.mutable.contains(&TypeId::of::<T>())
+            } else {
+                self.archetype_access
+                    .accessed
+                    .contains(location.archetype as usize)
+            };
+            if can_read {
                 // SAFE: we have already checked that the entity/component matches our archetype access. and systems are scheduled to run with safe archetype access
                 unsafe {
                     self.world
@@ -65,16 +92,22 @@ impl<'a, Q: HecsQuery> Query<'a, Q> {
 
     pub fn entity(&mut self, entity: Entity) -> Result<QueryOneChecked<'_, Q>, QueryError> {
         if let Some(location) = self.world.get_entity_location(entity) {
+            let archetype = &self.world.archetypes[location.archetype as usize];
+            let sparse_sets = self.world.sparse_sets();
             if self
                 .archetype_access
                 .accessed
                 .contains(location.archetype as usize)
+                || Q::Fetch::access(archetype, sparse_sets).is_some()
             {
                 // SAFE: we have already checked that the entity matches our archetype. and systems are scheduled to run with safe archetype access
                 Ok(unsafe {
                     QueryOneChecked::new(
-                        &self.world.archetypes[location.archetype as usize],
+                        archetype,
+                        sparse_sets,
                         location.index,
+                        self.last_change_tick,
+                        self.change_tick,
                     )
                 })
             } else {
@@ -93,15 +126,21 @@ impl<'a, Q: HecsQuery> Query<'a, Q> {
             Some(location) => location,
         };
 
-        if self
-            .archetype_access
-            .mutable
-            .contains(location.archetype as usize)
-        {
+        let can_write = if self.world.sparse_sets().contains(ComponentId::of::<T>()) {
+            self.archetype_access
+                .sparse_sets
+                .mutable
+                .contains(&TypeId::of::<T>())
+        } else {
+            self.archetype_access
+                .mutable
+                .contains(location.archetype as usize)
+        };
+        if can_write {
             // SAFE: RefMut does exclusivity checks and we have already validated the entity
             unsafe {
                 self.world
-                    .get_ref_mut_at_location_unchecked(location)
+                    .get_ref_mut_at_location_unchecked(location, self.change_tick)
                     .map_err(QueryError::ComponentError)
             }
         } else {
@@ -109,6 +148,42 @@ impl<'a, Q: HecsQuery> Query<'a, Q> {
         }
     }
 
+    /// Gets mutable references to the given entities' components of the given type at once. This will
+    /// fail if an entity is given more than once, if an entity does not have the given component type
+    /// or if the given component type does not match this query.
+    pub fn get_many_mut<T: Component, const N: usize>(
+        &self,
+        entities: [Entity; N],
+    ) -> Result<RefMutMany<'_, T, N>, QueryError> {
+        let sparse = self.world.sparse_sets().contains(ComponentId::of::<T>());
+        for entity in entities.iter() {
+            let location = match self.world.get_entity_location(*entity) {
+                None => return Err(QueryError::ComponentError(ComponentError::NoSuchEntity)),
+                Some(location) => location,
+            };
+            let can_write = if sparse {
+                self.archetype_access
+                    .sparse_sets
+                    .mutable
+                    .contains(&TypeId::of::<T>())
+            } else {
+                self.archetype_access
+                    .mutable
+                    .contains(location.archetype as usize)
+            };
+            if !can_write {
+                return Err(QueryError::CannotWriteArchetype);
+            }
+        }
+
+        // SAFE: RefMutMany does exclusivity checks and we have already validated the entities
+        unsafe {
+            self.world
+                .get_many_ref_mut_unchecked(entities, self.change_tick)
+                .map_err(QueryError::ComponentError)
+        }
+    }
+
     pub fn removed<C: Component>(&self) -> &[Entity] {
         self.world.removed::<C>()
     }
@@ -127,8 +202,12 @@ impl<'a, Q: HecsQuery> Query<'a, Q> {
 /// Note that borrows are not released until this object is dropped.
 pub struct QueryBorrowChecked<'w, Q: HecsQuery> {
     archetypes: &'w [Archetype],
+    sparse_sets: &'w SparseSets,
+    matched_archetypes: &'w [usize],
     archetype_access: &'w ArchetypeAccess,
     borrowed: bool,
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
@@ -136,19 +215,34 @@ impl<'w, Q: HecsQuery> fmt::Debug for QueryBorrowChecked<'w, Q> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("QueryBorrowChecked")
             .field("archetypes", &self.archetypes)
+            .field("sparse_sets", self.sparse_sets)
+            .field("matched_archetypes", &self.matched_archetypes)
             .field("archetype_access", self.archetype_access)
             .field("borrowed", &self.borrowed)
+            .field("last_change_tick", &self.last_change_tick)
+            .field("change_tick", &self.change_tick)
             .field("_marker", &self._marker)
             .finish()
     }
 }
 
 impl<'w, Q: HecsQuery> QueryBorrowChecked<'w, Q> {
-    pub(crate) fn new(archetypes: &'w [Archetype], archetype_access: &'w ArchetypeAccess) -> Self {
+    pub(crate) fn new(
+        archetypes: &'w [Archetype],
+        sparse_sets: &'w SparseSets,
+        matched_archetypes: &'w [usize],
+        archetype_access: &'w ArchetypeAccess,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetypes,
+            sparse_sets,
+            matched_archetypes,
             borrowed: false,
             archetype_access,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData,
         }
     }
@@ -200,9 +294,17 @@ impl<'w, Q: HecsQuery> QueryBorrowChecked<'w, Q> {
         for index in self.archetype_access.accessed.ones() {
             Q::Fetch::borrow(&self.archetypes[index]);
         }
+        Q::Fetch::borrow_sparse(self.sparse_sets);
 
         self.borrowed = true;
     }
+
+    /// The `index`th archetype matching the query
+    fn archetype(&self, index: usize) -> Option<&'w Archetype> {
+        self.matched_archetypes
+            .get(index)
+            .map(|&index| &self.archetypes[index])
+    }
 }
 
 unsafe impl<'w, Q: HecsQuery> Send for QueryBorrowChecked<'w, Q> {}
@@ -215,6 +317,7 @@ impl<'w, Q: HecsQuery> Drop for QueryBorrowChecked<'w, Q> {
             for index in self.archetype_access.accessed.ones() {
                 Q::Fetch::release(&self.archetypes[index]);
             }
+            Q::Fetch::release_sparse(self.sparse_sets);
         }
     }
 }
@@ -247,15 +350,20 @@ impl<'q, 'w, Q: HecsQuery> Iterator for QueryIter<'q, 'w, Q> {
         loop {
             match unsafe { self.iter.next() } {
                 None => {
-                    let archetype = self.borrow.archetypes.get(self.archetype_index)?;
+                    let archetype = self.borrow.archetype(self.archetype_index)?;
                     self.archetype_index += 1;
                     unsafe {
-                        self.iter = Q::Fetch::get(archetype, 0).map_or(ChunkIter::EMPTY, |fetch| {
-                            ChunkIter {
-                                fetch,
-                                len: archetype.len(),
-                                position: 0,
-                            }
+                        self.iter = Q::Fetch::get(
+                            archetype,
+                            self.borrow.sparse_sets,
+                            0,
+                            self.borrow.last_change_tick,
+                            self.borrow.change_tick,
+                        )
+                        .map_or(ChunkIter::EMPTY, |fetch| ChunkIter {
+                            fetch,
+                            len: archetype.len(),
+                            position: 0,
                         });
                     }
                 }
@@ -273,10 +381,9 @@ impl<'q, 'w, Q: HecsQuery> Iterator for QueryIter<'q, 'w, Q> {
 impl<'q, 'w, Q: HecsQuery> ExactSizeIterator for QueryIter<'q, 'w, Q> {
     fn len(&self) -> usize {
         self.borrow
-            .archetypes
+            .matched_archetypes
             .iter()
-            .filter(|&x| Q::Fetch::access(x).is_some())
-            .map(|x| x.len())
+            .map(|&index| self.borrow.archetypes[index].len())
             .sum()
     }
 }
@@ -325,14 +432,22 @@ impl<'q, 'w, Q: HecsQuery> ParallelIterator<Batch<'q, Q>> for ParIter<'q, 'w, Q>
 
     fn next_batch(&mut self) -> Option<Batch<'q, Q>> {
         loop {
-            let archetype = self.borrow.archetypes.get(self.archetype_index)?;
+            let archetype = self.borrow.archetype(self.archetype_index)?;
             let offset = self.batch_size * self.batch;
             if offset >= archetype.len() {
                 self.archetype_index += 1;
                 self.batch = 0;
                 continue;
             }
-            if let Some(fetch) = unsafe { Q::Fetch::get(archetype, offset as usize) } {
+            if let Some(fetch) = unsafe {
+                Q::Fetch::get(
+                    archetype,
+                    self.borrow.sparse_sets,
+                    offset as usize,
+                    self.borrow.last_change_tick,
+                    self.borrow.change_tick,
+                )
+            } {
                 self.batch += 1;
                 return Some(Batch {
                     _marker: PhantomData,
@@ -375,8 +490,11 @@ unsafe impl<'q, Q: HecsQuery> Send for Batch<'q, Q> {}
 #[derive(Debug)]
 pub struct QueryOneChecked<'a, Q: HecsQuery> {
     archetype: &'a Archetype,
+    sparse_sets: &'a SparseSets,
     index: usize,
     borrowed: bool,
+    last_change_tick: u32,
+    change_tick: u32,
     _marker: PhantomData<Q>,
 }
 
@@ -386,11 +504,20 @@ impl<'a, Q: HecsQuery> QueryOneChecked<'a, Q> {
     /// # Safety
     ///
     /// `index` must be in-bounds for `archetype`
-    pub(crate) unsafe fn new(archetype: &'a Archetype, index: usize) -> Self {
+    pub(crate) unsafe fn new(
+        archetype: &'a Archetype,
+        sparse_sets: &'a SparseSets,
+        index: usize,
+        last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self {
         Self {
             archetype,
+            sparse_sets,
             index,
             borrowed: false,
+            last_change_tick,
+            change_tick,
             _marker: PhantomData,
         }
     }
@@ -403,12 +530,19 @@ impl<'a, Q: HecsQuery> QueryOneChecked<'a, Q> {
     /// pre-existing borrow.
     pub fn get(&mut self) -> Option<<Q::Fetch as Fetch<'_>>::Item> {
         unsafe {
-            let fetch = Q::Fetch::get(self.archetype, self.index as usize)?;
-            self.borrowed = true;
+            let fetch = Q::Fetch::get(
+                self.archetype,
+                self.sparse_sets,
+                self.index as usize,
+                self.last_change_tick,
+                self.change_tick,
+            )?;
             if fetch.should_skip(0) {
                 None
             } else {
                 Q::Fetch::borrow(self.archetype);
+                Q::Fetch::borrow_sparse(self.sparse_sets);
+                self.borrowed = true;
                 Some(fetch.fetch(0))
             }
         }
@@ -432,8 +566,11 @@ impl<'a, Q: HecsQuery> QueryOneChecked<'a, Q> {
     fn transform<R: HecsQuery>(self) -> QueryOneChecked<'a, R> {
         QueryOneChecked {
             archetype: self.archetype,
+            sparse_sets: self.sparse_sets,
             index: self.index,
             borrowed: self.borrowed,
+            last_change_tick: self.last_change_tick,
+            change_tick: self.change_tick,
             _marker: PhantomData,
         }
     }
@@ -443,6 +580,7 @@ impl<Q: HecsQuery> Drop for QueryOneChecked<'_, Q> {
     fn drop(&mut self) {
         if self.borrowed {
             Q::Fetch::release(self.archetype);
+            Q::Fetch::release_sparse(self.sparse_sets);
         }
     }
 }
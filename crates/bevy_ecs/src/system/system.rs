@@ -1,5 +1,5 @@
 use crate::resource::Resources;
-use bevy_hecs::{Access, Query, World};
+use bevy_hecs::{check_tick, Access, Fetch, Query, World};
 use bevy_utils::HashSet;
 use fixedbitset::FixedBitSet;
 use std::{any::TypeId, borrow::Cow};
@@ -21,6 +21,34 @@ impl SystemId {
     }
 }
 
+/// The change ticks of a system run. A system sees the changes made after the `last_*` ticks, which
+/// are the ticks of its previous run, and records its own changes with the current ticks.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct SystemChangeTicks {
+    pub last_change_tick: u32,
+    pub change_tick: u32,
+    pub last_resource_change_tick: u32,
+    pub resource_change_tick: u32,
+}
+
+impl SystemChangeTicks {
+    /// Returns the ticks for the next run of a system that last ran with `self`
+    pub fn next_run(&self, world: &World, resources: &Resources) -> Self {
+        let mut ticks = SystemChangeTicks {
+            last_change_tick: self.change_tick,
+            change_tick: world.increment_change_tick(),
+            last_resource_change_tick: self.resource_change_tick,
+            resource_change_tick: resources.increment_change_tick(),
+        };
+        check_tick(&mut ticks.last_change_tick, ticks.change_tick);
+        check_tick(
+            &mut ticks.last_resource_change_tick,
+            ticks.resource_change_tick,
+        );
+        ticks
+    }
+}
+
 /// An ECS system that can be added to a [Schedule](crate::Schedule)
 pub trait System: Send + Sync {
     fn name(&self) -> Cow<'static, str>;
@@ -34,35 +62,61 @@ pub trait System: Send + Sync {
     fn initialize(&mut self, _world: &mut World, _resources: &mut Resources) {}
 }
 
-/// Provides information about the archetypes a [System] reads and writes
-#[derive(Debug, Default)]
+/// Provides information about the archetypes a [System] reads and writes. Components stored in
+/// sparse sets aren't part of any archetype, so their access is tracked by type.
+#[derive(Debug, Default, Clone)]
 pub struct ArchetypeAccess {
     pub accessed: FixedBitSet, // union of both immutable and mutable
     pub mutable: FixedBitSet,
+    pub sparse_sets: TypeAccess,
 }
 
 // credit to Ratysz from the Yaks codebase
 impl ArchetypeAccess {
     pub fn is_compatible(&self, other: &ArchetypeAccess) -> bool {
-        self.mutable.is_disjoint(&other.accessed) && self.accessed.is_disjoint(&other.mutable)
+        self.mutable.is_disjoint(&other.accessed)
+            && self.accessed.is_disjoint(&other.mutable)
+            && self.sparse_sets.is_compatible(&other.sparse_sets)
     }
 
     pub fn union(&mut self, other: &ArchetypeAccess) {
         self.mutable.union_with(&other.mutable);
         self.accessed.union_with(&other.accessed);
+        self.sparse_sets.union(&other.sparse_sets);
     }
 
     pub fn set_access_for_query<Q>(&mut self, world: &World)
     where
         Q: Query,
     {
-        let iterator = world.archetypes();
-        let bits = iterator.len();
+        self.set_access::<Q>(world, 0..world.archetypes().len());
+    }
+
+    /// Like `set_access_for_query`, but only checks the archetypes at `matched_archetypes`
+    pub fn set_access_for_matched_archetypes<Q>(
+        &mut self,
+        world: &World,
+        matched_archetypes: &[usize],
+    ) where
+        Q: Query,
+    {
+        self.set_access::<Q>(world, matched_archetypes.iter().copied());
+    }
+
+    fn set_access<Q>(&mut self, world: &World, archetypes: impl Iterator<Item = usize>)
+    where
+        Q: Query,
+    {
+        let bits = world.archetypes().len();
         self.accessed.grow(bits);
         self.mutable.grow(bits);
-        iterator
-            .enumerate()
-            .filter_map(|(index, archetype)| archetype.access::<Q>().map(|access| (index, access)))
+        let sparse_sets = world.sparse_sets();
+        archetypes
+            .filter_map(|index| {
+                world.archetypes[index]
+                    .access::<Q>(sparse_sets)
+                    .map(|access| (index, access))
+            })
             .for_each(|(archetype, access)| match access {
                 Access::Read => self.accessed.set(archetype, true),
                 Access::Write => {
@@ -71,11 +125,28 @@ impl ArchetypeAccess {
                 }
                 Access::Iterate => (),
             });
+
+        let sparse_set_access = &mut self.sparse_sets;
+        Q::Fetch::component_access(&mut |ty, access| {
+            if !sparse_sets.contains(ty.into()) {
+                return;
+            }
+            match access {
+                Access::Read => {
+                    sparse_set_access.immutable.insert(ty);
+                }
+                Access::Write => {
+                    sparse_set_access.mutable.insert(ty);
+                }
+                Access::Iterate => (),
+            }
+        });
     }
 
     pub fn clear(&mut self) {
         self.accessed.clear();
         self.mutable.clear();
+        self.sparse_sets.clear();
     }
 }
 
@@ -108,7 +179,7 @@ impl TypeAccess {
 mod tests {
     use super::{ArchetypeAccess, TypeAccess};
     use crate::resource::{FetchResource, Res, ResMut, ResourceQuery};
-    use bevy_hecs::World;
+    use bevy_hecs::{StorageType, With, World};
     use std::any::TypeId;
 
     struct A;
@@ -141,6 +212,29 @@ mod tests {
         assert!(access.accessed.contains(e3_archetype));
     }
 
+    #[test]
+    fn sparse_set_access() {
+        let mut world = World::default();
+        world.register_component::<C>(StorageType::SparseSet);
+        world.spawn((A, C));
+        world.spawn((B,));
+
+        let mut write_c = ArchetypeAccess::default();
+        write_c.set_access_for_query::<(&A, &mut C)>(&world);
+        let mut read_c = ArchetypeAccess::default();
+        read_c.set_access_for_query::<(&B, &C)>(&world);
+        let mut read_a = ArchetypeAccess::default();
+        read_a.set_access_for_query::<&A>(&world);
+        let mut with_c = ArchetypeAccess::default();
+        with_c.set_access_for_query::<With<C, &B>>(&world);
+
+        assert!(write_c.sparse_sets.mutable.contains(&TypeId::of::<C>()));
+        assert!(!write_c.is_compatible(&read_c));
+        assert!(write_c.is_compatible(&with_c));
+        assert!(read_a.is_compatible(&read_c));
+        assert!(read_a.is_compatible(&with_c));
+    }
+
     #[test]
     fn resource_query_access() {
         let access =
@@ -0,0 +1,276 @@
+use crate::{
+    resource::{
+        ChangedRes, FetchResource, FetchResourceChanged, FetchResourceLocalMut, FetchResourceRead,
+        FetchResourceWrite, FromResources, Local, Res, ResMut, Resource, ResourceQuery, Resources,
+    },
+    system::{ArchetypeAccess, Commands, Query, SystemChangeTicks, SystemId, TypeAccess},
+};
+use bevy_hecs::{MatchedArchetypes, Query as HecsQuery, World};
+use std::marker::PhantomData;
+
+/// A group of parameters that a query system can take after its queries
+///
+/// Implement it with `#[derive(SystemParam)]` on a struct whose fields are [Res], [ResMut],
+/// [ChangedRes], [Local], [Query], [Commands] or other `SystemParam`s. The resources and archetypes
+/// accessed by the fields are added to the accesses of the system, so the executor schedules it as
+/// if it took the fields as separate parameters. The struct can have at most one lifetime, which
+/// is the lifetime of the fetched parameters.
+///
+/// A system can only take one `SystemParam`, as its last parameter. To use more of them, nest
+/// them in a single group.
+///
+/// # Example
+/// ```
+/// use bevy_ecs::prelude::*;
+///
+/// struct Score(usize);
+/// struct Player;
+///
+/// #[derive(SystemParam)]
+/// struct Scoring<'a> {
+///     score: ResMut<'a, Score>,
+///     players: Query<'a, &'a Player>,
+/// }
+///
+/// fn count_players(mut scoring: Scoring) {
+///     scoring.score.0 = scoring.players.iter().iter().count();
+/// }
+///
+/// let system = count_players.system();
+/// ```
+///
+/// The parameters only live as long as the system runs, so they can't be taken with a longer
+/// lifetime:
+/// ```compile_fail
+/// use bevy_ecs::prelude::*;
+///
+/// struct Score(usize);
+///
+/// #[derive(SystemParam)]
+/// struct Scoring<'a> {
+///     score: Res<'a, Score>,
+/// }
+///
+/// fn leak_score(scoring: Scoring<'static>) {}
+///
+/// let system = leak_score.system();
+/// ```
+pub trait SystemParam: FetchSystemParam {}
+
+/// A parameter of a system that can be a field of a [SystemParam]
+pub trait FetchSystemParam {
+    type Fetch: for<'a> SystemParamFetch<'a>;
+}
+
+/// Fetches a [FetchSystemParam] for the lifetime `'a` of a system run
+pub trait SystemParamFetch<'a> {
+    /// The fetched parameter
+    type Item;
+
+    /// Adds the resources and queries accessed by the parameter to the state of a new system
+    fn init(state: &mut SystemParamState);
+
+    /// Prepares the resources of the parameter when the system is initialized
+    fn initialize(_state: &SystemParamState, _world: &mut World, _resources: &mut Resources) {}
+
+    /// Fetches the parameter, or returns `None` if the system should be skipped, like when a
+    /// [ChangedRes] didn't change. `query_index` is the index of the first query of the parameter
+    /// in `state`, and is advanced past them.
+    ///
+    /// # Safety
+    /// The accesses of the parameter must not conflict with the systems that are running
+    unsafe fn get_param(
+        state: &'a SystemParamState,
+        query_index: &mut usize,
+        world: &'a World,
+        resources: &'a Resources,
+    ) -> Option<Self::Item>;
+}
+
+/// Stands for a [SystemParam] in the queries of a system function, where it couldn't be told apart
+/// from a query otherwise
+pub struct ParamGroup<P>(PhantomData<P>);
+
+/// The resources and queries of the [SystemParam]s of a system
+pub struct SystemParamState {
+    system_id: SystemId,
+    commands: Commands,
+    resource_access: TypeAccess,
+    queries: Vec<ParamQuery>,
+    pub(crate) change_ticks: SystemChangeTicks,
+}
+
+struct ParamQuery {
+    archetype_access: ArchetypeAccess,
+    matched_archetypes: MatchedArchetypes,
+    update: fn(&World, &mut MatchedArchetypes, &mut ArchetypeAccess),
+}
+
+impl SystemParamState {
+    pub(crate) fn new(
+        system_id: SystemId,
+        commands: Commands,
+        resource_access: TypeAccess,
+    ) -> Self {
+        Self {
+            system_id,
+            commands,
+            resource_access,
+            queries: Vec::new(),
+            change_ticks: SystemChangeTicks::default(),
+        }
+    }
+
+    pub fn system_id(&self) -> SystemId {
+        self.system_id
+    }
+
+    /// The resources accessed by the system, including the ones of its parameters
+    pub fn resource_access(&self) -> &TypeAccess {
+        &self.resource_access
+    }
+
+    /// Adds resources accessed by a parameter to the accesses of the system
+    pub fn add_resource_access(&mut self, access: &TypeAccess) {
+        self.resource_access.union(access);
+    }
+
+    /// Adds a query of a parameter, which can be fetched with [SystemParamState::query]
+    pub fn add_query<Q: HecsQuery>(&mut self) {
+        self.queries.push(ParamQuery {
+            archetype_access: ArchetypeAccess::default(),
+            matched_archetypes: MatchedArchetypes::default(),
+            update: update_query_access::<Q>,
+        });
+    }
+
+    /// Creates the query at `query_index`, which must have been added with the same `Q`
+    ///
+    /// # Safety
+    /// The accesses of the query must not conflict with the systems that are running
+    pub unsafe fn query<'a, Q: HecsQuery>(
+        &'a self,
+        query_index: usize,
+        world: &'a World,
+    ) -> Query<'a, Q> {
+        let query = &self.queries[query_index];
+        Query::new(
+            world,
+            &query.archetype_access,
+            query.matched_archetypes.indices(),
+            self.change_ticks.last_change_tick,
+            self.change_ticks.change_tick,
+        )
+    }
+
+    pub(crate) fn update_archetype_access(
+        &mut self,
+        world: &World,
+        archetype_access: &mut ArchetypeAccess,
+    ) {
+        for query in self.queries.iter_mut() {
+            (query.update)(
+                world,
+                &mut query.matched_archetypes,
+                &mut query.archetype_access,
+            );
+            archetype_access.union(&query.archetype_access);
+        }
+    }
+}
+
+fn update_query_access<Q: HecsQuery>(
+    world: &World,
+    matched_archetypes: &mut MatchedArchetypes,
+    archetype_access: &mut ArchetypeAccess,
+) {
+    archetype_access.clear();
+    matched_archetypes.update::<Q>(world);
+    archetype_access.set_access_for_matched_archetypes::<Q>(world, matched_archetypes.indices());
+}
+
+macro_rules! impl_system_param_for_resource {
+    ($resource: ident, $fetch: ident, $($bounds: tt)*) => {
+        impl<'a, T: $($bounds)*> FetchSystemParam for $resource<'a, T> {
+            type Fetch = $fetch<T>;
+        }
+
+        impl<'a, T: $($bounds)*> SystemParamFetch<'a> for $fetch<T> {
+            type Item = $resource<'a, T>;
+
+            fn init(state: &mut SystemParamState) {
+                state.add_resource_access(&<Self as FetchResource>::access());
+            }
+
+            fn initialize(state: &SystemParamState, _world: &mut World, resources: &mut Resources) {
+                <$resource<T> as ResourceQuery>::initialize(resources, Some(state.system_id));
+            }
+
+            unsafe fn get_param(
+                state: &'a SystemParamState,
+                _query_index: &mut usize,
+                _world: &'a World,
+                resources: &'a Resources,
+            ) -> Option<Self::Item> {
+                resources.query_system::<$resource<T>>(
+                    state.system_id,
+                    state.change_ticks.last_resource_change_tick,
+                    state.change_ticks.resource_change_tick,
+                )
+            }
+        }
+    };
+}
+
+impl_system_param_for_resource!(Res, FetchResourceRead, Resource);
+impl_system_param_for_resource!(ResMut, FetchResourceWrite, Resource);
+impl_system_param_for_resource!(ChangedRes, FetchResourceChanged, Resource);
+impl_system_param_for_resource!(Local, FetchResourceLocalMut, Resource + FromResources);
+
+/// Fetches a [Query] parameter
+pub struct FetchQuery<Q>(PhantomData<Q>);
+
+impl<'a, Q: HecsQuery> FetchSystemParam for Query<'a, Q> {
+    type Fetch = FetchQuery<Q>;
+}
+
+impl<'a, Q: HecsQuery> SystemParamFetch<'a> for FetchQuery<Q> {
+    type Item = Query<'a, Q>;
+
+    fn init(state: &mut SystemParamState) {
+        state.add_query::<Q>();
+    }
+
+    unsafe fn get_param(
+        state: &'a SystemParamState,
+        query_index: &mut usize,
+        world: &'a World,
+        _resources: &'a Resources,
+    ) -> Option<Self::Item> {
+        let query = state.query::<Q>(*query_index, world);
+        *query_index += 1;
+        Some(query)
+    }
+}
+
+/// Fetches a [Commands] parameter
+pub struct FetchCommands;
+
+impl FetchSystemParam for Commands {
+    type Fetch = FetchCommands;
+}
+
+impl<'a> SystemParamFetch<'a> for FetchCommands {
+    type Item = Commands;
+
+    fn init(_state: &mut SystemParamState) {}
+
+    unsafe fn get_param(
+        state: &'a SystemParamState,
+        _query_index: &mut usize,
+        _world: &'a World,
+        _resources: &'a Resources,
+    ) -> Option<Self::Item> {
+        Some(state.commands.clone())
+    }
+}
@@ -1,5 +1,5 @@
-mod entity_map;
+mod relation;
 mod world_builder;
 
-pub use entity_map::*;
+pub use relation::*;
 pub use world_builder::*;
@@ -0,0 +1,325 @@
+use crate::resource::{FromResources, Resources};
+use bevy_hecs::{Entity, EntityMap, MapEntities, MapEntitiesError, World};
+use std::{fmt, marker::PhantomData};
+
+/// A relationship of kind `K` from the entity that has this component to another entity, its target
+///
+/// `K` is a marker type that names the relationship, like `struct Holds;` or `struct Targets;`.
+/// Once the kind is registered in a world with [Relation::register], the target of every relation
+/// has a [RelationSources] component listing the entities related to it. It is kept up to date as
+/// relations are inserted, replaced and removed, directly or with [Commands](crate::Commands).
+/// Despawning the target of relations removes them from their sources.
+///
+/// To spawn relations with scenes, register them with
+/// `register_component_with::<Relation<K>>(|registration| registration.map_entities())`. The
+/// relations a scene spawns are indexed by [Relation::maintenance_system] once their targets are
+/// mapped to the spawned entities.
+///
+/// # Example
+/// ```
+/// use bevy_ecs::prelude::*;
+///
+/// struct Holds;
+///
+/// let mut world = World::new();
+/// Relation::<Holds>::register(&mut world);
+/// let player = world.spawn(());
+/// let sword = world.spawn((Relation::<Holds>::new(player),));
+/// let sources = world.get::<RelationSources<Holds>>(player).unwrap();
+/// assert_eq!(sources.iter().collect::<Vec<_>>(), vec![sword]);
+///
+/// world.despawn(player).unwrap();
+/// assert!(world.get::<Relation<Holds>>(sword).is_err());
+/// ```
+pub struct Relation<K> {
+    /// Changing the target directly doesn't run the hooks added with [Relation::register], so the
+    /// [RelationSources] are out of date until [Relation::maintenance_system] runs
+    pub target: Entity,
+    /// Whether `target` is an entity of the world the relation is in. Relations made with
+    /// [FromResources] are filled in from a scene, and point into the scene until mapped.
+    mapped: bool,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<K> Relation<K> {
+    pub fn new(target: Entity) -> Self {
+        Self {
+            target,
+            mapped: true,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K: 'static> Relation<K> {
+    /// Registers the hooks that maintain the [RelationSources] of relations of kind `K` in `world`,
+    /// and lets [World::merge] remap relations of kind `K`
+    pub fn register(world: &mut World) {
+        world.on_insert::<Relation<K>>(Self::update_index);
+        world.on_replace::<Relation<K>>(Self::update_index);
+        world.on_remove::<Relation<K>>(Self::remove_from_index);
+        world.on_remove::<RelationSources<K>>(Self::remove_relations_to);
+        world.register_map_entities::<Relation<K>>();
+        world.register_map_entities::<RelationSources<K>>();
+        world.register_map_entities::<IndexedRelation<K>>();
+    }
+
+    /// Brings the [RelationSources] of relations of kind `K` up to date after relations were
+    /// retargeted without running hooks, like [MapEntities] does when a scene is spawned
+    pub fn maintenance_system(world: &mut World, _resources: &mut Resources) {
+        let outdated = world
+            .query::<(Entity, &Relation<K>, Option<&IndexedRelation<K>>)>()
+            .iter()
+            .filter(|(_, relation, indexed)| {
+                relation.mapped && indexed.map(|indexed| indexed.target) != Some(relation.target)
+            })
+            .map(|(source, _, _)| source)
+            .collect::<Vec<_>>();
+        for source in outdated {
+            Self::update_index(world, source);
+        }
+    }
+
+    fn update_index(world: &mut World, source: Entity) {
+        let target = match world.get::<Relation<K>>(source) {
+            Ok(relation) if relation.mapped => relation.target,
+            Ok(_) => {
+                // the target is an entity of a scene until the scene's entities are mapped
+                Self::remove_from_index(world, source);
+                return;
+            }
+            Err(_) => return,
+        };
+        let indexed = world
+            .get::<IndexedRelation<K>>(source)
+            .ok()
+            .map(|indexed| indexed.target);
+        if indexed == Some(target) {
+            return;
+        }
+        if let Some(indexed) = indexed {
+            Self::unindex(world, source, indexed);
+        }
+
+        let is_indexed = if world.get::<RelationSources<K>>(target).is_ok() {
+            let mut sources = world.get_mut::<RelationSources<K>>(target).unwrap();
+            if !sources.contains(source) {
+                sources.sources.push(source);
+            }
+            true
+        } else {
+            // fails if the target doesn't exist (yet)
+            world
+                .insert_one(target, RelationSources::<K>::new(vec![source]))
+                .is_ok()
+        };
+        if is_indexed {
+            world
+                .insert_one(source, IndexedRelation::<K>::new(target))
+                .unwrap();
+        } else if indexed.is_some() {
+            world.remove_one::<IndexedRelation<K>>(source).unwrap();
+        }
+    }
+
+    fn remove_from_index(world: &mut World, source: Entity) {
+        if let Ok(indexed) = world.get::<IndexedRelation<K>>(source) {
+            let target = indexed.target;
+            Self::unindex(world, source, target);
+            world.remove_one::<IndexedRelation<K>>(source).unwrap();
+        }
+    }
+
+    fn unindex(world: &mut World, source: Entity, target: Entity) {
+        if let Ok(mut sources) = world.get_mut::<RelationSources<K>>(target) {
+            sources.sources.retain(|&entity| entity != source);
+        }
+    }
+
+    fn remove_relations_to(world: &mut World, target: Entity) {
+        let sources = match world.get::<RelationSources<K>>(target) {
+            Ok(sources) => sources.sources.clone(),
+            Err(_) => return,
+        };
+        for source in sources {
+            let _ = world.remove_one::<Relation<K>>(source);
+        }
+    }
+}
+
+impl<K> MapEntities for Relation<K> {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.target = entity_map.get(self.target)?;
+        self.mapped = true;
+        Ok(())
+    }
+}
+
+impl<K> FromResources for Relation<K> {
+    fn from_resources(_resources: &Resources) -> Self {
+        Self {
+            target: Entity::new(u32::MAX),
+            mapped: false,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K> Clone for Relation<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> Copy for Relation<K> {}
+
+impl<K> fmt::Debug for Relation<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Relation").field(&self.target).finish()
+    }
+}
+
+/// The entities with a [Relation] of kind `K` to this entity
+///
+/// Maintained by the hooks added with [Relation::register]. Removing this component from an
+/// entity, for example by despawning it, removes the relations to it.
+pub struct RelationSources<K> {
+    sources: Vec<Entity>,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<K> RelationSources<K> {
+    fn new(sources: Vec<Entity>) -> Self {
+        Self {
+            sources,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.sources.iter().copied()
+    }
+
+    pub fn contains(&self, source: Entity) -> bool {
+        self.sources.contains(&source)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+}
+
+impl<K> MapEntities for RelationSources<K> {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        for source in self.sources.iter_mut() {
+            *source = entity_map.get(*source)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K> fmt::Debug for RelationSources<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RelationSources")
+            .field(&self.sources)
+            .finish()
+    }
+}
+
+/// The target whose [RelationSources] list the source of a relation, if it differs from the
+/// relation's current target
+struct IndexedRelation<K> {
+    target: Entity,
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<K> IndexedRelation<K> {
+    fn new(target: Entity) -> Self {
+        Self {
+            target,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K> MapEntities for IndexedRelation<K> {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.target = entity_map.get(self.target)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Relation, RelationSources};
+    use crate::{resource::Resources, system::Commands};
+    use bevy_hecs::{Entity, World};
+
+    struct Holds;
+
+    fn sources(world: &World, target: Entity) -> Vec<Entity> {
+        world
+            .get::<RelationSources<Holds>>(target)
+            .map(|sources| sources.iter().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn relation_sources_stay_consistent() {
+        let mut world = World::new();
+        let mut resources = Resources::default();
+        Relation::<Holds>::register(&mut world);
+        let (a, b) = (world.spawn(()), world.spawn(()));
+        let x = world.spawn((Relation::<Holds>::new(a),));
+        let y = world.spawn((Relation::<Holds>::new(a),));
+        assert_eq!(sources(&world, a), vec![x, y]);
+
+        world.insert_one(x, Relation::<Holds>::new(b)).unwrap();
+        assert_eq!(sources(&world, a), vec![y]);
+        assert_eq!(sources(&world, b), vec![x]);
+
+        world.despawn(y).unwrap();
+        assert!(sources(&world, a).is_empty());
+
+        let mut commands = Commands::default();
+        commands.set_entity_reserver(world.get_entity_reserver());
+        commands.spawn((Relation::<Holds>::new(a),));
+        let z = commands.current_entity().unwrap();
+        commands.insert_one(x, Relation::<Holds>::new(a));
+        commands.apply(&mut world, &mut resources);
+        assert_eq!(sources(&world, a), vec![z, x]);
+        assert!(sources(&world, b).is_empty());
+
+        commands.despawn(x);
+        commands.apply(&mut world, &mut resources);
+        assert_eq!(sources(&world, a), vec![z]);
+
+        world.insert_one(b, Relation::<Holds>::new(a)).unwrap();
+        world.despawn(a).unwrap();
+        assert!(world.get::<Relation<Holds>>(z).is_err());
+        assert!(world.get::<Relation<Holds>>(b).is_err());
+    }
+
+    #[test]
+    fn relations_are_remapped_when_merged() {
+        let mut level = World::new();
+        Relation::<Holds>::register(&mut level);
+        let a = level.spawn(());
+        let x = level.spawn((Relation::<Holds>::new(a),));
+
+        let mut world = World::new();
+        Relation::<Holds>::register(&mut world);
+        world.spawn(());
+        let entity_map = world.merge(&mut level).unwrap();
+        let (a, x) = (entity_map.get(a).unwrap(), entity_map.get(x).unwrap());
+        assert_eq!(world.get::<Relation<Holds>>(x).unwrap().target, a);
+        assert_eq!(sources(&world, a), vec![x]);
+
+        world.remove_one::<Relation<Holds>>(x).unwrap();
+        assert!(sources(&world, a).is_empty());
+    }
+}
@@ -1,7 +1,13 @@
-use crate::{impl_property, property_serde::Serializable, Property, PropertyTypeRegistry};
-use bevy_ecs::Entity;
+use crate::{
+    impl_property,
+    property_serde::{DynamicPropertiesDeserializer, MapSerializer, Serializable},
+    DeserializeProperty, DynamicProperties, Properties, Property, PropertyIter, PropertyType,
+    PropertyTypeRegistry,
+};
+use bevy_ecs::{Entity, Relation};
 use erased_serde::Deserializer;
-use serde::Deserialize;
+use serde::{de::DeserializeSeed, Deserialize};
+use std::any::Any;
 
 impl_property!(Entity, serialize_entity, deserialize_entity);
 
@@ -22,3 +28,123 @@ fn deserialize_entity(
     let entity = private::Entity::deserialize(deserializer)?;
     Ok(Box::new(Entity::new(entity.0)))
 }
+
+// what `#[derive(Properties)]` would generate for `Relation`, which can't derive it in bevy_ecs
+impl<K: 'static> Properties for Relation<K> {
+    fn prop(&self, name: &str) -> Option<&dyn Property> {
+        match name {
+            "target" => Some(&self.target),
+            _ => None,
+        }
+    }
+
+    fn prop_mut(&mut self, name: &str) -> Option<&mut dyn Property> {
+        match name {
+            "target" => Some(&mut self.target),
+            _ => None,
+        }
+    }
+
+    fn prop_with_index(&self, index: usize) -> Option<&dyn Property> {
+        match index {
+            0 => Some(&self.target),
+            _ => None,
+        }
+    }
+
+    fn prop_with_index_mut(&mut self, index: usize) -> Option<&mut dyn Property> {
+        match index {
+            0 => Some(&mut self.target),
+            _ => None,
+        }
+    }
+
+    fn prop_name(&self, index: usize) -> Option<&str> {
+        match index {
+            0 => Some("target"),
+            _ => None,
+        }
+    }
+
+    fn prop_len(&self) -> usize {
+        1
+    }
+
+    fn iter_props(&self) -> PropertyIter {
+        PropertyIter::new(self)
+    }
+}
+
+impl<K: 'static> DeserializeProperty for Relation<K> {
+    fn deserialize(
+        deserializer: &mut dyn Deserializer,
+        property_type_registry: &PropertyTypeRegistry,
+    ) -> Result<Box<dyn Property>, erased_serde::Error> {
+        let dynamic_properties_deserializer =
+            DynamicPropertiesDeserializer::new(property_type_registry);
+        let dynamic_properties: DynamicProperties =
+            dynamic_properties_deserializer.deserialize(deserializer)?;
+        Ok(Box::new(dynamic_properties))
+    }
+}
+
+impl<K: 'static> Property for Relation<K> {
+    #[inline]
+    fn type_name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    #[inline]
+    fn any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    #[inline]
+    fn clone_prop(&self) -> Box<dyn Property> {
+        Box::new(self.to_dynamic())
+    }
+
+    #[inline]
+    fn set(&mut self, value: &dyn Property) {
+        self.apply(value);
+    }
+
+    #[inline]
+    fn apply(&mut self, value: &dyn Property) {
+        if let Some(properties) = value.as_properties() {
+            if properties.property_type() != self.property_type() {
+                panic!(
+                    "Properties type mismatch. This type is {:?} but the applied type is {:?}",
+                    self.property_type(),
+                    properties.property_type()
+                );
+            }
+            for (i, prop) in properties.iter_props().enumerate() {
+                let name = properties.prop_name(i).unwrap();
+                if let Some(p) = self.prop_mut(name) {
+                    p.apply(prop);
+                }
+            }
+        } else {
+            panic!("attempted to apply non-Properties type to Properties type");
+        }
+    }
+
+    #[inline]
+    fn as_properties(&self) -> Option<&dyn Properties> {
+        Some(self)
+    }
+
+    fn serializable<'a>(&'a self, registry: &'a PropertyTypeRegistry) -> Serializable<'a> {
+        Serializable::Owned(Box::new(MapSerializer::new(self, registry)))
+    }
+
+    fn property_type(&self) -> PropertyType {
+        PropertyType::Map
+    }
+}
@@ -179,23 +179,26 @@ impl<'a> FetchResource<'a> for FetchDrawContext {
         resources.release::<SharedBuffers>();
     }
 
-    unsafe fn get(resources: &'a Resources, _system_id: Option<SystemId>) -> Self::Item {
+    unsafe fn get(
+        resources: &'a Resources,
+        _system_id: Option<SystemId>,
+        _last_change_tick: u32,
+        change_tick: u32,
+    ) -> Self::Item {
         let pipelines = {
-            let (value, type_state) = resources
-                .get_unsafe_ref_with_type_state::<Assets<PipelineDescriptor>>(
-                    ResourceIndex::Global,
-                );
-            ResMut::new(value, type_state.mutated())
+            let (value, component_ticks) = resources
+                .get_unsafe_ref_with_ticks::<Assets<PipelineDescriptor>>(ResourceIndex::Global);
+            ResMut::new(value, component_ticks, change_tick)
         };
         let shaders = {
-            let (value, type_state) =
-                resources.get_unsafe_ref_with_type_state::<Assets<Shader>>(ResourceIndex::Global);
-            ResMut::new(value, type_state.mutated())
+            let (value, component_ticks) =
+                resources.get_unsafe_ref_with_ticks::<Assets<Shader>>(ResourceIndex::Global);
+            ResMut::new(value, component_ticks, change_tick)
         };
         let pipeline_compiler = {
-            let (value, type_state) =
-                resources.get_unsafe_ref_with_type_state::<PipelineCompiler>(ResourceIndex::Global);
-            ResMut::new(value, type_state.mutated())
+            let (value, component_ticks) =
+                resources.get_unsafe_ref_with_ticks::<PipelineCompiler>(ResourceIndex::Global);
+            ResMut::new(value, component_ticks, change_tick)
         };
 
         DrawContext {
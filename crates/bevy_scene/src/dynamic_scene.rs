@@ -40,7 +40,10 @@ impl DynamicScene {
                     })
                 }
                 for type_info in archetype.types() {
-                    if let Some(component_registration) = component_registry.get(&type_info.id()) {
+                    if let Some(component_registration) = type_info
+                        .type_id()
+                        .and_then(|ty| component_registry.get(&ty))
+                    {
                         let properties =
                             component_registration.get_component_properties(&archetype, index);
 
@@ -161,7 +161,10 @@ impl SceneSpawner {
                     .entry(*scene_entity)
                     .or_insert_with(|| world.reserve_entity());
                 for type_info in archetype.types() {
-                    if let Some(component_registration) = component_registry.get(&type_info.id()) {
+                    if let Some(component_registration) = type_info
+                        .type_id()
+                        .and_then(|ty| component_registry.get(&ty))
+                    {
                         component_registration.component_copy(
                             &scene.world,
                             world,
@@ -274,3 +277,63 @@ pub fn scene_spawner_system(world: &mut World, resources: &mut Resources) {
         .update_spawned_scenes(world, resources, &updated_spawned_scenes)
         .unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Scene, ScenePlugin, SceneSpawner};
+    use bevy_app::App;
+    use bevy_asset::{AssetPlugin, Assets};
+    use bevy_ecs::{Entity, Relation, RelationSources, World};
+    use bevy_tasks::{IoTaskPool, TaskPool};
+    use bevy_type_registry::{RegisterType, TypeRegistryPlugin};
+
+    struct Holds;
+
+    fn sources(world: &World, target: Entity) -> Vec<Entity> {
+        world
+            .get::<RelationSources<Holds>>(target)
+            .map(|sources| sources.iter().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn spawned_relations_are_indexed_once_mapped() {
+        let mut app = App::build();
+        app.add_resource(IoTaskPool(TaskPool::new()))
+            .add_plugin(TypeRegistryPlugin)
+            .add_plugin(AssetPlugin)
+            .add_plugin(ScenePlugin)
+            .register_component_with::<Relation<Holds>>(|registration| registration.map_entities());
+        let App {
+            world, resources, ..
+        } = &mut app.app;
+        Relation::<Holds>::register(world);
+
+        let mut scene_world = World::new();
+        let player = scene_world.spawn(());
+        scene_world.spawn((Relation::<Holds>::new(player),));
+        let scene = resources
+            .get_mut::<Assets<Scene>>()
+            .unwrap()
+            .add(Scene::new(scene_world));
+
+        // has the id of the player in the scene
+        let bystander = world.spawn(());
+        assert_eq!(bystander, player);
+        let mut scene_spawner = SceneSpawner::default();
+        scene_spawner.spawn_sync(world, resources, scene).unwrap();
+        assert!(sources(world, bystander).is_empty());
+
+        Relation::<Holds>::maintenance_system(world, resources);
+        let (player, sword) = world
+            .query::<(Entity, &Relation<Holds>)>()
+            .iter()
+            .map(|(sword, relation)| (relation.target, sword))
+            .next()
+            .unwrap();
+        assert_ne!(player, bystander);
+        assert_ne!(sword, bystander);
+        assert_eq!(sources(world, player), vec![sword]);
+        assert!(sources(world, bystander).is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+use bevy_ecs::{Column, ColumnWriter, ComponentId, ComponentsSnapshot, Resources, TypeInfo, World};
+use bevy_type_registry::{ComponentRegistration, ComponentRegistry, TypeRegistry};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WorldSnapshotError {
+    #[error("Snapshot contains a component that is no longer registered.")]
+    UnregisteredComponent { id: ComponentId },
+}
+
+/// A copy of the entities and registered components of a [World], which can be restored later,
+/// for example to roll back a simulation
+///
+/// Components are copied with the [ComponentRegistry], so only registered component types are
+/// saved. The types that can't be copied are reported by [WorldSnapshot::uncloned_components].
+/// Components registered with [cloneable](bevy_type_registry::ComponentRegistrationBuilder::cloneable)
+/// are copied with [Clone], the others through their properties. Both taking and restoring a
+/// snapshot copy the components one column at a time, straight into their storage.
+///
+/// Restoring a snapshot brings back its entities with the same IDs and generations, so entities
+/// stored in components stay valid.
+#[derive(Debug)]
+pub struct WorldSnapshot {
+    components: ComponentsSnapshot,
+    uncloned_components: Vec<ComponentId>,
+}
+
+impl WorldSnapshot {
+    /// Saves the entities of `world` and all of their registered components
+    pub fn from_world(world: &World, resources: &Resources) -> Self {
+        Self::from_world_filtered(world, resources, |_| true)
+    }
+
+    /// Saves the entities of `world` and the components whose registration passes `filter`.
+    /// Components that don't pass are left out without being reported.
+    pub fn from_world_filtered(
+        world: &World,
+        resources: &Resources,
+        filter: impl Fn(&ComponentRegistration) -> bool,
+    ) -> Self {
+        let type_registry = resources.get::<TypeRegistry>().unwrap();
+        let component_registry = type_registry.component.read();
+        let mut uncloned_components = Vec::new();
+        let components = world.snapshot_components(
+            |type_info| match registration(&component_registry, type_info) {
+                Some(registration) => filter(registration),
+                None => {
+                    if !uncloned_components.contains(&type_info.id()) {
+                        uncloned_components.push(type_info.id());
+                    }
+                    false
+                }
+            },
+            |source, destination| copy_column(&component_registry, resources, source, destination),
+        );
+
+        WorldSnapshot {
+            components,
+            uncloned_components,
+        }
+    }
+
+    /// The components found in the world that couldn't be saved because their type isn't in the
+    /// [ComponentRegistry]. They are lost when the snapshot is restored.
+    pub fn uncloned_components(&self) -> &[ComponentId] {
+        &self.uncloned_components
+    }
+
+    /// Despawns all entities in `world` and replaces them with the ones in the snapshot. The
+    /// snapshot can be restored any number of times.
+    ///
+    /// The remove hooks of the despawned components run before they are despawned, and the insert
+    /// hooks of the restored components run once all entities are back. Nothing changes if a
+    /// component type of the snapshot was unregistered since it was taken.
+    pub fn restore(
+        &self,
+        world: &mut World,
+        resources: &Resources,
+    ) -> Result<(), WorldSnapshotError> {
+        let type_registry = resources.get::<TypeRegistry>().unwrap();
+        let component_registry = type_registry.component.read();
+        if let Some(type_info) = self
+            .components
+            .types()
+            .find(|type_info| registration(&component_registry, type_info).is_none())
+        {
+            return Err(WorldSnapshotError::UnregisteredComponent { id: type_info.id() });
+        }
+
+        world.restore_components(&self.components, |source, destination| {
+            copy_column(&component_registry, resources, source, destination)
+        });
+        Ok(())
+    }
+}
+
+fn registration<'a>(
+    component_registry: &'a ComponentRegistry,
+    type_info: &TypeInfo,
+) -> Option<&'a ComponentRegistration> {
+    type_info
+        .type_id()
+        .and_then(|type_id| component_registry.get(&type_id))
+}
+
+fn copy_column(
+    component_registry: &ComponentRegistry,
+    resources: &Resources,
+    source: &Column,
+    destination: &mut ColumnWriter,
+) {
+    // only registered types are in the snapshot
+    registration(component_registry, &source.type_info())
+        .unwrap()
+        .copy_column(source, destination, resources);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorldSnapshot;
+    use bevy_ecs::{ComponentId, Resources, StorageType, World};
+    use bevy_property::Properties;
+    use bevy_type_registry::{ComponentRegistration, TypeRegistry};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(Properties, Default, Clone, Debug, PartialEq)]
+    struct Position {
+        x: f32,
+    }
+
+    #[derive(Properties, Default, Debug, PartialEq)]
+    struct Health {
+        value: u32,
+    }
+
+    #[derive(Properties, Default, Clone, Debug, PartialEq)]
+    struct Sparse {
+        value: u32,
+    }
+
+    struct Unregistered;
+
+    #[test]
+    fn snapshot_restores_components_and_entities() {
+        let type_registry = TypeRegistry::default();
+        {
+            let mut component_registry = type_registry.component.write();
+            component_registry.add_registration(
+                ComponentRegistration::build::<Position>()
+                    .cloneable()
+                    .finish(),
+            );
+            component_registry.register::<Health>();
+            component_registry.add_registration(
+                ComponentRegistration::build::<Sparse>()
+                    .cloneable()
+                    .finish(),
+            );
+        }
+        let mut resources = Resources::default();
+        resources.insert(type_registry);
+
+        let mut world = World::new();
+        world.register_component::<Sparse>(StorageType::SparseSet);
+        let removed = Arc::new(AtomicUsize::new(0));
+        let removed_in_hook = removed.clone();
+        world.on_remove::<Health>(move |_, _| {
+            removed_in_hook.fetch_add(1, Ordering::Relaxed);
+        });
+        let a = world.spawn((Position { x: 1.0 }, Health { value: 10 }));
+        let b = world.spawn((Position { x: 2.0 }, Sparse { value: 3 }, Unregistered));
+        let snapshot = WorldSnapshot::from_world(&world, &resources);
+        assert_eq!(snapshot.uncloned_components().len(), 1);
+
+        world.get_mut::<Position>(a).unwrap().x = 5.0;
+        world.get_mut::<Health>(a).unwrap().value = 0;
+        world.despawn(b).unwrap();
+        let c = world.spawn((Position { x: 3.0 },));
+
+        snapshot.restore(&mut world, &resources).unwrap();
+        assert_eq!(removed.load(Ordering::Relaxed), 1);
+        assert!(!world.contains(c));
+        assert_eq!(*world.get::<Position>(a).unwrap(), Position { x: 1.0 });
+        assert_eq!(*world.get::<Health>(a).unwrap(), Health { value: 10 });
+        assert_eq!(*world.get::<Position>(b).unwrap(), Position { x: 2.0 });
+        assert_eq!(*world.get::<Sparse>(b).unwrap(), Sparse { value: 3 });
+        assert!(world.get::<Unregistered>(b).is_err());
+        assert!(world
+            .sparse_sets()
+            .get(ComponentId::of::<Sparse>())
+            .unwrap()
+            .contains(b));
+
+        let mut unregistered_resources = Resources::default();
+        unregistered_resources.insert(TypeRegistry::default());
+        assert!(snapshot
+            .restore(&mut world, &unregistered_resources)
+            .is_err());
+        assert_eq!(*world.get::<Health>(a).unwrap(), Health { value: 10 });
+    }
+}
@@ -66,11 +66,13 @@ impl RegisterType for AppBuilder {
         {
             let mut builder = ComponentRegistration::build::<T>();
             builder = build(builder);
+            let registration = builder.finish();
+            registration.register_map_entities(&mut self.app.world);
             let type_registry = self.app.resources.get::<TypeRegistry>().unwrap();
             type_registry
                 .component
                 .write()
-                .add_registration(builder.finish());
+                .add_registration(registration);
             type_registry.property.write().register::<T>();
         }
         self
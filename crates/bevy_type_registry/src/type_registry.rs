@@ -1,6 +1,6 @@
 use bevy_ecs::{
-    Archetype, Component, Entity, EntityMap, FromResources, MapEntities, MapEntitiesError,
-    Resources, World,
+    Archetype, Column, ColumnWriter, Component, Entity, EntityMap, FromResources, MapEntities,
+    MapEntitiesError, Resources, World,
 };
 use bevy_property::{
     DeserializeProperty, Properties, Property, PropertyTypeRegistration, PropertyTypeRegistry,
@@ -86,9 +86,11 @@ pub struct ComponentRegistration {
     component_apply_fn: fn(&mut World, Entity, &dyn Property),
     component_properties_fn: fn(&Archetype, usize) -> &dyn Properties,
     component_copy_fn: fn(&World, &mut World, &Resources, Entity, Entity),
+    copy_column_fn: fn(&Column, &mut ColumnWriter, &Resources),
     copy_to_scene_fn: fn(&World, &mut World, &Resources, Entity, Entity),
     copy_from_scene_fn: fn(&World, &mut World, &Resources, Entity, Entity),
     map_entities_fn: fn(&mut World, &EntityMap) -> Result<(), MapEntitiesError>,
+    register_map_entities_fn: fn(&mut World),
 }
 
 struct ComponentRegistrationDefaults;
@@ -129,6 +131,18 @@ impl ComponentRegistrationDefaults {
             .unwrap();
     }
 
+    fn copy_column<T: Component + Properties + FromResources>(
+        source: &Column,
+        destination: &mut ColumnWriter,
+        resources: &Resources,
+    ) {
+        for source_component in source.get::<T>().unwrap() {
+            let mut component = T::from_resources(resources);
+            component.apply(source_component);
+            destination.push(component);
+        }
+    }
+
     fn component_properties<T: Component + Properties>(
         archetype: &Archetype,
         index: usize,
@@ -143,6 +157,8 @@ impl ComponentRegistrationDefaults {
     fn map_entities(_world: &mut World, _entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
         Ok(())
     }
+
+    fn register_map_entities(_world: &mut World) {}
 }
 
 impl ComponentRegistration {
@@ -163,10 +179,12 @@ impl ComponentRegistration {
             component_add_fn: ComponentRegistrationDefaults::component_add::<T>,
             component_apply_fn: ComponentRegistrationDefaults::component_apply::<T>,
             component_copy_fn: ComponentRegistrationDefaults::component_copy::<T>,
+            copy_column_fn: ComponentRegistrationDefaults::copy_column::<T>,
             component_properties_fn: ComponentRegistrationDefaults::component_properties::<T>,
             copy_from_scene_fn: ComponentRegistrationDefaults::component_copy::<T>,
             copy_to_scene_fn: ComponentRegistrationDefaults::component_copy::<T>,
             map_entities_fn: ComponentRegistrationDefaults::map_entities,
+            register_map_entities_fn: ComponentRegistrationDefaults::register_map_entities,
             short_name: PropertyTypeRegistration::get_short_name(std::any::type_name::<T>()),
             long_name: std::any::type_name::<T>(),
         }
@@ -216,6 +234,17 @@ impl ComponentRegistration {
         );
     }
 
+    /// Pushes a copy of each component of `source`, which must be of this registration's type, to
+    /// `destination`
+    pub fn copy_column(
+        &self,
+        source: &Column,
+        destination: &mut ColumnWriter,
+        resources: &Resources,
+    ) {
+        (self.copy_column_fn)(source, destination, resources);
+    }
+
     pub fn copy_from_scene(
         &self,
         scene_world: &World,
@@ -257,6 +286,12 @@ impl ComponentRegistration {
     ) -> Result<(), MapEntitiesError> {
         (self.map_entities_fn)(world, entity_map)
     }
+
+    /// Lets `world` remap the entities referenced by this component when entities are moved into it
+    /// with [World::merge] or [World::move_entities_from], if the component maps entities
+    pub fn register_map_entities(&self, world: &mut World) {
+        (self.register_map_entities_fn)(world);
+    }
 }
 
 pub struct ComponentRegistrationBuilder<T> {
@@ -280,6 +315,24 @@ where
 
             Ok(())
         };
+        self.registration.register_map_entities_fn = |world: &mut World| {
+            world.register_map_entities::<T>();
+        };
+        self
+    }
+
+    /// Copies the component with [Clone] instead of through its properties in
+    /// [ComponentRegistration::copy_column], which is much faster
+    pub fn cloneable(mut self) -> Self
+    where
+        T: Clone,
+    {
+        self.registration.copy_column_fn =
+            |source: &Column, destination: &mut ColumnWriter, _resources: &Resources| {
+                for component in source.get::<T>().unwrap() {
+                    destination.push(component.clone());
+                }
+            };
         self
     }
 
@@ -26,8 +26,7 @@ fn move_cubes(
     for (mut transform, material_handle) in &mut query.iter() {
         let material = materials.get_mut(material_handle).unwrap();
         transform.translation += Vec3::new(1.0, 0.0, 0.0) * time.delta_seconds;
-        material.albedo =
-            Color::BLUE * Vec3::splat((3.0 * time.seconds_since_startup as f32).sin());
+        material.albedo = Color::BLUE * Vec3::splat((3.0 * time.elapsed_seconds as f32).sin());
     }
 }
 
@@ -35,8 +35,8 @@ fn event_trigger_system(
     mut state: ResMut<EventTriggerState>,
     mut my_events: ResMut<Events<MyEvent>>,
 ) {
-    state.event_timer.tick(time.delta_seconds);
-    if state.event_timer.finished {
+    state.event_timer.tick(time.delta);
+    if state.event_timer.finished() {
         my_events.send(MyEvent {
             message: "MyEvent just happened!".to_string(),
         });
@@ -106,7 +106,7 @@ fn rotate(
         }
 
         // To demonstrate removing children, we'll start to remove the children after a couple of seconds
-        if time.seconds_since_startup >= 2.0 && children.len() == 3 {
+        if time.elapsed_seconds >= 2.0 && children.len() == 3 {
             // Using .despawn() on an entity does not remove it from its parent's list of children!
             // It must be done manually if using .despawn()
             // NOTE: This is a bug. Eventually Bevy will update the children list automatically
@@ -114,7 +114,7 @@ fn rotate(
             commands.despawn(child);
         }
 
-        if time.seconds_since_startup >= 4.0 {
+        if time.elapsed_seconds >= 4.0 {
             // Alternatively, you can use .despawn_recursive()
             // This will remove the entity from its parent's list of children, as well as despawn
             // any children the entity has.
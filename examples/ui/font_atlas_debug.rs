@@ -63,9 +63,9 @@ fn atlas_render_system(
 
 fn text_update_system(mut state: ResMut<State>, time: Res<Time>, mut query: Query<&mut Text>) {
     for mut text in &mut query.iter() {
-        state.timer.tick(time.delta_seconds);
+        state.timer.tick(time.delta);
         let c = rand::random::<u8>() as char;
-        if !text.value.contains(c) && state.timer.finished {
+        if !text.value.contains(c) && state.timer.finished() {
             text.value = format!("{}{}", text.value, c);
             state.timer.reset();
         }